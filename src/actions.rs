@@ -3,7 +3,7 @@ use std::fmt;
 use bevy::prelude::*;
 use bevy_egui::*;
 use bevy_input_actionmap::*;
-use crate::{editor::{EditorAction, EditorCameraAction}, pong::PongAction, state::StateAction, tanks::camera::CameraActions};
+use crate::{editor::{EditorAction, EditorCameraAction}, pong::PongAction, state::StateAction, tanks::{actions::TankAction, camera::CameraActions, save::SaveAction}};
 
 // Since I am using action maps I wanted a to display what actions are currently possible
 pub struct ActionsPlugin;
@@ -33,6 +33,8 @@ pub fn draw_actions(
     editor_map: Res<InputMap<EditorAction>>,
     editor_camera_map: Res<InputMap<EditorCameraAction>>,
     camera_map: Res<InputMap<CameraActions>>,
+    tank_map: Res<InputMap<TankAction>>,
+    save_map: Res<InputMap<SaveAction>>,
     mut window: ResMut<ActionsWindow>
 ) {
     egui::Window::new("Key Bindings")
@@ -43,6 +45,8 @@ pub fn draw_actions(
          dispaly_input_map::<EditorAction>(&editor_map, ui);
          dispaly_input_map::<EditorCameraAction>(&editor_camera_map, ui);
          dispaly_input_map::<CameraActions>(&camera_map, ui);
+         dispaly_input_map::<TankAction>(&tank_map, ui);
+         dispaly_input_map::<SaveAction>(&save_map, ui);
     });
 }
 
@@ -0,0 +1,427 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::{asset::LoadState, prelude::*, render::camera::Camera};
+use bevy_egui::{
+    egui::{CollapsingHeader, Grid, Sense, Window},
+    EguiContext,
+};
+use bevy_kira_audio::{Audio, AudioChannel, AudioSource};
+use bevy_prototype_debug_lines::DebugLines;
+
+use crate::GameStages;
+
+use super::{
+    cursor_on_ground, draw_snap_target, EditorCamera, EditorCommand, EditorState, EditorViewports,
+    GridData, RegisterSceneComponent, SceneMesh,
+};
+
+/// Lists everything under `assets/`, plays audio in place and drops glTF models and basic
+/// shapes into the viewport, on the ground under the cursor and snapped to the editor grid when
+/// snapping is on
+pub struct AssetBrowserPlugin;
+
+impl Plugin for AssetBrowserPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(AssetBrowser::default())
+            .register_scene_component::<EditorModel>()
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing).with_system(draw_asset_browser.system()),
+            )
+            // after the hierarchy is updated, so models brought back by undo already have
+            // their children
+            .add_system_to_stage(CoreStage::Last, spawn_models.system());
+    }
+}
+
+/// Entity showing a glTF model placed from the asset browser, the model's scene is spawned
+/// under it
+#[derive(Default, Reflect)]
+#[reflect(Component)]
+pub struct EditorModel {
+    /// Relative to the assets folder
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssetKind {
+    Font,
+    Audio,
+    Model,
+    Texture,
+    Ron,
+    Other,
+}
+
+impl AssetKind {
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "ttf" | "otf" => AssetKind::Font,
+            "wav" | "mp3" | "ogg" | "flac" => AssetKind::Audio,
+            "glb" | "gltf" => AssetKind::Model,
+            "png" | "jpg" | "jpeg" => AssetKind::Texture,
+            "ron" => AssetKind::Ron,
+            _ => AssetKind::Other,
+        }
+    }
+}
+
+pub struct AssetEntry {
+    /// Relative to the assets folder, always with forward slashes
+    pub path: String,
+    pub kind: AssetKind,
+    pub size: u64,
+}
+
+pub struct AssetBrowser {
+    pub show: bool,
+    entries: Vec<AssetEntry>,
+    /// Assets loaded from the browser, kept so they stay loaded
+    handles: HashMap<String, HandleUntyped>,
+    channel: AudioChannel,
+    playing: Option<String>,
+    dragging: Option<Placing>,
+}
+
+/// What is being dragged into the viewport
+#[derive(Debug, Clone, PartialEq)]
+enum Placing {
+    /// Path to a glTF file
+    Model(String),
+    Shape(SceneMesh),
+}
+
+impl Placing {
+    fn name(&self) -> &str {
+        match self {
+            Placing::Model(path) => file_name(path),
+            Placing::Shape(shape) => shape.name(),
+        }
+    }
+
+    fn component(self) -> Box<dyn Reflect> {
+        match self {
+            Placing::Model(path) => Box::new(EditorModel { path }),
+            Placing::Shape(shape) => Box::new(shape),
+        }
+    }
+}
+
+impl Default for AssetBrowser {
+    fn default() -> Self {
+        AssetBrowser {
+            show: false,
+            entries: Vec::new(),
+            handles: HashMap::new(),
+            channel: AudioChannel::new("editor_preview".to_string()),
+            playing: None,
+            dragging: None,
+        }
+    }
+}
+
+/// Same place the asset server looks
+fn assets_root() -> PathBuf {
+    let base = std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|_| {
+            std::env::current_exe().map(|exe| exe.parent().map(Path::to_path_buf).unwrap_or(exe))
+        })
+        .unwrap_or_default();
+    base.join("assets")
+}
+
+/// Every file under a folder, sorted by path
+pub fn scan_assets(root: &Path) -> Vec<AssetEntry> {
+    fn walk(root: &Path, dir: &Path, entries: &mut Vec<AssetEntry>) {
+        let read = match std::fs::read_dir(dir) {
+            Ok(read) => read,
+            Err(_) => return,
+        };
+        for entry in read.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                walk(root, &path, entries);
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                let relative = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                entries.push(AssetEntry {
+                    kind: AssetKind::from_path(&relative),
+                    path: relative,
+                    size: metadata.len(),
+                });
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    walk(root, root, &mut entries);
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Folder an asset is in, "" for the top
+fn folder_of(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(folder, _)| folder)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+fn load_state_text(state: LoadState) -> &'static str {
+    match state {
+        LoadState::NotLoaded => "-",
+        LoadState::Loading => "Loading",
+        LoadState::Loaded => "Loaded",
+        LoadState::Failed => "Failed",
+    }
+}
+
+fn draw_asset_browser(
+    egui_ctx: Res<EguiContext>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    windows: Res<Windows>,
+    grid: Res<GridData>,
+    mut browser: ResMut<AssetBrowser>,
+    mut lines: ResMut<DebugLines>,
+    viewports: Res<EditorViewports>,
+    mut ev_command: EventWriter<EditorCommand>,
+    cameras: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+) {
+    if !browser.show {
+        return;
+    }
+    if browser.entries.is_empty() {
+        browser.entries = scan_assets(&assets_root());
+    }
+
+    let mut show = browser.show;
+    let browser = &mut *browser;
+    Window::new("Assets")
+        .open(&mut show)
+        .scroll(true)
+        .show(egui_ctx.ctx(), |ui| {
+            if ui.button("Refresh").clicked() {
+                browser.entries = scan_assets(&assets_root());
+            }
+
+            let mut folders: Vec<String> = browser
+                .entries
+                .iter()
+                .map(|entry| folder_of(&entry.path).to_string())
+                .collect();
+            folders.sort();
+            folders.dedup();
+
+            for folder in folders.iter().map(String::as_str) {
+                let title = if folder.is_empty() { "assets" } else { folder };
+                CollapsingHeader::new(title)
+                    .id_source(folder)
+                    .show(ui, |ui| {
+                        Grid::new(folder).striped(true).show(ui, |ui| {
+                            for entry in browser
+                                .entries
+                                .iter()
+                                .filter(|entry| folder_of(&entry.path) == folder)
+                            {
+                                let name = ui.label(file_name(&entry.path)).interact(Sense::drag());
+                                if entry.kind == AssetKind::Model && name.drag_started() {
+                                    browser.dragging = Some(Placing::Model(entry.path.clone()));
+                                }
+                                ui.label(format!("{:?}", entry.kind));
+                                ui.label(format_size(entry.size));
+                                ui.label(load_state_text(
+                                    asset_server.get_load_state(entry.path.as_str()),
+                                ));
+
+                                match entry.kind {
+                                    AssetKind::Audio => {
+                                        if browser.playing.as_ref() == Some(&entry.path) {
+                                            if ui.small_button("Stop").clicked() {
+                                                audio.stop_channel(&browser.channel);
+                                                browser.playing = None;
+                                            }
+                                        } else if ui.small_button("Play").clicked() {
+                                            let handle: Handle<AudioSource> =
+                                                asset_server.load(entry.path.as_str());
+                                            audio.stop_channel(&browser.channel);
+                                            audio.play_in_channel(handle.clone(), &browser.channel);
+                                            browser
+                                                .handles
+                                                .insert(entry.path.clone(), handle.clone_untyped());
+                                            browser.playing = Some(entry.path.clone());
+                                        }
+                                    }
+                                    AssetKind::Model => {
+                                        ui.label("drag to place");
+                                    }
+                                    _ => {
+                                        if !browser.handles.contains_key(&entry.path)
+                                            && ui.small_button("Load").clicked()
+                                        {
+                                            browser.handles.insert(
+                                                entry.path.clone(),
+                                                asset_server.load_untyped(entry.path.as_str()),
+                                            );
+                                        }
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+            }
+
+            CollapsingHeader::new("shapes").show(ui, |ui| {
+                for shape in SceneMesh::SHAPES.iter() {
+                    let name = ui.label(shape.name()).interact(Sense::drag());
+                    if name.drag_started() {
+                        browser.dragging = Some(Placing::Shape(*shape));
+                    }
+                }
+            });
+
+            if let Some(placing) = browser.dragging.as_ref() {
+                ui.separator();
+                ui.label(format!("Placing {}", placing.name()));
+            }
+        });
+    browser.show = show;
+
+    // drop a model into the viewport, anywhere that isn't an egui window
+    let placing = match browser.dragging.clone() {
+        Some(placing) => placing,
+        None => return,
+    };
+    let over_viewport = !egui_ctx.ctx().is_pointer_over_area();
+    let ground = cursor_on_ground(&windows, &viewports, &cameras).filter(|_| over_viewport);
+    if let Some(ground) = ground.filter(|_| grid.snapping()) {
+        draw_snap_target(&mut lines, &grid, ground);
+    }
+    if !egui_ctx.ctx().input().pointer.any_released() {
+        return;
+    }
+    browser.dragging = None;
+    if let Some(ground) = ground {
+        let position = if grid.snapping() {
+            grid.snap_to_cell(ground)
+        } else {
+            ground
+        };
+        let name = placing.name().to_string();
+        ev_command.send(EditorCommand::Spawn {
+            label: format!("Place {}", name),
+            components: vec![
+                Box::new(Name::new(name)),
+                // the kit pieces and shapes are about one unit so they're scaled to a cell
+                Box::new(Transform {
+                    translation: position,
+                    scale: Vec3::splat(grid.cell_size),
+                    ..Default::default()
+                }),
+                Box::new(GlobalTransform::default()),
+                placing.component(),
+            ],
+            parent: None,
+        });
+    }
+}
+
+/// Spawn the glTF scene under each new model
+fn spawn_models(
+    asset_server: Res<AssetServer>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    models: Query<(Entity, &EditorModel, Option<&Children>), Added<EditorModel>>,
+) {
+    for (entity, model, children) in models.iter() {
+        // brought back with its scene already
+        if children.is_some() {
+            continue;
+        }
+        let scene = asset_server.load(format!("{}#Scene0", model.path).as_str());
+        scene_spawner.spawn_as_child(scene, entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_from_extensions() {
+        assert_eq!(
+            AssetKind::from_path("fonts/FiraSans-Bold.ttf"),
+            AssetKind::Font
+        );
+        assert_eq!(
+            AssetKind::from_path("audio/pong/bounce.wav"),
+            AssetKind::Audio
+        );
+        assert_eq!(
+            AssetKind::from_path("retro_medieval/wall.glb"),
+            AssetKind::Model
+        );
+        assert_eq!(
+            AssetKind::from_path("textures/bevy.PNG"),
+            AssetKind::Texture
+        );
+        assert_eq!(
+            AssetKind::from_path("dungeon_pack_iso.gen.ron"),
+            AssetKind::Ron
+        );
+        assert_eq!(
+            AssetKind::from_path("audio/pong/licenses.md"),
+            AssetKind::Other
+        );
+        assert_eq!(AssetKind::from_path("README"), AssetKind::Other);
+    }
+
+    #[test]
+    fn sizes_are_readable() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+
+    #[test]
+    fn paths_split_into_folder_and_name() {
+        assert_eq!(folder_of("retro_medieval/wall.glb"), "retro_medieval");
+        assert_eq!(folder_of("audio/pong/bounce.wav"), "audio/pong");
+        assert_eq!(folder_of("tiles.png"), "");
+        assert_eq!(file_name("audio/pong/bounce.wav"), "bounce.wav");
+        assert_eq!(file_name("tiles.png"), "tiles.png");
+    }
+}
@@ -1,27 +1,43 @@
+use anyhow::Result;
 use bevy::{
+    app::AppExit,
     input::mouse::{MouseMotion, MouseWheel},
     prelude::*,
-    render::camera::PerspectiveProjection,
+    render::{camera::PerspectiveProjection, mesh::VertexAttributeValues},
 };
+use bevy_egui::EguiContext;
 use bevy_input_actionmap::*;
 use bevy_inspector_egui::Inspectable;
 use bevy_mod_picking::PickingCameraBundle;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
 
 use crate::helpers::{cleanup_actions_system, cleanup_system};
+use crate::ConfigPath;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use super::EditorState;
+use std::path::PathBuf;
+use super::{EditorOnly, EditorSelection, EditorState, EditorViewports, GridData, SceneWindow};
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_plugin(ActionPlugin::<EditorCameraAction>::default())
+        .add_startup_system(load_camera_config.system())
         .add_system_set(
             SystemSet::on_enter(EditorState::Loading)
                 .with_system(spawn_cameras.system()),
         )
+        .add_system_set(
+            SystemSet::on_update(EditorState::Playing)
+                .with_system(focus_selected.system())
+                .with_system(run_bookmarks.system())
+                .with_system(save_on_exit.system()),
+        )
         .add_system_set(
             SystemSet::on_exit(EditorState::Playing)
+                .with_system(save_camera_config.system())
                 .with_system(cleanup_system::<EditorCamera>.system())
                 .with_system(cleanup_actions_system::<EditorCameraAction>.system())
         )
@@ -34,6 +50,15 @@ impl Plugin for CameraPlugin {
 pub enum EditorCameraAction {
     Orbit,
     Pan,
+    Focus,
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+    Bookmark(u8),
+    SetBookmark(u8),
 }
 
 impl fmt::Display for EditorCameraAction {
@@ -41,27 +66,154 @@ impl fmt::Display for EditorCameraAction {
         match self {
             EditorCameraAction::Orbit => write!(f, "Orbit Camera"),
             EditorCameraAction::Pan => write!(f, "Pan Camera"),
+            EditorCameraAction::Focus => write!(f, "Focus Selected"),
+            EditorCameraAction::Forward => write!(f, "Fly - Forward"),
+            EditorCameraAction::Back => write!(f, "Fly - Back"),
+            EditorCameraAction::Left => write!(f, "Fly - Left"),
+            EditorCameraAction::Right => write!(f, "Fly - Right"),
+            EditorCameraAction::Up => write!(f, "Fly - Up"),
+            EditorCameraAction::Down => write!(f, "Fly - Down"),
+            EditorCameraAction::Bookmark(slot) => write!(f, "Camera Bookmark {}", slot),
+            EditorCameraAction::SetBookmark(slot) => write!(f, "Set Camera Bookmark {}", slot),
         }
     }
 }
 
-/// Marker component for editor game camera
-#[derive(Inspectable, Debug)]
+/// Marker component for editor cameras, one for each view
+#[derive(Inspectable, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EditorCamera {
-    UI,
     Perspective,
+    Top,
+    Front,
+    Side,
+}
+
+/// Where the camera is looking from, enough to put it back exactly
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub focus: Vec3,
+    pub rotation: Quat,
+    pub radius: f32,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        let location = Vec3::new(100.0, 100.0, 600.0);
+        CameraView {
+            focus: Vec3::ZERO,
+            rotation: Transform::from_translation(location)
+                .looking_at(Vec3::ZERO, Vec3::Y)
+                .rotation,
+            radius: location.length(),
+        }
+    }
+}
+
+impl CameraView {
+    fn of(pan_orbit: &PanOrbitCamera, transform: &Transform) -> Self {
+        CameraView {
+            focus: pan_orbit.focus,
+            rotation: transform.rotation,
+            radius: pan_orbit.radius,
+        }
+    }
+
+    fn translation(&self) -> Vec3 {
+        self.focus + self.rotation * Vec3::new(0.0, 0.0, self.radius)
+    }
+
+    fn apply(&self, pan_orbit: &mut PanOrbitCamera, transform: &mut Transform) {
+        pan_orbit.focus = self.focus;
+        pan_orbit.radius = self.radius;
+        pan_orbit.flying = false;
+        transform.rotation = self.rotation;
+        transform.translation = self.translation();
+    }
+}
+
+/// Editor camera settings kept between sessions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EditorCameraConfig {
+    pub view: CameraView,
+    pub fly_speed: f32,
+    /// By scene name, "" before a scene is saved or opened, then by number key
+    pub bookmarks: HashMap<String, BTreeMap<u8, CameraView>>,
+}
+
+impl Default for EditorCameraConfig {
+    fn default() -> Self {
+        EditorCameraConfig {
+            view: CameraView::default(),
+            fly_speed: 500.0,
+            bookmarks: HashMap::new(),
+        }
+    }
+}
+
+pub fn camera_config_path(config: &ConfigPath) -> PathBuf {
+    [
+        config.path.clone(),
+        "editor".to_string(),
+        "camera.ron".to_string(),
+    ]
+    .iter()
+    .collect()
+}
+
+pub fn write_camera_config(config: &ConfigPath, camera: &EditorCameraConfig) -> Result<()> {
+    let path = camera_config_path(config);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, to_string_pretty(camera, PrettyConfig::new())?)?;
+    Ok(())
+}
+
+pub fn read_camera_config(config: &ConfigPath) -> Result<EditorCameraConfig> {
+    let text = std::fs::read_to_string(camera_config_path(config))?;
+    Ok(ron::de::from_str(&text)?)
+}
+
+fn load_camera_config(mut commands: Commands, config: Res<ConfigPath>) {
+    let camera = match read_camera_config(&config) {
+        Ok(camera) => camera,
+        Err(e) => {
+            println!("Failed to load editor camera: {}", e);
+            EditorCameraConfig::default()
+        }
+    };
+    commands.insert_resource(camera);
 }
 
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
 /// Spawn a camera like this
 #[allow(dead_code)]
-pub fn spawn_cameras(mut commands: Commands, mut input_map: ResMut<InputMap<EditorCameraAction>>) {
+pub fn spawn_cameras(
+    mut commands: Commands,
+    mut input_map: ResMut<InputMap<EditorCameraAction>>,
+    camera_config: Res<EditorCameraConfig>,
+) {
 
-    let location = Vec3::new(100.0, 100.0, 600.0);
-    let radius = location.length();
+    let view = camera_config.view;
 
     commands
         .spawn_bundle(PerspectiveCameraBundle {
-            transform: Transform::from_translation(location).looking_at(Vec3::ZERO, Vec3::Y),
+            transform: Transform {
+                translation: view.translation(),
+                rotation: view.rotation,
+                ..Default::default()
+            },
             perspective_projection: PerspectiveProjection {
                 far: f32::MAX,
                 ..Default::default()
@@ -69,16 +221,32 @@ pub fn spawn_cameras(mut commands: Commands, mut input_map: ResMut<InputMap<Edit
             ..Default::default()
         })
         .insert(PanOrbitCamera {
-            radius,
+            focus: view.focus,
+            radius: view.radius,
+            fly_speed: camera_config.fly_speed,
             ..Default::default()
         })
         .insert_bundle(PickingCameraBundle::default())
         .insert(EditorCamera::Perspective)
+        .insert(EditorOnly)
         .insert(Name::new("Editor 3d Camera"));
 
         input_map
             .bind(EditorCameraAction::Orbit, MouseButton::Right)
-            .bind(EditorCameraAction::Pan, MouseButton::Middle);
+            .bind(EditorCameraAction::Pan, MouseButton::Middle)
+            .bind(EditorCameraAction::Focus, KeyCode::F)
+            .bind(EditorCameraAction::Forward, KeyCode::W)
+            .bind(EditorCameraAction::Back, KeyCode::S)
+            .bind(EditorCameraAction::Left, KeyCode::A)
+            .bind(EditorCameraAction::Right, KeyCode::D)
+            .bind(EditorCameraAction::Up, KeyCode::E)
+            .bind(EditorCameraAction::Down, KeyCode::Q);
+        for (i, key) in BOOKMARK_KEYS.iter().enumerate() {
+            let slot = i as u8 + 1;
+            input_map
+                .bind(EditorCameraAction::Bookmark(slot), *key)
+                .bind(EditorCameraAction::SetBookmark(slot), vec![KeyCode::LControl, *key]);
+        }
 
 }
 
@@ -88,6 +256,10 @@ pub struct PanOrbitCamera {
     pub focus: Vec3,
     pub radius: f32,
     pub upside_down: bool,
+    /// Flying moves the camera itself instead of orbiting, the focus follows along in front
+    pub flying: bool,
+    /// World units a second, changed with the scroll wheel while flying
+    pub fly_speed: f32,
 }
 
 impl Default for PanOrbitCamera {
@@ -96,21 +268,34 @@ impl Default for PanOrbitCamera {
             focus: Vec3::ZERO,
             radius: 5.0,
             upside_down: false,
+            flying: false,
+            fly_speed: 500.0,
         }
     }
 }
 
 // This is from the bevy cheatbook
 /// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with right mouse click.
+/// Holding right click and pressing WASD (Q and E for down and up) flies instead, where the
+/// scroll wheel sets the speed
 pub fn pan_orbit_camera(
     windows: Res<Windows>,
+    time: Res<Time>,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     input_map: Res<InputMap<EditorCameraAction>>,
+    viewports: Res<EditorViewports>,
     mut query: Query<(&mut PanOrbitCamera, &mut Transform, &PerspectiveProjection)>,
 ) {
     // change input mapping for orbit and panning here
 
+    // the other views move their own cameras
+    let cursor = windows.get_primary().and_then(|w| w.cursor_position());
+    if !viewports.is_over(EditorCamera::Perspective, cursor) {
+        ev_motion.iter().last();
+        ev_scroll.iter().last();
+        return;
+    }
 
     let mut pan = Vec2::ZERO;
     let mut rotation_move = Vec2::ZERO;
@@ -134,6 +319,16 @@ pub fn pan_orbit_camera(
         orbit_button_changed = true;
     }
 
+    // camera space, forward is -z
+    let axis = |positive: EditorCameraAction, negative: EditorCameraAction| {
+        input_map.active(positive) as i32 as f32 - input_map.active(negative) as i32 as f32
+    };
+    let fly_move = Vec3::new(
+        axis(EditorCameraAction::Right, EditorCameraAction::Left),
+        axis(EditorCameraAction::Up, EditorCameraAction::Down),
+        axis(EditorCameraAction::Back, EditorCameraAction::Forward),
+    );
+
     for (mut pan_orbit, mut transform, projection) in query.iter_mut() {
         if orbit_button_changed {
             // only check for upside down when orbiting started or ended this frame
@@ -142,23 +337,37 @@ pub fn pan_orbit_camera(
             pan_orbit.upside_down = up.y <= 0.0;
         }
 
+        // keeps flying until right click is let go, even if the keys are
+        if !input_map.active(EditorCameraAction::Orbit) {
+            pan_orbit.flying = false;
+        } else if fly_move != Vec3::ZERO {
+            pan_orbit.flying = true;
+        }
+
+        if pan_orbit.flying {
+            if rotation_move.length_squared() > 0.0 {
+                let window = get_primary_window_size(&windows);
+                look(&mut transform.rotation, rotation_move, window, pan_orbit.upside_down);
+            }
+            if scroll.abs() > 0.0 {
+                pan_orbit.fly_speed = (pan_orbit.fly_speed * 1.2f32.powf(scroll)).clamp(1.0, 100000.0);
+            }
+            if fly_move != Vec3::ZERO {
+                let velocity = transform.rotation * fly_move.normalize() * pan_orbit.fly_speed;
+                transform.translation += velocity * time.delta_seconds();
+            }
+            // the camera stays put and the focus moves, so orbiting carries on from here
+            let rot_matrix = Mat3::from_quat(transform.rotation);
+            pan_orbit.focus =
+                transform.translation - rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+            continue;
+        }
+
         let mut any = false;
         if rotation_move.length_squared() > 0.0 {
             any = true;
             let window = get_primary_window_size(&windows);
-            let delta_x = {
-                let delta = rotation_move.x / window.x * std::f32::consts::PI * 2.0;
-                if pan_orbit.upside_down {
-                    -delta
-                } else {
-                    delta
-                }
-            };
-            let delta_y = rotation_move.y / window.y * std::f32::consts::PI;
-            let yaw = Quat::from_rotation_y(-delta_x);
-            let pitch = Quat::from_rotation_x(-delta_y);
-            transform.rotation = yaw * transform.rotation; // rotate around global y axis
-            transform.rotation = transform.rotation * pitch; // rotate around local x axis
+            look(&mut transform.rotation, rotation_move, window, pan_orbit.upside_down);
         } else if pan.length_squared() > 0.0 {
             any = true;
             // make panning distance independent of resolution and FOV,
@@ -188,8 +397,197 @@ pub fn pan_orbit_camera(
     }
 }
 
+/// Turn for a mouse movement, a full window width is a full turn
+fn look(rotation: &mut Quat, delta: Vec2, window: Vec2, upside_down: bool) {
+    let delta_x = {
+        let delta = delta.x / window.x * std::f32::consts::PI * 2.0;
+        if upside_down {
+            -delta
+        } else {
+            delta
+        }
+    };
+    let delta_y = delta.y / window.y * std::f32::consts::PI;
+    let yaw = Quat::from_rotation_y(-delta_x);
+    let pitch = Quat::from_rotation_x(-delta_y);
+    *rotation = yaw * *rotation; // rotate around global y axis
+    *rotation = *rotation * pitch; // rotate around local x axis
+}
+
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
     let window = Vec2::new(window.width() as f32, window.height() as f32);
     window
 }
+
+/// World space box around the meshes of an entity and everything under it. Anything without a
+/// mesh counts as a point
+fn bounds(
+    entity: Entity,
+    meshes: &Assets<Mesh>,
+    targets: &Query<(&GlobalTransform, Option<&Handle<Mesh>>, Option<&Children>)>,
+    min: &mut Vec3,
+    max: &mut Vec3,
+) {
+    let (global, mesh, children) = match targets.get(entity) {
+        Ok(target) => target,
+        Err(_) => return,
+    };
+    let positions = mesh
+        .and_then(|mesh| meshes.get(mesh))
+        .and_then(|mesh| match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => Some(positions),
+            _ => None,
+        });
+    match positions {
+        Some(positions) => {
+            let matrix = global.compute_matrix();
+            for position in positions.iter() {
+                let position = matrix.transform_point3(Vec3::from(*position));
+                *min = min.min(position);
+                *max = max.max(position);
+            }
+        }
+        None => {
+            *min = min.min(global.translation);
+            *max = max.max(global.translation);
+        }
+    }
+    for child in children.iter().flat_map(|children| children.iter()) {
+        bounds(*child, meshes, targets, min, max);
+    }
+}
+
+/// How far back a camera has to be to fit a sphere this size in view
+pub fn framing_distance(radius: f32, fov: f32) -> f32 {
+    radius / (fov * 0.5).sin()
+}
+
+fn focus_selected(
+    egui_ctx: Res<EguiContext>,
+    input_map: Res<InputMap<EditorCameraAction>>,
+    selection: Res<EditorSelection>,
+    grid: Res<GridData>,
+    meshes: Res<Assets<Mesh>>,
+    targets: Query<(&GlobalTransform, Option<&Handle<Mesh>>, Option<&Children>)>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform, &PerspectiveProjection)>,
+) {
+    // typing an f into a text box shouldn't move the camera
+    if !input_map.just_active(EditorCameraAction::Focus) || egui_ctx.ctx().wants_keyboard_input() {
+        return;
+    }
+    let entity = match selection.entity {
+        Some(entity) => entity,
+        None => return,
+    };
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    bounds(entity, &meshes, &targets, &mut min, &mut max);
+    if min.x > max.x {
+        return;
+    }
+    // small things are framed as though they were a cell, so the camera doesn't end up inside
+    let radius = ((max - min).length() * 0.5).max(grid.cell_size * 0.5);
+
+    for (mut pan_orbit, mut transform, projection) in cameras.iter_mut() {
+        let view = CameraView {
+            focus: (min + max) * 0.5,
+            rotation: transform.rotation,
+            radius: framing_distance(radius, projection.fov),
+        };
+        view.apply(&mut pan_orbit, &mut transform);
+    }
+}
+
+/// Number keys go back to a bookmark, ctrl and a number key sets one. Bookmarks belong to the
+/// scene that was last saved or opened
+fn run_bookmarks(
+    egui_ctx: Res<EguiContext>,
+    input_map: Res<InputMap<EditorCameraAction>>,
+    config: Res<ConfigPath>,
+    scene_window: Res<SceneWindow>,
+    mut camera_config: ResMut<EditorCameraConfig>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    if egui_ctx.ctx().wants_keyboard_input() {
+        return;
+    }
+    let scene = scene_window.current.clone().unwrap_or_default();
+    for slot in 1..=BOOKMARK_KEYS.len() as u8 {
+        for (mut pan_orbit, mut transform) in cameras.iter_mut() {
+            // ctrl is held as part of setting, so setting wins
+            if input_map.just_active(EditorCameraAction::SetBookmark(slot)) {
+                let view = CameraView::of(&pan_orbit, &transform);
+                camera_config
+                    .bookmarks
+                    .entry(scene.clone())
+                    .or_default()
+                    .insert(slot, view);
+                camera_config.view = view;
+                camera_config.fly_speed = pan_orbit.fly_speed;
+                if let Err(e) = write_camera_config(&config, &camera_config) {
+                    println!("Failed to save editor camera: {}", e);
+                }
+            } else if input_map.just_active(EditorCameraAction::Bookmark(slot)) {
+                let view = camera_config
+                    .bookmarks
+                    .get(&scene)
+                    .and_then(|bookmarks| bookmarks.get(&slot));
+                if let Some(view) = view {
+                    view.apply(&mut pan_orbit, &mut transform);
+                }
+            }
+        }
+    }
+}
+
+fn save_camera_config(
+    config: Res<ConfigPath>,
+    mut camera_config: ResMut<EditorCameraConfig>,
+    cameras: Query<(&PanOrbitCamera, &Transform)>,
+) {
+    if let Some((pan_orbit, transform)) = cameras.iter().next() {
+        camera_config.view = CameraView::of(pan_orbit, transform);
+        camera_config.fly_speed = pan_orbit.fly_speed;
+    }
+    if let Err(e) = write_camera_config(&config, &camera_config) {
+        println!("Failed to save editor camera: {}", e);
+    }
+}
+
+/// Quitting with the editor open never leaves the editor state, so save on the way out too
+fn save_on_exit(
+    mut ev_exit: EventReader<AppExit>,
+    config: Res<ConfigPath>,
+    camera_config: ResMut<EditorCameraConfig>,
+    cameras: Query<(&PanOrbitCamera, &Transform)>,
+) {
+    if ev_exit.iter().next().is_some() {
+        save_camera_config(config, camera_config, cameras);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing_fits_the_whole_sphere() {
+        let fov = std::f32::consts::FRAC_PI_2;
+        let distance = framing_distance(10.0, fov);
+        // the edge of the view just touches the sphere
+        assert!((distance * (fov * 0.5).sin() - 10.0).abs() < 0.001);
+        assert!(framing_distance(20.0, fov) > distance);
+    }
+
+    #[test]
+    fn views_put_the_camera_back() {
+        let view = CameraView::default();
+        assert!((view.translation() - Vec3::new(100.0, 100.0, 600.0)).length() < 0.01);
+
+        let mut pan_orbit = PanOrbitCamera::default();
+        let mut transform = Transform::default();
+        view.apply(&mut pan_orbit, &mut transform);
+        assert_eq!(CameraView::of(&pan_orbit, &transform), view);
+    }
+}
@@ -0,0 +1,844 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use bevy::{ecs::component::Component, prelude::*, reflect::Struct};
+use bevy_egui::{
+    egui::{Align, Color32, Key, ScrollArea, TextEdit, TopBottomPanel},
+    EguiContext,
+};
+use bevy_inspector_egui::{plugin::InspectorWindows, WorldInspectorParams};
+use strum::IntoEnumIterator;
+
+use crate::{resource_names, GameState};
+
+use super::{is_editor_only, EditorCommand, EditorState};
+
+/// Drop-down console for typing editor commands, opened with the backtick key. Commands come
+/// from `register_console_command`, so any plugin can add its own, and resources registered
+/// with `register_console_resource` can be read, set and inspected by name
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(ConsoleWindow::default())
+            .register_console_command(
+                ConsoleCommand::new("help [command]", "List commands, or show one", help)
+                    .with_completion(complete_help),
+            )
+            .register_console_command(ConsoleCommand::new("clear", "Clear the output", clear))
+            .register_console_command(
+                ConsoleCommand::new("state <name>", "Switch game state", set_state)
+                    .with_completion(complete_state),
+            )
+            .register_console_command(ConsoleCommand::new(
+                "spawn <name>",
+                "Create an empty entity",
+                spawn,
+            ))
+            .register_console_command(
+                ConsoleCommand::new(
+                    "despawn <name>",
+                    "Remove an entity and its children",
+                    despawn,
+                )
+                .with_completion(complete_entity),
+            )
+            .register_console_command(
+                ConsoleCommand::new("get <resource>", "Show a resource's fields", get_resource)
+                    .with_completion(complete_resource),
+            )
+            .register_console_command(
+                ConsoleCommand::new(
+                    "set <resource> <field> <value>",
+                    "Change a resource's field",
+                    set_resource,
+                )
+                .with_completion(complete_resource),
+            )
+            .register_console_command(
+                ConsoleCommand::new(
+                    "inspect <resource|world>",
+                    "Open or close an inspector window",
+                    inspect,
+                )
+                .with_completion(complete_inspect),
+            )
+            .register_console_command(ConsoleCommand::new(
+                "resources",
+                "List every resource in the world",
+                list_resources,
+            ))
+            .add_system_set(
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(draw_console.exclusive_system()),
+            );
+    }
+}
+
+/// Runs a command with the words typed after its name, what it returns is printed
+pub type RunCommand = fn(&mut World, &[String]) -> Result<String>;
+
+/// Suggestions for the next word, given the words typed after the command name so far
+pub type CompleteCommand = fn(&mut World, &[String]) -> Vec<String>;
+
+#[derive(Clone, Copy)]
+pub struct ConsoleCommand {
+    /// Name followed by its arguments, like `state <name>`
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: RunCommand,
+    pub complete: Option<CompleteCommand>,
+}
+
+impl ConsoleCommand {
+    pub fn new(usage: &'static str, help: &'static str, run: RunCommand) -> Self {
+        ConsoleCommand {
+            usage,
+            help,
+            run,
+            complete: None,
+        }
+    }
+
+    pub fn with_completion(mut self, complete: CompleteCommand) -> Self {
+        self.complete = Some(complete);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.usage.split_whitespace().next().unwrap_or_default()
+    }
+}
+
+/// A resource the console can get at by name
+#[derive(Clone, Copy)]
+struct ConsoleResource {
+    /// Field names and values as text
+    fields: fn(&World) -> Vec<(String, String)>,
+    set: fn(&mut World, &str, &str) -> Result<String>,
+    /// Opens or closes its inspector window, giving whether it is open now
+    inspect: fn(&mut World) -> Result<bool>,
+}
+
+/// Everything the console knows how to do
+#[derive(Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+    resources: BTreeMap<String, ConsoleResource>,
+}
+
+pub trait RegisterConsoleCommand {
+    /// Add a command to the editor console, replacing any with the same name
+    fn register_console_command(&mut self, command: ConsoleCommand) -> &mut Self;
+
+    /// Let the console `get`, `set` and `inspect` a resource by name. It has to derive
+    /// `Reflect` and have an `InspectorPlugin`
+    fn register_console_resource<T: Struct + Component>(&mut self, name: &str) -> &mut Self;
+}
+
+impl RegisterConsoleCommand for AppBuilder {
+    fn register_console_command(&mut self, command: ConsoleCommand) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .commands
+            .insert(command.name().to_string(), command);
+        self
+    }
+
+    fn register_console_resource<T: Struct + Component>(&mut self, name: &str) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .resources
+            .insert(
+                name.to_string(),
+                ConsoleResource {
+                    fields: resource_fields::<T>,
+                    set: set_resource_field::<T>,
+                    inspect: toggle_inspector::<T>,
+                },
+            );
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    Input,
+    Output,
+    Error,
+}
+
+struct ConsoleLine {
+    kind: LineKind,
+    text: String,
+}
+
+/// Oldest output is dropped past this many lines
+const MAX_OUTPUT: usize = 500;
+
+const OUTPUT_HEIGHT: f32 = 200.0;
+
+#[derive(Default)]
+pub struct ConsoleWindow {
+    pub open: bool,
+    input: String,
+    output: Vec<ConsoleLine>,
+    /// Lines entered, oldest first
+    history: Vec<String>,
+    /// Where the arrow keys have got to in the history, None while typing something new
+    browsing: Option<usize>,
+    /// Give the input the keyboard next time it is drawn
+    focus: bool,
+    /// Scroll down to new output next time it is drawn
+    scroll: bool,
+}
+
+impl ConsoleWindow {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focus = self.open;
+    }
+
+    fn print(&mut self, kind: LineKind, text: &str) {
+        for line in text.lines() {
+            self.output.push(ConsoleLine {
+                kind,
+                text: line.to_string(),
+            });
+        }
+        let extra = self.output.len().saturating_sub(MAX_OUTPUT);
+        self.output.drain(..extra);
+        self.scroll = true;
+    }
+
+    fn remember(&mut self, line: &str) {
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.browsing = None;
+    }
+
+    /// Step back or forward through the history into the input, forward off the end clears it
+    fn browse(&mut self, back: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        self.browsing = match (self.browsing, back) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.input = match self.browsing {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+}
+
+/// Words of a command line, double quotes keep spaces in a word
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            }
+            c => {
+                word.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        words.push(word);
+    }
+    words
+}
+
+fn quote(word: &str) -> String {
+    if word.contains(char::is_whitespace) {
+        format!("\"{}\"", word)
+    } else {
+        word.to_string()
+    }
+}
+
+/// Longest start all of them share
+fn common_prefix(candidates: &[String]) -> String {
+    let first = match candidates.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut prefix: Vec<char> = first.chars().collect();
+    for candidate in candidates.iter().skip(1) {
+        let shared = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(shared);
+    }
+    prefix.into_iter().collect()
+}
+
+/// Finish the last word of a line from the candidates for it, as far as they agree. Gives the
+/// new line and the candidates that fit, to list when there is more than one
+pub fn complete_line(
+    line: &str,
+    candidates_for: impl FnOnce(&[String]) -> Vec<String>,
+) -> (String, Vec<String>) {
+    let mut words = split_args(line);
+    let partial = if line.trim().is_empty() || line.ends_with(' ') {
+        String::new()
+    } else {
+        words.pop().unwrap_or_default()
+    };
+    let lower = partial.to_lowercase();
+    let matches: Vec<String> = candidates_for(&words)
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&lower))
+        .collect();
+
+    let completed = match matches.len() {
+        0 => return (line.to_string(), matches),
+        1 => format!("{} ", quote(&matches[0])),
+        _ => {
+            let prefix = common_prefix(&matches);
+            let prefix = if prefix.len() < partial.len() {
+                partial
+            } else {
+                prefix
+            };
+            // left open so the rest can still be typed
+            if prefix.contains(char::is_whitespace) {
+                format!("\"{}", prefix)
+            } else {
+                prefix
+            }
+        }
+    };
+    let mut line: String = words.iter().map(|word| quote(word) + " ").collect();
+    line.push_str(&completed);
+    (line, matches)
+}
+
+fn commands(world: &World) -> Option<&ConsoleCommands> {
+    world.get_resource::<ConsoleCommands>()
+}
+
+fn find_resource(world: &World, name: &str) -> Result<ConsoleResource> {
+    commands(world)
+        .and_then(|commands| commands.resources.get(name).copied())
+        .ok_or_else(|| anyhow!("No resource called {}, try get", name))
+}
+
+fn run_line(world: &mut World, line: &str) -> Result<String> {
+    let words = split_args(line);
+    let (name, args) = match words.split_first() {
+        Some(split) => split,
+        None => return Ok(String::new()),
+    };
+    let command = commands(world)
+        .and_then(|commands| commands.commands.get(name.as_str()).copied())
+        .ok_or_else(|| anyhow!("Unknown command {}, try help", name))?;
+    (command.run)(world, args)
+}
+
+fn candidates(world: &mut World, words: &[String]) -> Vec<String> {
+    match words.split_first() {
+        None => commands(world)
+            .map(|commands| commands.commands.keys().cloned().collect())
+            .unwrap_or_default(),
+        Some((name, args)) => {
+            let complete = commands(world)
+                .and_then(|commands| commands.commands.get(name.as_str()))
+                .and_then(|command| command.complete);
+            match complete {
+                Some(complete) => complete(world, args),
+                None => Vec::new(),
+            }
+        }
+    }
+}
+
+fn draw_console(world: &mut World) {
+    let mut console = std::mem::take(
+        &mut *world
+            .get_resource_mut::<ConsoleWindow>()
+            .expect("ConsoleWindow not found."),
+    );
+    if !console.open {
+        *world.get_resource_mut::<ConsoleWindow>().unwrap() = console;
+        return;
+    }
+
+    let ctx = world
+        .get_resource::<EguiContext>()
+        .expect("EguiContext not found.")
+        .ctx()
+        .clone();
+    let mut submitted = None;
+    let mut completing = false;
+
+    // drawn after the editor's top bar, so it drops down from under it
+    TopBottomPanel::top("console").show(&ctx, |ui| {
+        ScrollArea::from_max_height(OUTPUT_HEIGHT).show(ui, |ui| {
+            for line in console.output.iter() {
+                let color = match line.kind {
+                    LineKind::Input => Color32::GRAY,
+                    LineKind::Output => Color32::WHITE,
+                    LineKind::Error => Color32::RED,
+                };
+                ui.colored_label(color, &line.text);
+            }
+            if console.scroll {
+                ui.scroll_to_cursor(Align::BOTTOM);
+                console.scroll = false;
+            }
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(">");
+            let response = ui.add(
+                TextEdit::singleline(&mut console.input)
+                    .id_source("console_input")
+                    .desired_width(ui.available_width() - 100.0),
+            );
+            // the key that closes the console shouldn't end up in it
+            console.input.retain(|c| c != '`');
+
+            let input = ui.input();
+            if response.lost_focus() && input.key_pressed(Key::Enter) {
+                submitted = Some(std::mem::take(&mut console.input));
+                console.focus = true;
+            }
+            // tab would move the keyboard on to the next widget, keep it here
+            if (response.has_focus() || response.lost_focus()) && input.key_pressed(Key::Tab) {
+                completing = true;
+                console.focus = true;
+            }
+            if response.has_focus() && input.key_pressed(Key::ArrowUp) {
+                console.browse(true);
+            }
+            if response.has_focus() && input.key_pressed(Key::ArrowDown) {
+                console.browse(false);
+            }
+            if console.focus {
+                response.request_focus();
+                console.focus = false;
+            }
+
+            if ui.button("Copy").clicked() {
+                let text: Vec<&str> = console
+                    .output
+                    .iter()
+                    .map(|line| line.text.as_str())
+                    .collect();
+                ui.output().copied_text = text.join("\n");
+            }
+            if ui.button("Clear").clicked() {
+                console.output.clear();
+            }
+        });
+    });
+
+    if let Some(line) = submitted.filter(|line| !line.trim().is_empty()) {
+        console.print(LineKind::Input, &format!("> {}", line));
+        console.remember(&line);
+        // put back first, commands are free to print or clear it themselves
+        *world.get_resource_mut::<ConsoleWindow>().unwrap() = console;
+        let result = run_line(world, &line);
+        let mut console = world.get_resource_mut::<ConsoleWindow>().unwrap();
+        match result {
+            Ok(output) => console.print(LineKind::Output, &output),
+            Err(e) => console.print(LineKind::Error, &e.to_string()),
+        }
+        return;
+    }
+
+    if completing {
+        let (line, matches) = complete_line(&console.input, |words| candidates(world, words));
+        if matches.len() > 1 {
+            console.print(LineKind::Output, &matches.join("  "));
+        }
+        console.input = line;
+    }
+    *world.get_resource_mut::<ConsoleWindow>().unwrap() = console;
+}
+
+fn help(world: &mut World, args: &[String]) -> Result<String> {
+    let commands = commands(world).ok_or_else(|| anyhow!("No commands registered"))?;
+    match args.first() {
+        Some(name) => commands
+            .commands
+            .get(name.as_str())
+            .map(|command| format!("{} - {}", command.usage, command.help))
+            .ok_or_else(|| anyhow!("Unknown command {}", name)),
+        None => Ok(commands
+            .commands
+            .values()
+            .map(|command| format!("{} - {}", command.usage, command.help))
+            .collect::<Vec<_>>()
+            .join("\n")),
+    }
+}
+
+fn complete_help(world: &mut World, args: &[String]) -> Vec<String> {
+    if args.is_empty() {
+        candidates(world, &[])
+    } else {
+        Vec::new()
+    }
+}
+
+fn clear(world: &mut World, _args: &[String]) -> Result<String> {
+    if let Some(mut console) = world.get_resource_mut::<ConsoleWindow>() {
+        console.output.clear();
+    }
+    Ok(String::new())
+}
+
+/// States that can be switched to, loading only happens once at the start
+fn game_states() -> impl Iterator<Item = GameState> {
+    GameState::iter().filter(|state| *state != GameState::Loading)
+}
+
+fn set_state(world: &mut World, args: &[String]) -> Result<String> {
+    let name = args.first().ok_or_else(|| anyhow!("Which state?"))?;
+    let target = game_states()
+        .find(|state| state.to_string().eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("No state called {}", name))?;
+    let mut state = world
+        .get_resource_mut::<State<GameState>>()
+        .ok_or_else(|| anyhow!("No game state"))?;
+    // setting the current state panics further in
+    if *state.current() == target {
+        return Ok(format!("Already in {}", target));
+    }
+    state
+        .set(target.clone())
+        .map_err(|e| anyhow!("Can't switch to {}: {:?}", target, e))?;
+    Ok(format!("Switching to {}", target))
+}
+
+fn complete_state(_world: &mut World, args: &[String]) -> Vec<String> {
+    if args.is_empty() {
+        game_states().map(|state| state.to_string()).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+fn send_command(world: &mut World, command: EditorCommand) -> Result<()> {
+    world
+        .get_resource_mut::<Events<EditorCommand>>()
+        .ok_or_else(|| anyhow!("Editor commands aren't set up"))?
+        .send(command);
+    Ok(())
+}
+
+fn spawn(world: &mut World, args: &[String]) -> Result<String> {
+    let name = args
+        .first()
+        .ok_or_else(|| anyhow!("What should it be called?"))?;
+    // through the history, so it can be undone
+    send_command(
+        world,
+        EditorCommand::Spawn {
+            label: format!("Spawn {}", name),
+            components: vec![
+                Box::new(Name::new(name.clone())),
+                Box::new(Transform::default()),
+                Box::new(GlobalTransform::default()),
+            ],
+            parent: None,
+        },
+    )?;
+    Ok(format!("Spawned {}", name))
+}
+
+/// Named entities that belong to the game, not the editor
+fn named_entities(world: &mut World) -> Vec<(Entity, String)> {
+    let named: Vec<(Entity, String)> = world
+        .query::<(Entity, &Name)>()
+        .iter(world)
+        .map(|(entity, name)| (entity, name.as_str().to_string()))
+        .collect();
+    named
+        .into_iter()
+        .filter(|(entity, _)| !is_editor_only(world, *entity))
+        .collect()
+}
+
+fn despawn(world: &mut World, args: &[String]) -> Result<String> {
+    let name = args.first().ok_or_else(|| anyhow!("Which entity?"))?;
+    let matching: Vec<Entity> = named_entities(world)
+        .into_iter()
+        .filter(|(_, entity_name)| entity_name == name)
+        .map(|(entity, _)| entity)
+        .collect();
+    let entity = *matching
+        .first()
+        .ok_or_else(|| anyhow!("Nothing called {}", name))?;
+    send_command(world, EditorCommand::Despawn(entity))?;
+    Ok(match matching.len() {
+        1 => format!("Despawned {}", name),
+        n => format!("Despawned {}, {} more have that name", name, n - 1),
+    })
+}
+
+fn complete_entity(world: &mut World, args: &[String]) -> Vec<String> {
+    if !args.is_empty() {
+        return Vec::new();
+    }
+    let mut names: Vec<String> = named_entities(world)
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Text for a field, for the types the console can set
+fn value_text(value: &dyn Reflect) -> String {
+    if let Some(value) = value.downcast_ref::<f32>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<f64>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<u32>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<usize>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<i32>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<bool>() {
+        value.to_string()
+    } else if let Some(value) = value.downcast_ref::<String>() {
+        quote(value)
+    } else if let Some(value) = value.downcast_ref::<Color>() {
+        format!("{:?}", value)
+    } else {
+        format!("({})", value.type_name())
+    }
+}
+
+fn parse_into(target: &mut dyn Reflect, text: &str) -> Result<()> {
+    if let Some(value) = target.downcast_mut::<f32>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<f64>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<u32>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<usize>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<i32>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<bool>() {
+        *value = text.parse()?;
+    } else if let Some(value) = target.downcast_mut::<String>() {
+        *value = text.to_string();
+    } else {
+        return Err(anyhow!(
+            "Can't set a {} from the console",
+            target.type_name()
+        ));
+    }
+    Ok(())
+}
+
+fn resource_fields<T: Struct + Component>(world: &World) -> Vec<(String, String)> {
+    let resource = match world.get_resource::<T>() {
+        Some(resource) => resource,
+        None => return Vec::new(),
+    };
+    (0..resource.field_len())
+        .filter_map(|i| {
+            Some((
+                resource.name_at(i)?.to_string(),
+                value_text(resource.field_at(i)?),
+            ))
+        })
+        .collect()
+}
+
+fn set_resource_field<T: Struct + Component>(
+    world: &mut World,
+    field: &str,
+    text: &str,
+) -> Result<String> {
+    let mut resource = world
+        .get_resource_mut::<T>()
+        .ok_or_else(|| anyhow!("Not loaded yet"))?;
+    let target = resource
+        .field_mut(field)
+        .ok_or_else(|| anyhow!("No field called {}", field))?;
+    parse_into(target, text)?;
+    Ok(format!("{} = {}", field, value_text(target)))
+}
+
+fn toggle_inspector<T: Component>(world: &mut World) -> Result<bool> {
+    let mut windows = world
+        .get_resource_mut::<InspectorWindows>()
+        .ok_or_else(|| anyhow!("No inspector windows"))?;
+    let window = windows.window_data_mut::<T>();
+    window.visible = !window.visible;
+    Ok(window.visible)
+}
+
+fn get_resource(world: &mut World, args: &[String]) -> Result<String> {
+    let name = args.first().ok_or_else(|| anyhow!("Which resource?"))?;
+    let resource = find_resource(world, name)?;
+    Ok((resource.fields)(world)
+        .into_iter()
+        .map(|(field, value)| format!("{} = {}", field, value))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn set_resource(world: &mut World, args: &[String]) -> Result<String> {
+    let (name, field, value) = match args {
+        [name, field, value] => (name, field, value),
+        _ => return Err(anyhow!("Needs a resource, a field and a value")),
+    };
+    let resource = find_resource(world, name)?;
+    (resource.set)(world, field, value)
+}
+
+fn complete_resource(world: &mut World, args: &[String]) -> Vec<String> {
+    let resources = match commands(world) {
+        Some(commands) => &commands.resources,
+        None => return Vec::new(),
+    };
+    match args {
+        [] => resources.keys().cloned().collect(),
+        [name] => resources
+            .get(name.as_str())
+            .map(|resource| {
+                (resource.fields)(world)
+                    .into_iter()
+                    .map(|(field, _)| field)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn inspect(world: &mut World, args: &[String]) -> Result<String> {
+    let name = args.first().ok_or_else(|| anyhow!("Which resource?"))?;
+    let open = if name == "world" {
+        let mut params = world
+            .get_resource_mut::<WorldInspectorParams>()
+            .ok_or_else(|| anyhow!("No world inspector"))?;
+        params.enabled = !params.enabled;
+        params.enabled
+    } else {
+        let resource = find_resource(world, name)?;
+        (resource.inspect)(world)?
+    };
+    Ok(format!(
+        "{} inspector {}",
+        name,
+        if open { "opened" } else { "closed" }
+    ))
+}
+
+fn complete_inspect(world: &mut World, args: &[String]) -> Vec<String> {
+    if !args.is_empty() {
+        return Vec::new();
+    }
+    let mut names = complete_resource(world, args);
+    names.push("world".to_string());
+    names
+}
+
+fn list_resources(world: &mut World, _args: &[String]) -> Result<String> {
+    Ok(resource_names(world.archetypes(), world.components()).join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_keep_words_together() {
+        assert_eq!(
+            split_args("  set grid  snap false "),
+            vec!["set", "grid", "snap", "false"]
+        );
+        assert_eq!(
+            split_args("despawn \"Player 1 Camera\" x"),
+            vec!["despawn", "Player 1 Camera", "x"]
+        );
+        // an empty quoted word still counts, an unfinished one runs to the end
+        assert_eq!(split_args("spawn \"\""), vec!["spawn", ""]);
+        assert_eq!(split_args("spawn \"half done"), vec!["spawn", "half done"]);
+    }
+
+    #[test]
+    fn completes_as_far_as_candidates_agree() {
+        let names = |words: &[String]| -> Vec<String> {
+            if words.is_empty() {
+                vec!["spawn".to_string(), "set".to_string(), "state".to_string()]
+            } else {
+                vec!["Player 1".to_string(), "Player 2".to_string()]
+            }
+        };
+        assert_eq!(complete_line("sp", names).0, "spawn ");
+        let (line, matches) = complete_line("s", names);
+        assert_eq!(line, "s");
+        assert_eq!(matches.len(), 3);
+        assert_eq!(complete_line("st", names).0, "state ");
+        assert_eq!(complete_line("x", names), ("x".to_string(), Vec::new()));
+        // names with spaces are quoted, left open while there's still a choice
+        assert_eq!(complete_line("despawn p", names).0, "despawn \"Player ");
+        assert_eq!(
+            complete_line("despawn \"Player 2", names).0,
+            "despawn \"Player 2\" "
+        );
+    }
+
+    #[test]
+    fn arrows_walk_the_history() {
+        let mut console = ConsoleWindow::default();
+        console.browse(true);
+        assert_eq!(console.input, "");
+        console.remember("help");
+        console.remember("clear");
+        console.remember("clear");
+        assert_eq!(console.history.len(), 2);
+
+        console.browse(true);
+        assert_eq!(console.input, "clear");
+        console.browse(true);
+        console.browse(true);
+        assert_eq!(console.input, "help");
+        console.browse(false);
+        assert_eq!(console.input, "clear");
+        console.browse(false);
+        assert_eq!(console.input, "");
+        assert_eq!(console.browsing, None);
+    }
+
+    #[test]
+    fn commands_are_named_by_their_usage() {
+        fn run(_: &mut World, _: &[String]) -> Result<String> {
+            Ok(String::new())
+        }
+        assert_eq!(ConsoleCommand::new("set <a> <b>", "", run).name(), "set");
+        assert_eq!(ConsoleCommand::new("clear", "", run).name(), "clear");
+    }
+}
@@ -0,0 +1,529 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_egui::EguiContext;
+use bevy_mod_picking::{PickableMesh, PickingEvent};
+use bevy_prototype_debug_lines::DebugLines;
+
+use crate::GameStages;
+
+use super::{
+    draw_snap_target, mesh_bounds, ray_hits_box, EditorCamera, EditorState, EditorViewport,
+    EditorViewports, GridData,
+};
+
+/// Click an entity in any of the views to select it, then drag the handles drawn over it to
+/// move, rotate or scale it. Edits go straight to the entity's `Transform`, so the inspector shows
+/// them as they happen
+pub struct GizmoPlugin;
+
+impl Plugin for GizmoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(EditorSelection::default())
+            .insert_resource(Gizmo::default())
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(drag_gizmo.system().label("gizmo").after("viewports"))
+                    .with_system(select_entities.system().after("gizmo"))
+                    .with_system(draw_gizmo.system().after("gizmo")),
+            )
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_exit(EditorState::Playing).with_system(clear_selection.system()),
+            );
+    }
+}
+
+/// Entity the editor is working on
+#[derive(Default)]
+pub struct EditorSelection {
+    pub entity: Option<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Which way the handles point, scaling is always along the entity's own axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GizmoSpace {
+    World,
+    Local,
+}
+
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    drag: Option<GizmoDrag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Gizmo {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            drag: None,
+        }
+    }
+}
+
+impl Gizmo {
+    pub fn toggle_space(&mut self) {
+        self.space = match self.space {
+            GizmoSpace::World => GizmoSpace::Local,
+            GizmoSpace::Local => GizmoSpace::World,
+        };
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+}
+
+struct GizmoDrag {
+    axis: usize,
+    /// Camera of the view the drag started in, it carries on in that view
+    camera: Entity,
+    /// Cursor position last frame
+    last: Vec2,
+    /// Transform when the drag started, snapping works from here so it can be turned on and
+    /// off part way through
+    start: Transform,
+    /// How far it has been dragged without snapping. World units when moving, radians when
+    /// rotating and a multiple of the starting size when scaling
+    amount: f32,
+}
+
+/// Handles stay the same size on screen, this is their length as a fraction of half the
+/// view's height
+const HANDLE_SCALE: f32 = 0.35;
+
+/// How close in pixels the cursor has to be to grab a handle
+const GRAB_DISTANCE: f32 = 8.0;
+
+const RING_SEGMENTS: usize = 32;
+
+const AXIS_COLORS: [Color; 3] = [Color::RED, Color::GREEN, Color::BLUE];
+
+/// Where the gizmo is drawn and which way its handles point
+struct GizmoFrame {
+    origin: Vec3,
+    axes: [Vec3; 3],
+    length: f32,
+}
+
+impl GizmoFrame {
+    fn new(global: &GlobalTransform, space: GizmoSpace, view: &GizmoView) -> Self {
+        let axes = match space {
+            GizmoSpace::World => [Vec3::X, Vec3::Y, Vec3::Z],
+            GizmoSpace::Local => [
+                global.rotation * Vec3::X,
+                global.rotation * Vec3::Y,
+                global.rotation * Vec3::Z,
+            ],
+        };
+        GizmoFrame {
+            origin: global.translation,
+            axes,
+            length: view.half_height_at(global.translation) * HANDLE_SCALE,
+        }
+    }
+
+    fn tip(&self, axis: usize) -> Vec3 {
+        self.origin + self.axes[axis] * self.length
+    }
+
+    /// Points around the rotate handle for an axis, the first and last are the same
+    fn ring(&self, axis: usize) -> Vec<Vec3> {
+        let u = self.axes[(axis + 1) % 3];
+        let v = self.axes[(axis + 2) % 3];
+        (0..=RING_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / RING_SEGMENTS as f32 * PI * 2.0;
+                self.origin + (u * angle.cos() + v * angle.sin()) * self.length
+            })
+            .collect()
+    }
+}
+
+/// Camera view for going between world and window positions
+struct GizmoView {
+    world_to_ndc: Mat4,
+    viewport: EditorViewport,
+    camera: Vec3,
+}
+
+impl GizmoView {
+    fn new(camera: &Camera, camera_transform: &GlobalTransform, viewport: &EditorViewport) -> Self {
+        GizmoView {
+            world_to_ndc: camera.projection_matrix * camera_transform.compute_matrix().inverse(),
+            viewport: viewport.clone(),
+            camera: camera_transform.translation,
+        }
+    }
+
+    /// Window position of a world position, None if its behind the camera
+    fn to_window(&self, position: Vec3) -> Option<Vec2> {
+        let clip = self.world_to_ndc * position.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(self.viewport.from_ndc(Vec2::new(ndc.x, ndc.y)))
+    }
+
+    /// World units from the middle to the top of the view, as deep into it as this position.
+    /// Shrinks with distance in perspective, orthographic views are the same all the way back
+    fn half_height_at(&self, position: Vec3) -> f32 {
+        let clip = self.world_to_ndc * position.extend(1.0);
+        let depth = clip.z / clip.w;
+        let ndc_to_world = self.world_to_ndc.inverse();
+        ndc_to_world
+            .project_point3(Vec3::new(0.0, 0.0, depth))
+            .distance(ndc_to_world.project_point3(Vec3::new(0.0, 1.0, depth)))
+    }
+
+    /// Closest the cursor is to a line through these points, in pixels
+    fn distance_to(&self, points: &[Vec3], cursor: Vec2) -> f32 {
+        points
+            .windows(2)
+            .filter_map(|pair| Some((self.to_window(pair[0])?, self.to_window(pair[1])?)))
+            .map(|(a, b)| distance_to_segment(cursor, a, b))
+            .fold(f32::MAX, f32::min)
+    }
+}
+
+/// View the gizmo is being worked in, the one a drag started in or else the one under the
+/// cursor
+fn active_view(
+    gizmo: &Gizmo,
+    windows: &Windows,
+    viewports: &EditorViewports,
+    cameras: &Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+) -> Option<GizmoView> {
+    let viewport = match gizmo.drag.as_ref() {
+        Some(drag) => viewports.for_camera(drag.camera),
+        None => windows
+            .get_primary()
+            .and_then(|w| w.cursor_position())
+            .and_then(|cursor| viewports.at(cursor)),
+    }
+    .or_else(|| viewports.viewports.first())?;
+    let (camera, camera_transform) = cameras.get(viewport.camera).ok()?;
+    Some(GizmoView::new(camera, camera_transform, viewport))
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 {
+        ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+/// Handle under the cursor
+fn grabbed_axis(
+    frame: &GizmoFrame,
+    mode: GizmoMode,
+    view: &GizmoView,
+    cursor: Vec2,
+) -> Option<usize> {
+    (0..3)
+        .map(|axis| {
+            let distance = match mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    view.distance_to(&[frame.origin, frame.tip(axis)], cursor)
+                }
+                GizmoMode::Rotate => view.distance_to(&frame.ring(axis), cursor),
+            };
+            (axis, distance)
+        })
+        .filter(|(_, distance)| *distance <= GRAB_DISTANCE)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(axis, _)| axis)
+}
+
+fn drag_gizmo(
+    egui_ctx: Res<EguiContext>,
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    selection: Res<EditorSelection>,
+    grid: Res<GridData>,
+    viewports: Res<EditorViewports>,
+    mut gizmo: ResMut<Gizmo>,
+    cameras: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+    mut targets: Query<(&mut Transform, &GlobalTransform, Option<&Parent>)>,
+    parents: Query<&GlobalTransform>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        gizmo.drag = None;
+    }
+
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    let view = match active_view(&gizmo, &windows, &viewports, &cameras) {
+        Some(view) => view,
+        None => return,
+    };
+    let (mut transform, global, parent) =
+        match selection.entity.and_then(|e| targets.get_mut(e).ok()) {
+            Some(target) => target,
+            None => return,
+        };
+    let frame = GizmoFrame::new(global, gizmo.space, &view);
+
+    let mode = gizmo.mode;
+    if mouse.just_pressed(MouseButton::Left) && !egui_ctx.ctx().wants_pointer_input() {
+        gizmo.drag = grabbed_axis(&frame, mode, &view, cursor).map(|axis| GizmoDrag {
+            axis,
+            camera: view.viewport.camera,
+            last: cursor,
+            start: *transform,
+            amount: if mode == GizmoMode::Scale { 1.0 } else { 0.0 },
+        });
+    }
+
+    let drag = match gizmo.drag.as_mut() {
+        Some(drag) => drag,
+        None => return,
+    };
+    let (origin, tip) = match (
+        view.to_window(frame.origin),
+        view.to_window(frame.tip(drag.axis)),
+    ) {
+        (Some(origin), Some(tip)) => (origin, tip),
+        _ => return,
+    };
+    let parent = parent.and_then(|parent| parents.get(parent.0).ok());
+    let axis = frame.axes[drag.axis];
+    let delta = cursor - drag.last;
+    drag.last = cursor;
+
+    match mode {
+        GizmoMode::Translate | GizmoMode::Scale => {
+            let screen_axis = tip - origin;
+            if screen_axis.length_squared() < 1.0 {
+                return;
+            }
+            // how far along the handle the cursor moved, in handle lengths
+            let amount = delta.dot(screen_axis) / screen_axis.length_squared();
+            if mode == GizmoMode::Translate {
+                drag.amount += amount * frame.length;
+                let to_world = parent.map_or(Mat4::IDENTITY, |parent| parent.compute_matrix());
+                let mut position =
+                    to_world.transform_point3(drag.start.translation) + axis * drag.amount;
+                if grid.snapping() {
+                    position = grid.snap_position(position, axis);
+                }
+                transform.translation = to_world.inverse().transform_point3(position);
+            } else {
+                drag.amount *= 1.0 + amount;
+                let factor = if grid.snapping() {
+                    grid.snap_scale(drag.amount)
+                } else {
+                    drag.amount
+                };
+                transform.scale[drag.axis] = (drag.start.scale[drag.axis] * factor).max(0.01);
+            }
+        }
+        GizmoMode::Rotate => {
+            let from = cursor - delta - origin;
+            let to = cursor - origin;
+            let mut angle = from.perp_dot(to).atan2(from.dot(to));
+            // turning anticlockwise on screen is a positive turn when the axis faces us
+            if axis.dot(view.camera - frame.origin) < 0.0 {
+                angle = -angle;
+            }
+            drag.amount += angle;
+            let angle = if grid.snapping() {
+                grid.snap_angle(drag.amount)
+            } else {
+                drag.amount
+            };
+            let turn = Quat::from_axis_angle(axis, angle);
+            let turn = match parent {
+                Some(parent) => parent.rotation.inverse() * turn * parent.rotation,
+                None => turn,
+            };
+            transform.rotation = (turn * drag.start.rotation).normalize();
+        }
+    }
+}
+
+fn select_entities(
+    egui_ctx: Res<EguiContext>,
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    meshes: Res<Assets<Mesh>>,
+    gizmo: Res<Gizmo>,
+    viewports: Res<EditorViewports>,
+    mut selection: ResMut<EditorSelection>,
+    mut ev_picking: EventReader<PickingEvent>,
+    cameras: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+    pickables: Query<(Entity, &GlobalTransform, &Handle<Mesh>), With<PickableMesh>>,
+) {
+    if viewports.uses_window_camera() {
+        for event in ev_picking.iter() {
+            if let PickingEvent::Clicked(entity) = event {
+                if !gizmo.is_dragging() {
+                    selection.entity = Some(*entity);
+                }
+            }
+        }
+        return;
+    }
+
+    // bevy picking is looking through the window camera, which isn't what's on screen
+    ev_picking.iter().last();
+    if !mouse.just_pressed(MouseButton::Left)
+        || gizmo.is_dragging()
+        || egui_ctx.ctx().wants_pointer_input()
+    {
+        return;
+    }
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    let viewport = match viewports.at(cursor) {
+        Some(viewport) => viewport,
+        None => return,
+    };
+    let (camera, camera_transform) = match cameras.get(viewport.camera) {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let (origin, direction) = viewport.ray(camera, camera_transform, cursor);
+
+    // closest mesh box along the ray, tested in the mesh's own space so turned boxes fit
+    let hit = pickables
+        .iter()
+        .filter_map(|(entity, global, mesh)| {
+            let (min, max) = mesh_bounds(meshes.get(mesh)?)?;
+            let to_local = global.compute_matrix().inverse();
+            let distance = ray_hits_box(
+                to_local.transform_point3(origin),
+                to_local.transform_vector3(direction),
+                min,
+                max,
+            )?;
+            Some((entity, distance))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    if let Some((entity, _)) = hit {
+        selection.entity = Some(entity);
+    }
+}
+
+fn draw_gizmo(
+    windows: Res<Windows>,
+    gizmo: Res<Gizmo>,
+    grid: Res<GridData>,
+    viewports: Res<EditorViewports>,
+    mut selection: ResMut<EditorSelection>,
+    mut lines: ResMut<DebugLines>,
+    cameras: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+    targets: Query<&GlobalTransform>,
+) {
+    let entity = match selection.entity {
+        Some(entity) => entity,
+        None => return,
+    };
+    let global = match targets.get(entity) {
+        Ok(global) => global,
+        Err(_) => {
+            // despawned from under us
+            selection.entity = None;
+            return;
+        }
+    };
+    // every view shows the same lines, so they're sized for the one being worked in
+    let view = match active_view(&gizmo, &windows, &viewports, &cameras) {
+        Some(view) => view,
+        None => return,
+    };
+
+    let frame = GizmoFrame::new(global, gizmo.space, &view);
+    let active = gizmo.drag.as_ref().map(|drag| drag.axis);
+    if active.is_some() && gizmo.mode == GizmoMode::Translate && grid.snapping() {
+        draw_snap_target(&mut lines, &grid, global.translation);
+    }
+    for axis in 0..3 {
+        let color = if active == Some(axis) {
+            Color::YELLOW
+        } else {
+            AXIS_COLORS[axis]
+        };
+        match gizmo.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let tip = frame.tip(axis);
+                lines.line_colored(frame.origin, tip, 0.0, color);
+                // arrow heads for moving, boxes for scaling
+                let side = frame.axes[(axis + 1) % 3] * frame.length * 0.08;
+                let back = frame.axes[axis] * frame.length * 0.15;
+                if gizmo.mode == GizmoMode::Translate {
+                    lines.line_colored(tip, tip - back + side, 0.0, color);
+                    lines.line_colored(tip, tip - back - side, 0.0, color);
+                } else {
+                    let up = frame.axes[(axis + 2) % 3] * frame.length * 0.08;
+                    lines.line_colored(tip + side + up, tip + side - up, 0.0, color);
+                    lines.line_colored(tip + side - up, tip - side - up, 0.0, color);
+                    lines.line_colored(tip - side - up, tip - side + up, 0.0, color);
+                    lines.line_colored(tip - side + up, tip + side + up, 0.0, color);
+                }
+            }
+            GizmoMode::Rotate => {
+                for pair in frame.ring(axis).windows(2) {
+                    lines.line_colored(pair[0], pair[1], 0.0, color);
+                }
+            }
+        }
+    }
+}
+
+fn clear_selection(mut selection: ResMut<EditorSelection>, mut gizmo: ResMut<Gizmo>) {
+    selection.entity = None;
+    gizmo.drag = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_distance() {
+        let a = Vec2::ZERO;
+        let b = Vec2::new(10.0, 0.0);
+        assert_eq!(distance_to_segment(Vec2::new(5.0, 3.0), a, b), 3.0);
+        // past the ends measures to the end
+        assert_eq!(distance_to_segment(Vec2::new(-4.0, 3.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(Vec2::new(13.0, 4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(Vec2::new(1.0, 1.0), a, a), 2f32.sqrt());
+    }
+
+    #[test]
+    fn rings_go_around_the_axis() {
+        let frame = GizmoFrame {
+            origin: Vec3::new(1.0, 2.0, 3.0),
+            axes: [Vec3::X, Vec3::Y, Vec3::Z],
+            length: 2.0,
+        };
+        for axis in 0..3 {
+            let ring = frame.ring(axis);
+            assert_eq!(ring.len(), RING_SEGMENTS + 1);
+            for point in ring {
+                let offset = point - frame.origin;
+                assert!((offset.length() - 2.0).abs() < 0.001);
+                assert!(offset.dot(frame.axes[axis]).abs() < 0.001);
+            }
+        }
+    }
+}
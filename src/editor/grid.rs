@@ -1,286 +1,350 @@
-use crate::helpers::cleanup_system;
+use crate::{
+    helpers::grid::GridSpace,
+    tween::{ColorLens, Lens},
+};
 
-use super::EditorState;
+use super::{EditorCamera, EditorState, PanOrbitCamera, RegisterConsoleCommand};
 use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
 use bevy_inspector_egui::InspectorPlugin;
-use bevy_inspector_egui::{widgets::InspectableButton, Inspectable};
-use std::f32::consts::PI;
+use bevy_prototype_debug_lines::DebugLines;
 
-#[derive(Inspectable, Debug)]
+/// Editor grid, drawn fresh every frame around where the camera is looking so changes show up
+/// straight away and it never runs out
+#[derive(Inspectable, Debug, Reflect)]
 pub struct GridData {
-    #[inspectable()]
-    pub size: (u32, u32, u32),
-
     pub show_x_grid: bool,
     pub show_y_grid: bool,
     pub show_z_grid: bool,
 
-    pub grid_center: bool,
-    #[inspectable(min = 0.0)]
+    /// Smallest cell, also what placing and snapping line up to. Cells grow as the camera
+    /// pulls away
+    #[inspectable(min = 0.01)]
     pub cell_size: f32,
-
-    pub grid_x_material: Handle<ColorMaterial>,
-    pub grid_y_material: Handle<ColorMaterial>,
-    pub grid_z_material: Handle<ColorMaterial>,
-
-    #[inspectable(min = 0.0, max = 10.0)]
-    pub line_thickness: f32,
-    #[inspectable(min = 0.0, max = 10.0)]
-    pub line_thickness_bold: f32,
-
-    #[inspectable(text = "Rebuild")]
-    rebuild: InspectableButton<GridResetEventButton>,
+    /// Every this many lines is brighter, and cells grow by this much at a time
+    #[inspectable(min = 2, max = 10)]
+    pub major_every: u32,
+    /// Lines out from the middle in each direction
+    #[inspectable(min = 1, max = 200)]
+    pub lines: u32,
+    /// How far out lines start fading, as a fraction of the grid
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub fade_start: f32,
+
+    pub x_color: Color,
+    pub y_color: Color,
+    pub z_color: Color,
+
+    /// Line up moving, placing and duplicating with the grid cells. Holding the snap key
+    /// flips this while it's down
+    pub snap: bool,
+    /// Degrees a snapped turn moves in
+    #[inspectable(min = 1.0, max = 90.0)]
+    pub rotate_step: f32,
+    /// Snapped scaling goes in this much of the starting size at a time
+    #[inspectable(min = 0.01, max = 1.0)]
+    pub scale_step: f32,
+    #[inspectable(ignore)]
+    #[reflect(ignore)]
+    pub snap_held: bool,
 }
 
-impl FromWorld for GridData {
-    fn from_world(world: &mut World) -> Self {
-        let world = world.cell();
-
-        let mut materials = world
-            .get_resource_mut::<Assets<ColorMaterial>>()
-            .expect("ResMut<Assets<ColorMaterial>> not found.");
-
-        let size = 10000;
+impl Default for GridData {
+    fn default() -> Self {
         GridData {
-            size: (size, size, size),
             show_x_grid: false,
             show_y_grid: true,
             show_z_grid: false,
-            grid_center: true,
-            grid_x_material: materials.add(Color::RED.into()),
-            grid_y_material: materials.add(Color::GREEN.into()),
-            grid_z_material: materials.add(Color::BLUE.into()),
-            line_thickness: 0.02,
-            line_thickness_bold: 0.3,
             cell_size: 100.0,
-            rebuild: InspectableButton::<GridResetEventButton>::new(),
+            major_every: 5,
+            lines: 50,
+            fade_start: 0.4,
+            x_color: Color::RED,
+            y_color: Color::GREEN,
+            z_color: Color::BLUE,
+            snap: true,
+            rotate_step: 15.0,
+            scale_step: 0.25,
+            snap_held: false,
         }
     }
 }
 
-pub struct GridPlugin;
+impl GridData {
+    pub fn snapping(&self) -> bool {
+        self.snap != self.snap_held
+    }
 
-impl Plugin for GridPlugin {
-    fn build(&self, app: &mut AppBuilder) {
-        app.add_event::<GridSpawnEvent>()
-            .add_event::<GridClearEvent>()
-            .add_plugin(InspectorPlugin::<GridData>::new().open(false))
-            .add_system_set(SystemSet::on_enter(EditorState::Loading).with_system(setup.system()))
-            .add_system_set(
-                SystemSet::on_update(EditorState::Playing).with_system(rebuild_button.system()),
-            )
-            .add_system_set(
-                SystemSet::on_exit(EditorState::Playing)
-                    .with_system(cleanup_system::<Grid>.system()),
-            );
+    /// The grid across the ground, x and z as its x and y. It has no cells of its own, the
+    /// lines carry on forever either way
+    pub fn space(&self) -> GridSpace {
+        GridSpace::new(0, 0, self.cell_size)
     }
-}
 
-#[derive(Default)]
-pub struct GridSpawnEvent;
+    /// Puts a position on the grid, but only along the directions it is moving in. Across the
+    /// ground it goes to the middle of a cell, upwards it goes in whole cells from the ground
+    pub fn snap_position(&self, position: Vec3, moving: Vec3) -> Vec3 {
+        let space = self.space();
+        let (x, z) = space.cell_at(Vec2::new(position.x, position.z));
+        let center = space.cell_center(x, z);
+        let height = space.snap(Vec2::new(position.y, 0.0)).x;
+        let on_grid = Vec3::new(center.x, height, center.y);
+
+        let mut snapped = position;
+        for i in 0..3 {
+            if moving[i].abs() >= 0.001 {
+                snapped[i] = on_grid[i];
+            }
+        }
+        snapped
+    }
 
-#[derive(Default)]
-pub struct GridResetEventButton;
+    /// Middle of the ground cell a position is over, keeping its height
+    pub fn snap_to_cell(&self, position: Vec3) -> Vec3 {
+        self.snap_position(position, Vec3::new(1.0, 0.0, 1.0))
+    }
 
-#[derive(Default)]
-pub struct GridClearEvent;
+    /// Radians, to the nearest step
+    pub fn snap_angle(&self, angle: f32) -> f32 {
+        let step = self.rotate_step.to_radians();
+        (angle / step).round() * step
+    }
 
-#[derive(Bundle, Clone, Debug)]
-pub struct GridBundle {
-    pub transform: Transform,
-    pub global_transform: GlobalTransform,
+    /// How much to scale by, to the nearest step and never down to nothing
+    pub fn snap_scale(&self, factor: f32) -> f32 {
+        ((factor / self.scale_step).round() * self.scale_step).max(self.scale_step)
+    }
 }
 
-impl Default for GridBundle {
-    fn default() -> Self {
-        GridBundle {
-            transform: Transform::default(),
-            global_transform: Default::default(),
-        }
+/// Outline of the ground cell something is about to land in
+pub fn draw_snap_target(lines: &mut DebugLines, grid: &GridData, position: Vec3) {
+    let center = grid.snap_to_cell(position);
+    let half = grid.cell_size * 0.5;
+    let corners = [
+        center + Vec3::new(-half, 0.0, -half),
+        center + Vec3::new(half, 0.0, -half),
+        center + Vec3::new(half, 0.0, half),
+        center + Vec3::new(-half, 0.0, half),
+    ];
+    for i in 0..4 {
+        lines.line_colored(corners[i], corners[(i + 1) % 4], 0.0, Color::YELLOW);
+    }
+    // and a drop line when it's off the ground
+    if position.y.abs() > 0.001 {
+        lines.line_colored(
+            center,
+            Vec3::new(center.x, position.y, center.z),
+            0.0,
+            Color::YELLOW,
+        );
     }
 }
 
-#[derive(PartialEq, Debug)]
-enum Orientation {
-    Horizontal,
-    Vertical,
-}
+pub struct GridPlugin;
 
-enum Grid {
-    Grid,
-    Line,
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<GridData>::new().open(false))
+            .register_console_resource::<GridData>("grid")
+            .add_system_set(
+                SystemSet::on_update(EditorState::Playing).with_system(draw_grid.system()),
+            );
+    }
 }
 
+/// Which way a grid faces, named after the axis it is square to
 #[derive(Copy, Clone, Debug)]
-enum GridType {
+enum GridPlane {
     X,
     Y,
     Z,
 }
 
-pub fn setup(mut commands: Commands, grid: Res<GridData>) {
-    spawn_grid(&grid, &mut commands);
+impl GridPlane {
+    /// Two directions across the plane, then the normal
+    fn axes(&self) -> (Vec3, Vec3, Vec3) {
+        match self {
+            GridPlane::X => (Vec3::Z, Vec3::Y, Vec3::X),
+            GridPlane::Y => (Vec3::X, Vec3::Z, Vec3::Y),
+            GridPlane::Z => (Vec3::X, Vec3::Y, Vec3::Z),
+        }
+    }
 }
 
-fn spawn_grid(grid: &Res<GridData>, commands: &mut Commands) {
-    if grid.show_x_grid {
-        build_grid(commands, grid, GridType::X);
-    }
-    if grid.show_y_grid {
-        build_grid(commands, grid, GridType::Y);
-    }
-    if grid.show_z_grid {
-        build_grid(commands, grid, GridType::Z);
+/// Each line is split up so the fade can follow it
+const SEGMENTS: u32 = 20;
+
+/// How much of the way to the background minor lines are
+const MINOR_DIM: f32 = 0.6;
+
+/// Space between lines for a camera this far away. Starts at the cell size and grows by
+/// `major_every` until the grid reaches past what the camera can see
+pub fn grid_spacing(cell_size: f32, major_every: u32, lines: u32, distance: f32) -> f32 {
+    let reach = distance * 2.0;
+    let mut spacing = cell_size;
+    // capped, so a huge distance can't spin forever
+    for _ in 0..16 {
+        if spacing * lines as f32 >= reach {
+            break;
+        }
+        spacing *= major_every as f32;
     }
+    spacing
 }
 
-fn build_grid(commands: &mut Commands, grid: &Res<GridData>, grid_type: GridType) -> Entity {
-    // TODO: I am not proud of this, its but its close to working and good enough for the moment
-    // far to much code for what I am trying to and after all of that I still don't have the
-    // rotations quite right so the lines dont match up at the corner
-    let (transform, size) = match grid_type {
-        GridType::X => (
-            Transform {
-                translation: if grid.grid_center {
-                    Vec3::ZERO
-                } else {
-                    Vec3::new(grid.size.0 as f32 * -0.5, 0.0, 0.0)
-                },
-                rotation: Quat::from_rotation_y(PI / 2.0),
-                ..Default::default()
-            },
-            (grid.size.1 as f32, grid.size.2 as f32),
-        ),
-        GridType::Y => (
-            Transform {
-                translation: if grid.grid_center {
-                    Vec3::ZERO
-                } else {
-                    Vec3::new(0.0, grid.size.1 as f32 * -0.5, 0.0)
-                },
-                rotation: Quat::from_rotation_x(PI / 2.0),
-                ..Default::default()
-            },
-            (grid.size.2 as f32, grid.size.0 as f32),
-        ),
-        GridType::Z => (
-            Transform {
-                // blue
-                translation: if grid.grid_center {
-                    Vec3::ZERO
-                } else {
-                    Vec3::new(0.0, 0.0, grid.size.2 as f32 * -0.5)
-                },
-                rotation: Quat::from_rotation_z(PI / 2.0),
-                ..Default::default()
-            },
-            (grid.size.0 as f32, grid.size.1 as f32),
-        ),
-    };
+fn mix(from: Color, to: Color, ratio: f32) -> Color {
+    let mut color = from;
+    ColorLens { from, to }.lerp(&mut color, ratio);
+    color
+}
 
-    let parent = commands
-        .spawn_bundle(GridBundle {
-            transform: transform,
-            ..Default::default()
-        })
-        .insert(grid_type) // so we get cleaned up
-        .insert(Grid::Grid)
-        .insert(Name::new(format!("Grid {:?}", grid_type)))
-        .id();
-
-    let mut lines: Vec<Entity> = vec![];
-
-    let row_count = (size.0 / grid.cell_size).round() as u32;
-    for row in 0..=row_count {
-        let line = commands
-            .spawn_bundle(build_gridline(
-                Orientation::Horizontal,
-                row,
-                row == 0 || row == row_count || row % 5 == 0,
-                &grid,
-                grid_type,
-                size,
-            ))
-            .insert(grid_type)
-            .insert(Grid::Line)
-            .id();
-        lines.push(line);
+fn draw_grid(
+    data: Res<GridData>,
+    clear_color: Res<ClearColor>,
+    mut lines: ResMut<DebugLines>,
+    cameras: Query<(&GlobalTransform, &PanOrbitCamera), With<EditorCamera>>,
+) {
+    if data.cell_size <= 0.0 || data.major_every < 2 || data.lines == 0 {
+        return;
     }
+    // the other views look at the same grid as the perspective camera
+    let (camera, focus) = match cameras.iter().next() {
+        Some((transform, orbit)) => (transform.translation, orbit.focus),
+        None => return,
+    };
 
-    let column_count = (size.1 / grid.cell_size).round() as u32;
-
-    for column in 0..=column_count {
-        let line = commands
-            .spawn_bundle(build_gridline(
-                Orientation::Vertical,
-                column,
-                column == 0 || column == column_count || column % 5 == 0,
-                &grid,
-                grid_type,
-                size,
-            ))
-            .insert(grid_type)
-            .id();
-        lines.push(line);
+    let planes = [
+        (data.show_x_grid, GridPlane::X, data.x_color),
+        (data.show_y_grid, GridPlane::Y, data.y_color),
+        (data.show_z_grid, GridPlane::Z, data.z_color),
+    ];
+    for (show, plane, color) in planes.iter() {
+        if *show {
+            draw_plane(
+                &mut lines,
+                &data,
+                *plane,
+                *color,
+                clear_color.0,
+                camera,
+                focus,
+            );
+        }
     }
-    commands.entity(parent).push_children(&lines);
-
-    parent
 }
 
-fn build_gridline(
-    orientation: Orientation,
-    i: u32,
-    edge: bool,
-    data: &Res<GridData>,
-    grid_type: GridType,
-    size: (f32, f32),
-) -> SpriteBundle {
-    // The grid lines that define the boxes need to be thicker
-    let thickness = if edge {
-        data.line_thickness_bold
-    } else {
-        data.line_thickness
-    };
-    let left_edge = size.0 * -0.5;
-    let bot_edge = size.1 * -0.5;
-
-    let (x, y, sprite_size) = match orientation {
-        Orientation::Horizontal => (
-            0.0,
-            left_edge + (i as f32 * data.cell_size),
-            Vec2::new(size.1 as f32 + thickness, thickness),
-        ),
-        Orientation::Vertical => (
-            bot_edge + (i as f32 * data.cell_size),
-            0.0,
-            Vec2::new(thickness, size.0 as f32 + thickness),
-        ),
+fn draw_plane(
+    lines: &mut DebugLines,
+    data: &GridData,
+    plane: GridPlane,
+    color: Color,
+    background: Color,
+    camera: Vec3,
+    focus: Vec3,
+) {
+    let (u, v, normal) = plane.axes();
+    // middle of the grid is where the camera is looking, flattened onto the plane
+    let focus = focus - normal * focus.dot(normal);
+    let distance = camera.distance(focus).max(data.cell_size);
+    let spacing = grid_spacing(data.cell_size, data.major_every, data.lines, distance);
+    let major = spacing * data.major_every as f32;
+
+    // lined up with the major lines, so the grid doesn't slide around with the camera
+    let center_u = (focus.dot(u) / major).round() * major;
+    let center_v = (focus.dot(v) / major).round() * major;
+    let center = u * center_u + v * center_v;
+    let count = data.lines as i64;
+    let extent = spacing * count as f32;
+
+    let fade_start = data.fade_start.min(0.99);
+    let faded = |color: Color, point: Vec3| {
+        let out = (point - center).length() / extent;
+        let ratio = ((out - fade_start) / (1.0 - fade_start)).clamp(0.0, 1.0);
+        mix(color, background, ratio)
     };
 
-    SpriteBundle {
-        sprite: Sprite::new(sprite_size),
-        transform: Transform::from_xyz(x, y, 0.0),
-        material: match grid_type {
-            GridType::X => data.grid_x_material.clone(),
-            GridType::Y => data.grid_y_material.clone(),
-            GridType::Z => data.grid_z_material.clone(),
-        },
-        ..Default::default()
+    let step = |s: u32| -extent + 2.0 * extent * s as f32 / SEGMENTS as f32;
+    for (along, across, across_center) in [(u, v, center_v), (v, u, center_u)].iter() {
+        let first = (across_center / spacing).round() as i64;
+        for i in -count..=count {
+            let line_color = if (first + i).rem_euclid(data.major_every as i64) == 0 {
+                color
+            } else {
+                mix(color, background, MINOR_DIM)
+            };
+            let offset = *across * (i as f32 * spacing);
+            for s in 0..SEGMENTS {
+                let start = center + offset + *along * step(s);
+                let end = center + offset + *along * step(s + 1);
+                lines.line_gradient(
+                    start,
+                    end,
+                    0.0,
+                    faded(line_color, start),
+                    faded(line_color, end),
+                );
+            }
+        }
     }
 }
 
-fn rebuild_button(
-    mut commands: Commands,
-    grid: Res<GridData>,
-    mut ev_reset: EventReader<GridResetEventButton>,
-    q: Query<Entity, With<Grid>>,
-) {
-    for _ in ev_reset.iter() {
-        for e in q.iter() {
-            commands.entity(e).despawn_recursive();
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spacing_grows_with_distance() {
+        // close up the cells are as small as they go
+        assert_eq!(grid_spacing(100.0, 5, 50, 10.0), 100.0);
+        assert_eq!(grid_spacing(100.0, 5, 50, 2500.0), 100.0);
+        // then step up by whole major cells
+        assert_eq!(grid_spacing(100.0, 5, 50, 2600.0), 500.0);
+        assert_eq!(grid_spacing(100.0, 5, 50, 60000.0), 2500.0);
+        assert!(grid_spacing(1.0, 2, 1, f32::MAX).is_finite());
+    }
+
+    #[test]
+    fn snapping_lines_up_with_cells() {
+        let grid = GridData {
+            cell_size: 10.0,
+            ..Default::default()
+        };
+        let position = Vec3::new(12.0, 17.0, -3.0);
+        assert_eq!(grid.snap_to_cell(position), Vec3::new(15.0, 17.0, -5.0));
+        // only what is moving snaps
+        assert_eq!(
+            grid.snap_position(position, Vec3::X),
+            Vec3::new(15.0, 17.0, -3.0)
+        );
+        assert_eq!(
+            grid.snap_position(position, Vec3::Y),
+            Vec3::new(12.0, 20.0, -3.0)
+        );
+
+        assert!((grid.snap_angle(20f32.to_radians()) - 15f32.to_radians()).abs() < 0.0001);
+        assert!((grid.snap_angle(-25f32.to_radians()) + 30f32.to_radians()).abs() < 0.0001);
+        assert_eq!(grid.snap_scale(1.3), 1.25);
+        assert_eq!(grid.snap_scale(0.01), 0.25);
+    }
 
-        spawn_grid(&grid, &mut commands);
+    #[test]
+    fn holding_the_key_flips_snapping() {
+        let mut grid = GridData::default();
+        assert!(grid.snapping());
+        grid.snap_held = true;
+        assert!(!grid.snapping());
+        grid.snap = false;
+        assert!(grid.snapping());
+    }
+
+    #[test]
+    fn planes_are_square_to_their_axis() {
+        for plane in [GridPlane::X, GridPlane::Y, GridPlane::Z].iter() {
+            let (u, v, normal) = plane.axes();
+            assert_eq!(u.dot(v), 0.0);
+            assert_eq!(u.dot(normal), 0.0);
+            assert_eq!(v.dot(normal), 0.0);
+        }
+        assert_eq!(GridPlane::Y.axes().2, Vec3::Y);
     }
 }
@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, popup_below_widget, Rect, Sense, Ui, Window},
+    EguiContext,
+};
+
+use crate::GameStages;
+
+use super::{is_editor_only, EditorCommand, EditorSelection, EditorState};
+
+/// Tree of every entity by name. Click to select, drag onto another entity to reparent, right
+/// click for the rest. Everything goes through `EditorCommand` so it can be undone
+pub struct HierarchyPlugin;
+
+impl Plugin for HierarchyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(HierarchyWindow::default())
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(draw_hierarchy.exclusive_system()),
+            );
+    }
+}
+
+pub struct HierarchyWindow {
+    pub show: bool,
+    /// Only show entities with this in their name or one of their component names
+    filter: String,
+    show_editor: bool,
+    expanded: HashSet<Entity>,
+    renaming: Option<(Entity, String)>,
+    dragging: Option<Entity>,
+}
+
+impl Default for HierarchyWindow {
+    fn default() -> Self {
+        HierarchyWindow {
+            show: false,
+            filter: String::new(),
+            show_editor: false,
+            expanded: HashSet::new(),
+            renaming: None,
+            dragging: None,
+        }
+    }
+}
+
+/// Indent per level of the tree
+const INDENT: f32 = 12.0;
+
+struct Node {
+    entity: Entity,
+    name: String,
+    children: Vec<Node>,
+}
+
+fn display_name(world: &World, entity: Entity) -> String {
+    match world.get::<Name>(entity) {
+        Some(name) => name.as_str().to_string(),
+        None => format!("Entity {}", entity.id()),
+    }
+}
+
+fn matches(world: &World, entity: Entity, filter: &str) -> bool {
+    if display_name(world, entity).to_lowercase().contains(filter) {
+        return true;
+    }
+    let entity = match world.get_entity(entity) {
+        Some(entity) => entity,
+        None => return false,
+    };
+    entity
+        .archetype()
+        .components()
+        .filter_map(|id| world.components().get_info(id))
+        .any(|info| {
+            let name = info.name().rsplit("::").next().unwrap_or(info.name());
+            name.to_lowercase().contains(filter)
+        })
+}
+
+/// Node for an entity, None if neither it nor any of its children pass the filter
+fn build_node(world: &World, entity: Entity, filter: &str) -> Option<Node> {
+    let children: Vec<Node> = world
+        .get::<Children>(entity)
+        .map(|children| {
+            children
+                .iter()
+                .filter_map(|child| build_node(world, *child, filter))
+                .collect()
+        })
+        .unwrap_or_default();
+    if filter.is_empty() || !children.is_empty() || matches(world, entity, filter) {
+        Some(Node {
+            entity,
+            name: display_name(world, entity),
+            children,
+        })
+    } else {
+        None
+    }
+}
+
+fn build_tree(world: &mut World, state: &HierarchyWindow) -> Vec<Node> {
+    let filter = state.filter.trim().to_lowercase();
+    let mut roots: Vec<Entity> = world
+        .query_filtered::<Entity, Without<Parent>>()
+        .iter(world)
+        .collect();
+    roots.sort();
+    roots
+        .into_iter()
+        .filter(|entity| state.show_editor || !is_editor_only(world, *entity))
+        .filter_map(|entity| build_node(world, entity, &filter))
+        .collect()
+}
+
+fn is_descendant(world: &World, entity: Entity, ancestor: Entity) -> bool {
+    let mut current = world.get::<Parent>(entity).map(|parent| parent.0);
+    while let Some(parent) = current {
+        if parent == ancestor {
+            return true;
+        }
+        current = world.get::<Parent>(parent).map(|parent| parent.0);
+    }
+    false
+}
+
+fn new_entity(parent: Option<Entity>) -> EditorCommand {
+    EditorCommand::Spawn {
+        label: "Create Entity".to_string(),
+        components: vec![
+            Box::new(Name::new("Entity")),
+            Box::new(Transform::default()),
+            Box::new(GlobalTransform::default()),
+        ],
+        parent,
+    }
+}
+
+/// What the window wants done, applied once it is drawn
+#[derive(Default)]
+struct HierarchyOutput {
+    select: Option<Entity>,
+    commands: Vec<EditorCommand>,
+    /// Rows drawn this frame, for dropping onto
+    rows: Vec<(Option<Entity>, Rect)>,
+}
+
+fn draw_hierarchy(world: &mut World) {
+    let mut state = std::mem::take(
+        &mut *world
+            .get_resource_mut::<HierarchyWindow>()
+            .expect("HierarchyWindow not found."),
+    );
+    if !state.show {
+        *world.get_resource_mut::<HierarchyWindow>().unwrap() = state;
+        return;
+    }
+
+    let ctx = world
+        .get_resource::<EguiContext>()
+        .expect("EguiContext not found.")
+        .ctx()
+        .clone();
+    let selected = world
+        .get_resource::<EditorSelection>()
+        .and_then(|selection| selection.entity);
+    let tree = build_tree(world, &state);
+    let mut output = HierarchyOutput::default();
+    let mut show = state.show;
+
+    Window::new("Hierarchy")
+        .open(&mut show)
+        .scroll(true)
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Search");
+                ui.text_edit_singleline(&mut state.filter);
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.show_editor, "Editor Entities");
+                if ui.button("Create").clicked() {
+                    output.commands.push(new_entity(None));
+                }
+            });
+            ui.separator();
+
+            // dropping here moves an entity back to the top
+            let top = ui.label("(top)");
+            output.rows.push((None, top.rect));
+
+            let filtering = !state.filter.trim().is_empty();
+            for node in tree.iter() {
+                draw_node(ui, node, 0, filtering, selected, &mut state, &mut output);
+            }
+
+            if let Some(dragging) = state.dragging {
+                ui.separator();
+                ui.label(format!("Moving {}", display_name(world, dragging)));
+            }
+        });
+
+    // drop whatever is being dragged onto the row under the pointer
+    if let Some(dragging) = state.dragging {
+        let input = ctx.input();
+        if input.pointer.any_released() {
+            let target = input.pointer.hover_pos().and_then(|pos| {
+                output
+                    .rows
+                    .iter()
+                    .find(|(_, rect)| rect.contains(pos))
+                    .map(|(entity, _)| *entity)
+            });
+            if let Some(parent) = target {
+                let current = world.get::<Parent>(dragging).map(|parent| parent.0);
+                let valid = match parent {
+                    Some(parent) => parent != dragging && !is_descendant(world, parent, dragging),
+                    None => true,
+                };
+                if valid && parent != current {
+                    output.commands.push(EditorCommand::Reparent {
+                        entity: dragging,
+                        parent,
+                    });
+                }
+            }
+            state.dragging = None;
+        }
+    }
+
+    state.show = show;
+    *world.get_resource_mut::<HierarchyWindow>().unwrap() = state;
+    if let Some(entity) = output.select {
+        if let Some(mut selection) = world.get_resource_mut::<EditorSelection>() {
+            selection.entity = Some(entity);
+        }
+    }
+    let mut events = world
+        .get_resource_mut::<Events<EditorCommand>>()
+        .expect("Events<EditorCommand> not found.");
+    for command in output.commands {
+        events.send(command);
+    }
+}
+
+fn draw_node(
+    ui: &mut Ui,
+    node: &Node,
+    depth: usize,
+    filtering: bool,
+    selected: Option<Entity>,
+    state: &mut HierarchyWindow,
+    output: &mut HierarchyOutput,
+) {
+    let entity = node.entity;
+    // search results are always open, so matches aren't hidden
+    let open = filtering || state.expanded.contains(&entity);
+
+    ui.horizontal(|ui| {
+        ui.add_space(depth as f32 * INDENT);
+        if node.children.is_empty() {
+            ui.add_space(INDENT + 6.0);
+        } else if ui.small_button(if open { "v" } else { ">" }).clicked() {
+            if open {
+                state.expanded.remove(&entity);
+            } else {
+                state.expanded.insert(entity);
+            }
+        }
+
+        if let Some((renaming, text)) = state.renaming.as_mut() {
+            if *renaming == entity {
+                let response = ui.text_edit_singleline(text);
+                response.request_focus();
+                if response.lost_focus() {
+                    let name = text.trim().to_string();
+                    output.commands.push(EditorCommand::Set {
+                        entity,
+                        value: Box::new(Name::new(name)),
+                    });
+                    state.renaming = None;
+                }
+                return;
+            }
+        }
+
+        let response = ui
+            .selectable_label(selected == Some(entity), node.name.as_str())
+            .interact(Sense::drag());
+        output.rows.push((Some(entity), response.rect));
+        if response.clicked() {
+            output.select = Some(entity);
+        }
+        if response.drag_started() {
+            state.dragging = Some(entity);
+        }
+
+        let menu = ui.make_persistent_id(("hierarchy_menu", entity));
+        if response.secondary_clicked() {
+            output.select = Some(entity);
+            ui.memory().open_popup(menu);
+        }
+        popup_below_widget(ui, menu, &response, |ui| {
+            if ui.button("Create Child").clicked() {
+                output.commands.push(new_entity(Some(entity)));
+                state.expanded.insert(entity);
+            }
+            if ui.button("Duplicate").clicked() {
+                output.commands.push(EditorCommand::Duplicate(entity));
+            }
+            if ui.button("Rename").clicked() {
+                state.renaming = Some((entity, node.name.clone()));
+            }
+            if ui.button("Move to Top").clicked() {
+                output.commands.push(EditorCommand::Reparent {
+                    entity,
+                    parent: None,
+                });
+            }
+            ui.separator();
+            if ui
+                .add(egui::Button::new("Delete").text_color(egui::Color32::RED))
+                .clicked()
+            {
+                output.commands.push(EditorCommand::Despawn(entity));
+            }
+        });
+    });
+
+    if open {
+        for child in node.children.iter() {
+            draw_node(ui, child, depth + 1, filtering, selected, state, output);
+        }
+    }
+}
@@ -0,0 +1,828 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    reflect::{serde::ReflectSerializer, TypeRegistry, TypeRegistryArc},
+    transform::hierarchy::despawn_with_children_recursive,
+};
+use bevy_egui::{egui::Window, EguiContext};
+
+use crate::GameStages;
+
+use super::{EditorSelection, EditorState, GridData};
+
+/// Undo and redo for editor edits. Changes to the selected entity, from the inspector or the
+/// gizmo, are picked up once the mouse is let go. Spawning, despawning, reparenting and setting
+/// components on other entities goes through `EditorCommand` so it can be undone too
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<EditorCommand>()
+            .add_event::<HistoryRequest>()
+            .insert_resource(EditorHistory::default())
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(run_history.exclusive_system())
+                    .with_system(draw_history_window.system()),
+            );
+    }
+}
+
+/// Edits the editor makes to the world, each becomes a step in the history
+pub enum EditorCommand {
+    /// New entity with these components, under a parent if there is one
+    Spawn {
+        label: String,
+        components: Vec<Box<dyn Reflect>>,
+        parent: Option<Entity>,
+    },
+    /// Entity and all of its children
+    Despawn(Entity),
+    /// Copy of an entity and all of its children, next to the original
+    Duplicate(Entity),
+    /// Move under a new parent, or to the top, keeping where it is in the world
+    Reparent {
+        entity: Entity,
+        parent: Option<Entity>,
+    },
+    /// Add a component, or change its value
+    Set {
+        entity: Entity,
+        value: Box<dyn Reflect>,
+    },
+}
+
+pub enum HistoryRequest {
+    Undo,
+    Redo,
+    /// Undo or redo until this many edits are applied
+    JumpTo(usize),
+}
+
+/// Older edits are forgotten past this
+const MAX_HISTORY: usize = 100;
+
+pub struct EditorHistory {
+    pub show: bool,
+    entries: Vec<HistoryEntry>,
+    /// Edits before this are applied, the rest have been undone
+    position: usize,
+    /// Entities that were despawned and spawned again by undo or redo have a new id, edits
+    /// still use the old one
+    remap: HashMap<Entity, Entity>,
+    tracked: Option<Tracked>,
+}
+
+impl Default for EditorHistory {
+    fn default() -> Self {
+        EditorHistory {
+            show: false,
+            entries: Vec::new(),
+            position: 0,
+            remap: HashMap::new(),
+            tracked: None,
+        }
+    }
+}
+
+impl EditorHistory {
+    /// Forget everything, for when the entities the edits point at are gone
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.position = 0;
+        self.remap.clear();
+        self.tracked = None;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.entries.len()
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        self.entries.truncate(self.position);
+        self.entries.push(entry);
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len();
+    }
+
+    fn resolve(&self, entity: Entity) -> Entity {
+        let mut entity = entity;
+        while let Some(next) = self.remap.get(&entity) {
+            entity = *next;
+        }
+        entity
+    }
+}
+
+struct HistoryEntry {
+    label: String,
+    edit: Edit,
+}
+
+enum Edit {
+    Component {
+        entity: Entity,
+        before: Option<Box<dyn Reflect>>,
+        after: Box<dyn Reflect>,
+    },
+    Spawn {
+        snapshot: EntitySnapshot,
+        parent: Option<Entity>,
+    },
+    Despawn {
+        snapshot: EntitySnapshot,
+        parent: Option<Entity>,
+    },
+    Reparent {
+        entity: Entity,
+        before: (Option<Entity>, Transform),
+        after: (Option<Entity>, Transform),
+    },
+}
+
+/// Everything needed to bring back an entity and its children
+struct EntitySnapshot {
+    entity: Entity,
+    components: Vec<Box<dyn Reflect>>,
+    children: Vec<EntitySnapshot>,
+}
+
+/// Values of the selected entity's components when it last settled
+struct Tracked {
+    entity: Entity,
+    values: Vec<(Box<dyn Reflect>, String)>,
+}
+
+/// Rebuilt from other components, or handled by reparenting
+fn is_derived(type_name: &str) -> bool {
+    type_name == std::any::type_name::<GlobalTransform>()
+        || type_name == std::any::type_name::<Parent>()
+        || type_name == std::any::type_name::<PreviousParent>()
+        || type_name == std::any::type_name::<Children>()
+}
+
+/// Copies of the entity's reflected components
+fn components_of(world: &World, registry: &TypeRegistry, entity: Entity) -> Vec<Box<dyn Reflect>> {
+    let entity_ref = match world.get_entity(entity) {
+        Some(entity_ref) => entity_ref,
+        None => return Vec::new(),
+    };
+    entity_ref
+        .archetype()
+        .components()
+        .filter_map(|id| world.components().get_info(id)?.type_id())
+        .filter_map(|type_id| registry.get(type_id)?.data::<ReflectComponent>())
+        .filter_map(|reflect| reflect.reflect_component(world, entity))
+        .filter(|value| !is_derived(value.type_name()))
+        .map(|value| value.clone_value())
+        .collect()
+}
+
+/// Text form of a value to spot changes, None if it can't be serialized
+fn to_ron(registry: &TypeRegistry, value: &dyn Reflect) -> Option<String> {
+    ron::to_string(&ReflectSerializer::new(value, registry)).ok()
+}
+
+fn snapshot(world: &World, registry: &TypeRegistry, entity: Entity) -> EntitySnapshot {
+    let children = world
+        .get::<Children>(entity)
+        .map(|children| children.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+    EntitySnapshot {
+        entity,
+        components: components_of(world, registry, entity),
+        children: children
+            .into_iter()
+            .map(|child| snapshot(world, registry, child))
+            .collect(),
+    }
+}
+
+fn set_component(world: &mut World, registry: &TypeRegistry, entity: Entity, value: &dyn Reflect) {
+    if world.get_entity(entity).is_none() {
+        return;
+    }
+    let reflect = match registry
+        .get_with_name(value.type_name())
+        .and_then(|registration| registration.data::<ReflectComponent>())
+    {
+        Some(reflect) => reflect,
+        None => return,
+    };
+    if reflect.reflect_component(world, entity).is_some() {
+        reflect.apply_component(world, entity, value);
+    } else {
+        reflect.add_component(world, entity, value);
+    }
+}
+
+fn remove_component(world: &mut World, registry: &TypeRegistry, entity: Entity, type_name: &str) {
+    if world.get_entity(entity).is_none() {
+        return;
+    }
+    if let Some(reflect) = registry
+        .get_with_name(type_name)
+        .and_then(|registration| registration.data::<ReflectComponent>())
+    {
+        reflect.remove_component(world, entity);
+    }
+}
+
+fn restore(
+    world: &mut World,
+    registry: &TypeRegistry,
+    history: &mut EditorHistory,
+    snapshot: &EntitySnapshot,
+    parent: Option<Entity>,
+) {
+    let entity = world.spawn().id();
+    // edits made since it was last brought back use the id it had then, so carry on from that
+    let previous = history.resolve(snapshot.entity);
+    if entity != previous {
+        history.remap.insert(previous, entity);
+    }
+    fill(world, registry, entity, &snapshot.components, parent);
+    for child in snapshot.children.iter() {
+        restore(world, registry, history, child, Some(entity));
+    }
+}
+
+/// Spawn new entities from a snapshot, giving back a snapshot of the copies
+fn spawn_copy(
+    world: &mut World,
+    registry: &TypeRegistry,
+    snapshot: &EntitySnapshot,
+    parent: Option<Entity>,
+) -> EntitySnapshot {
+    let entity = world.spawn().id();
+    fill(world, registry, entity, &snapshot.components, parent);
+    EntitySnapshot {
+        entity,
+        components: snapshot
+            .components
+            .iter()
+            .map(|component| component.clone_value())
+            .collect(),
+        children: snapshot
+            .children
+            .iter()
+            .map(|child| spawn_copy(world, registry, child, Some(entity)))
+            .collect(),
+    }
+}
+
+fn fill(
+    world: &mut World,
+    registry: &TypeRegistry,
+    entity: Entity,
+    components: &[Box<dyn Reflect>],
+    parent: Option<Entity>,
+) {
+    for component in components.iter() {
+        set_component(world, registry, entity, component.as_ref());
+    }
+    if let Some(parent) = parent {
+        world.entity_mut(entity).insert(Parent(parent));
+    }
+}
+
+fn set_parent(world: &mut World, entity: Entity, parent: Option<Entity>, transform: Transform) {
+    if world.get_entity(entity).is_none() {
+        return;
+    }
+    let mut entity = world.entity_mut(entity);
+    match parent {
+        Some(parent) => {
+            entity.insert(Parent(parent));
+        }
+        None => {
+            entity.remove::<Parent>();
+        }
+    }
+    entity.insert(transform);
+}
+
+/// Local transform that keeps an entity where it is in the world under a new parent
+fn transform_under(world: &World, entity: Entity, parent: Option<Entity>) -> Transform {
+    let global = world
+        .get::<GlobalTransform>(entity)
+        .map(|global| global.compute_matrix())
+        .unwrap_or(Mat4::IDENTITY);
+    let parent = parent
+        .and_then(|parent| world.get::<GlobalTransform>(parent))
+        .map(|global| global.compute_matrix())
+        .unwrap_or(Mat4::IDENTITY);
+    Transform::from_matrix(parent.inverse() * global)
+}
+
+/// With snapping on copies go in the next cell over instead of on top of the original
+fn snapped_copy(world: &World, entity: Entity, parent: Option<Entity>) -> Option<Transform> {
+    let grid = world
+        .get_resource::<GridData>()
+        .filter(|grid| grid.snapping())?;
+    let transform = world.get::<Transform>(entity)?;
+    let global = world.get::<GlobalTransform>(entity)?;
+    let position = grid.snap_to_cell(global.translation + Vec3::X * grid.cell_size);
+    let to_world = parent
+        .and_then(|parent| world.get::<GlobalTransform>(parent))
+        .map_or(Mat4::IDENTITY, |global| global.compute_matrix());
+    Some(Transform {
+        translation: to_world.inverse().transform_point3(position),
+        ..*transform
+    })
+}
+
+fn name_of(world: &World, entity: Entity) -> String {
+    world
+        .get::<Name>(entity)
+        .map(|name| name.as_str().to_string())
+        .unwrap_or_else(|| format!("{:?}", entity))
+}
+
+fn short_name(type_name: &str) -> &str {
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+/// Turn a command into an edit and apply it
+fn execute(
+    world: &mut World,
+    registry: &TypeRegistry,
+    history: &mut EditorHistory,
+    command: EditorCommand,
+) -> Option<HistoryEntry> {
+    let (label, edit) = match command {
+        EditorCommand::Spawn {
+            label,
+            components,
+            parent,
+        } => {
+            let parent = parent.map(|parent| history.resolve(parent));
+            let entity = world.spawn().id();
+            fill(world, registry, entity, &components, parent);
+            // new entities are selected, so they can be worked on straight away
+            if let Some(mut selection) = world.get_resource_mut::<EditorSelection>() {
+                selection.entity = Some(entity);
+            }
+            let snapshot = EntitySnapshot {
+                entity,
+                components,
+                children: Vec::new(),
+            };
+            return Some(HistoryEntry {
+                label,
+                edit: Edit::Spawn { snapshot, parent },
+            });
+        }
+        EditorCommand::Despawn(entity) => {
+            let entity = history.resolve(entity);
+            world.get_entity(entity)?;
+            let parent = world.get::<Parent>(entity).map(|parent| parent.0);
+            (
+                format!("Despawn {}", name_of(world, entity)),
+                Edit::Despawn {
+                    snapshot: snapshot(world, registry, entity),
+                    parent,
+                },
+            )
+        }
+        EditorCommand::Reparent { entity, parent } => {
+            let entity = history.resolve(entity);
+            let parent = parent.map(|parent| history.resolve(parent));
+            let before = world.get::<Parent>(entity).map(|parent| parent.0);
+            let transform = *world.get::<Transform>(entity)?;
+            (
+                format!("Reparent {}", name_of(world, entity)),
+                Edit::Reparent {
+                    entity,
+                    before: (before, transform),
+                    after: (parent, transform_under(world, entity, parent)),
+                },
+            )
+        }
+        EditorCommand::Duplicate(entity) => {
+            let entity = history.resolve(entity);
+            world.get_entity(entity)?;
+            let parent = world.get::<Parent>(entity).map(|parent| parent.0);
+            let mut original = snapshot(world, registry, entity);
+            if let Some(transform) = snapped_copy(world, entity, parent) {
+                for component in original.components.iter_mut() {
+                    if component.type_name() == transform.type_name() {
+                        *component = Box::new(transform);
+                    }
+                }
+            }
+            let copy = spawn_copy(world, registry, &original, parent);
+            if let Some(mut selection) = world.get_resource_mut::<EditorSelection>() {
+                selection.entity = Some(copy.entity);
+            }
+            return Some(HistoryEntry {
+                label: format!("Duplicate {}", name_of(world, entity)),
+                edit: Edit::Spawn {
+                    snapshot: copy,
+                    parent,
+                },
+            });
+        }
+        EditorCommand::Set { entity, value } => {
+            let entity = history.resolve(entity);
+            world.get_entity(entity)?;
+            let before = components_of(world, registry, entity)
+                .into_iter()
+                .find(|component| component.type_name() == value.type_name());
+            (
+                format!(
+                    "Set {} on {}",
+                    short_name(value.type_name()),
+                    name_of(world, entity)
+                ),
+                Edit::Component {
+                    entity,
+                    before,
+                    after: value,
+                },
+            )
+        }
+    };
+    let entry = HistoryEntry { label, edit };
+    redo(world, registry, history, &entry.edit);
+    Some(entry)
+}
+
+fn undo(world: &mut World, registry: &TypeRegistry, history: &mut EditorHistory, edit: &Edit) {
+    match edit {
+        Edit::Component {
+            entity,
+            before,
+            after,
+        } => {
+            let entity = history.resolve(*entity);
+            match before {
+                Some(before) => set_component(world, registry, entity, before.as_ref()),
+                None => remove_component(world, registry, entity, after.type_name()),
+            }
+        }
+        Edit::Spawn { snapshot, .. } => {
+            let entity = history.resolve(snapshot.entity);
+            if world.get_entity(entity).is_some() {
+                despawn_with_children_recursive(world, entity);
+            }
+        }
+        Edit::Despawn { snapshot, parent } => {
+            let parent = parent.map(|parent| history.resolve(parent));
+            restore(world, registry, history, snapshot, parent);
+        }
+        Edit::Reparent { entity, before, .. } => {
+            let entity = history.resolve(*entity);
+            let parent = before.0.map(|parent| history.resolve(parent));
+            set_parent(world, entity, parent, before.1);
+        }
+    }
+}
+
+fn redo(world: &mut World, registry: &TypeRegistry, history: &mut EditorHistory, edit: &Edit) {
+    match edit {
+        Edit::Component { entity, after, .. } => {
+            let entity = history.resolve(*entity);
+            set_component(world, registry, entity, after.as_ref());
+        }
+        Edit::Spawn { snapshot, parent } => {
+            let parent = parent.map(|parent| history.resolve(parent));
+            restore(world, registry, history, snapshot, parent);
+        }
+        Edit::Despawn { snapshot, .. } => {
+            let entity = history.resolve(snapshot.entity);
+            if world.get_entity(entity).is_some() {
+                despawn_with_children_recursive(world, entity);
+            }
+        }
+        Edit::Reparent { entity, after, .. } => {
+            let entity = history.resolve(*entity);
+            let parent = after.0.map(|parent| history.resolve(parent));
+            set_parent(world, entity, parent, after.1);
+        }
+    }
+}
+
+/// Step the history until `target` edits are applied
+fn jump(world: &mut World, registry: &TypeRegistry, history: &mut EditorHistory, target: usize) {
+    let target = target.min(history.entries.len());
+    // entries are taken out while they are applied, so the history can be borrowed alongside
+    let entries = std::mem::take(&mut history.entries);
+    while history.position > target {
+        history.position -= 1;
+        undo(world, registry, history, &entries[history.position].edit);
+    }
+    while history.position < target {
+        redo(world, registry, history, &entries[history.position].edit);
+        history.position += 1;
+    }
+    history.entries = entries;
+}
+
+/// Record any change to the selected entity since it last settled, changes are held back while
+/// a mouse button is down so a whole drag becomes one edit
+fn track_selection(world: &mut World, registry: &TypeRegistry, history: &mut EditorHistory) {
+    let selected = world
+        .get_resource::<EditorSelection>()
+        .and_then(|selection| selection.entity)
+        .filter(|entity| world.get_entity(*entity).is_some());
+    let entity = match selected {
+        Some(entity) => entity,
+        None => {
+            history.tracked = None;
+            return;
+        }
+    };
+
+    let held = world
+        .get_resource::<Input<MouseButton>>()
+        .map_or(false, |mouse| mouse.get_pressed().next().is_some());
+    if held && history.tracked.as_ref().map(|t| t.entity) == Some(entity) {
+        return;
+    }
+
+    let values: Vec<(Box<dyn Reflect>, String)> = components_of(world, registry, entity)
+        .into_iter()
+        .filter_map(|value| {
+            let ron = to_ron(registry, value.as_ref())?;
+            Some((value, ron))
+        })
+        .collect();
+
+    if let Some(tracked) = history.tracked.take().filter(|t| t.entity == entity) {
+        for (value, ron) in values.iter() {
+            let before = tracked
+                .values
+                .iter()
+                .find(|(old, _)| old.type_name() == value.type_name());
+            if let Some((old, old_ron)) = before {
+                if old_ron != ron {
+                    history.push(HistoryEntry {
+                        label: format!(
+                            "Edit {} on {}",
+                            short_name(value.type_name()),
+                            name_of(world, entity)
+                        ),
+                        edit: Edit::Component {
+                            entity,
+                            before: Some(old.clone_value()),
+                            after: value.clone_value(),
+                        },
+                    });
+                }
+            }
+        }
+    }
+    history.tracked = Some(Tracked { entity, values });
+}
+
+fn run_history(world: &mut World) {
+    let commands: Vec<EditorCommand> = world
+        .get_resource_mut::<Events<EditorCommand>>()
+        .expect("Events<EditorCommand> not found.")
+        .drain()
+        .collect();
+    let requests: Vec<HistoryRequest> = world
+        .get_resource_mut::<Events<HistoryRequest>>()
+        .expect("Events<HistoryRequest> not found.")
+        .drain()
+        .collect();
+
+    let registry = world
+        .get_resource::<TypeRegistryArc>()
+        .expect("TypeRegistryArc not found.")
+        .clone();
+    let registry = registry.read();
+    // taken out of the world so edits can change the world while we hold it
+    let mut history = std::mem::take(
+        &mut *world
+            .get_resource_mut::<EditorHistory>()
+            .expect("EditorHistory not found."),
+    );
+
+    // settle any edit in progress first, so it lands before whatever happens next
+    track_selection(world, &registry, &mut history);
+
+    for command in commands {
+        if let Some(entry) = execute(world, &registry, &mut history, command) {
+            history.push(entry);
+        }
+    }
+
+    for request in requests {
+        let target = match request {
+            HistoryRequest::Undo => history.position.saturating_sub(1),
+            HistoryRequest::Redo => history.position + 1,
+            HistoryRequest::JumpTo(target) => target,
+        };
+        jump(world, &registry, &mut history, target);
+    }
+
+    // whatever we just did shouldn't be picked up as an edit of its own
+    history.tracked = None;
+    track_selection(world, &registry, &mut history);
+
+    *world
+        .get_resource_mut::<EditorHistory>()
+        .expect("EditorHistory not found.") = history;
+}
+
+fn draw_history_window(
+    egui_ctx: Res<EguiContext>,
+    mut history: ResMut<EditorHistory>,
+    mut ev_request: EventWriter<HistoryRequest>,
+) {
+    let mut show = history.show;
+    Window::new("History")
+        .open(&mut show)
+        .scroll(true)
+        .show(egui_ctx.ctx(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Undo").clicked() && history.can_undo() {
+                    ev_request.send(HistoryRequest::Undo);
+                }
+                if ui.button("Redo").clicked() && history.can_redo() {
+                    ev_request.send(HistoryRequest::Redo);
+                }
+            });
+            ui.separator();
+            if ui
+                .selectable_label(history.position == 0, "Start")
+                .clicked()
+            {
+                ev_request.send(HistoryRequest::JumpTo(0));
+            }
+            // undone edits are in brackets until something new replaces them
+            for (i, entry) in history.entries.iter().enumerate() {
+                let text = if i < history.position {
+                    entry.label.clone()
+                } else {
+                    format!("({})", entry.label)
+                };
+                if ui
+                    .selectable_label(history.position == i + 1, text)
+                    .clicked()
+                {
+                    ev_request.send(HistoryRequest::JumpTo(i + 1));
+                }
+            }
+        });
+    history.show = show;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (World, TypeRegistryArc, EditorHistory) {
+        let registry = TypeRegistryArc::default();
+        registry.write().register::<Transform>();
+        (World::default(), registry, EditorHistory::default())
+    }
+
+    fn run(
+        world: &mut World,
+        registry: &TypeRegistryArc,
+        history: &mut EditorHistory,
+        command: EditorCommand,
+    ) {
+        if let Some(entry) = execute(world, &registry.read(), history, command) {
+            history.push(entry);
+        }
+    }
+
+    /// Where every entity with a transform is along x
+    fn positions(world: &mut World) -> Vec<f32> {
+        let mut query = world.query::<&Transform>();
+        let mut positions: Vec<f32> = query.iter(world).map(|t| t.translation.x).collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        positions
+    }
+
+    fn at(x: f32) -> Box<dyn Reflect> {
+        Box::new(Transform::from_xyz(x, 0.0, 0.0))
+    }
+
+    #[test]
+    fn edits_after_a_redo_follow_a_spawned_entity() {
+        let (mut world, registry, mut history) = setup();
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Spawn {
+                label: "Spawn".to_string(),
+                components: vec![at(1.0)],
+                parent: None,
+            },
+        );
+        let spawned = world
+            .query::<(Entity, &Transform)>()
+            .iter(&world)
+            .next()
+            .unwrap()
+            .0;
+
+        jump(&mut world, &registry.read(), &mut history, 0);
+        assert!(positions(&mut world).is_empty());
+        jump(&mut world, &registry.read(), &mut history, 1);
+        assert_eq!(positions(&mut world), vec![1.0]);
+
+        // the redo brought it back under a new id, which is what the editor sees now
+        let current = history.resolve(spawned);
+        assert!(world.get_entity(current).is_some());
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Set {
+                entity: current,
+                value: at(5.0),
+            },
+        );
+        assert_eq!(positions(&mut world), vec![5.0]);
+
+        jump(&mut world, &registry.read(), &mut history, 0);
+        assert!(positions(&mut world).is_empty());
+        jump(&mut world, &registry.read(), &mut history, 2);
+        assert_eq!(positions(&mut world), vec![5.0]);
+        jump(&mut world, &registry.read(), &mut history, 1);
+        assert_eq!(positions(&mut world), vec![1.0]);
+    }
+
+    #[test]
+    fn edits_follow_an_entity_despawned_and_brought_back() {
+        let (mut world, registry, mut history) = setup();
+        let entity = world
+            .spawn()
+            .insert(Transform::from_xyz(1.0, 0.0, 0.0))
+            .id();
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Set {
+                entity,
+                value: at(2.0),
+            },
+        );
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Despawn(entity),
+        );
+        assert!(positions(&mut world).is_empty());
+
+        for (target, expected) in [
+            (1, vec![2.0]),
+            (2, vec![]),
+            (1, vec![2.0]),
+            (0, vec![1.0]),
+            (2, vec![]),
+            (1, vec![2.0]),
+        ] {
+            jump(&mut world, &registry.read(), &mut history, target);
+            assert_eq!(positions(&mut world), expected, "jumping to {}", target);
+        }
+    }
+
+    #[test]
+    fn edits_on_entities_gone_from_elsewhere_are_skipped() {
+        let (mut world, registry, mut history) = setup();
+        let entity = world.spawn().insert(Transform::default()).id();
+        let parent = world.spawn().id();
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Set {
+                entity,
+                value: at(3.0),
+            },
+        );
+        run(
+            &mut world,
+            &registry,
+            &mut history,
+            EditorCommand::Reparent {
+                entity,
+                parent: Some(parent),
+            },
+        );
+
+        // despawned without going through the history, a scene being opened say
+        world.despawn(entity);
+        jump(&mut world, &registry.read(), &mut history, 0);
+        jump(&mut world, &registry.read(), &mut history, 2);
+        assert!(positions(&mut world).is_empty());
+    }
+}
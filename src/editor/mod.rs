@@ -1,14 +1,31 @@
+pub mod asset_browser;
 pub mod camera;
+pub mod console;
+pub mod gizmo;
 pub mod grid;
+pub mod hierarchy;
+pub mod history;
+pub mod playback;
+pub mod scene;
 pub mod ui;
+pub mod viewports;
 
 use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use bevy_egui::EguiContext;
 use bevy_input_actionmap::{ActionPlugin, InputMap};
 use bevy_inspector_egui::{WorldInspectorParams, WorldInspectorPlugin};
 use std::fmt;
+pub use asset_browser::*;
 pub use camera::*;
+pub use console::*;
+pub use gizmo::*;
 pub use grid::*;
+pub use hierarchy::*;
+pub use history::*;
+pub use playback::*;
+pub use scene::*;
 pub use ui::*;
+pub use viewports::*;
 
 use crate::{GameStages, actions::ActionsWindow};
 
@@ -42,6 +59,14 @@ impl Plugin for EditorPlugin {
         .add_plugin(CameraPlugin)
         .add_plugin(GridPlugin)
         .add_plugin(UIPlugin)
+        .add_plugin(ScenePlugin)
+        .add_plugin(GizmoPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(AssetBrowserPlugin)
+        .add_plugin(PlaybackPlugin)
+        .add_plugin(ViewportsPlugin)
+        .add_plugin(ConsolePlugin)
         .add_startup_system(setup.system())
         .add_system(run_actions.system())
         .add_system_set(SystemSet::on_update(EditorState::Loading).with_system(loaded.system()));
@@ -53,6 +78,15 @@ pub enum EditorAction {
     Editor,
     World,
     Keys,
+    GizmoTranslate,
+    GizmoRotate,
+    GizmoScale,
+    GizmoSpace,
+    Undo,
+    Redo,
+    Snap,
+    Maximize,
+    Console,
 }
 
 impl fmt::Display for EditorAction {
@@ -61,6 +95,15 @@ impl fmt::Display for EditorAction {
             EditorAction::Editor => write!(f, "Toggle Editor"),
             EditorAction::World => write!(f, "Open World Inspector"),
             EditorAction::Keys => write!(f, "Toggle Key Mappings"),
+            EditorAction::GizmoTranslate => write!(f, "Gizmo - Translate"),
+            EditorAction::GizmoRotate => write!(f, "Gizmo - Rotate"),
+            EditorAction::GizmoScale => write!(f, "Gizmo - Scale"),
+            EditorAction::GizmoSpace => write!(f, "Gizmo - Toggle Local/World"),
+            EditorAction::Undo => write!(f, "Undo"),
+            EditorAction::Redo => write!(f, "Redo"),
+            EditorAction::Snap => write!(f, "Hold to Toggle Snapping"),
+            EditorAction::Maximize => write!(f, "Maximize View / Quad View"),
+            EditorAction::Console => write!(f, "Toggle Console"),
         }
     }
 }
@@ -69,6 +112,17 @@ fn setup(mut input_map: ResMut<InputMap<EditorAction>>) {
     input_map.bind(EditorAction::Editor, KeyCode::F12);
     input_map.bind(EditorAction::World, KeyCode::F11);
     input_map.bind(EditorAction::Keys, KeyCode::F10);
+    input_map.bind(EditorAction::GizmoTranslate, KeyCode::T);
+    input_map.bind(EditorAction::GizmoRotate, KeyCode::R);
+    input_map.bind(EditorAction::GizmoScale, KeyCode::Y);
+    input_map.bind(EditorAction::GizmoSpace, KeyCode::G);
+    input_map.bind(EditorAction::Undo, vec![KeyCode::LControl, KeyCode::Z]);
+    input_map.bind(EditorAction::Undo, vec![KeyCode::RControl, KeyCode::Z]);
+    input_map.bind(EditorAction::Redo, vec![KeyCode::LControl, KeyCode::LShift, KeyCode::Z]);
+    input_map.bind(EditorAction::Redo, vec![KeyCode::RControl, KeyCode::RShift, KeyCode::Z]);
+    input_map.bind(EditorAction::Snap, KeyCode::LAlt);
+    input_map.bind(EditorAction::Maximize, KeyCode::M);
+    input_map.bind(EditorAction::Console, KeyCode::Grave);
 }
 
 fn run_actions(
@@ -76,6 +130,13 @@ fn run_actions(
      mut state: ResMut<State<EditorState>>,
      mut world_inspection: ResMut<WorldInspectorParams>,
      mut action_window: ResMut<ActionsWindow>,
+     mut gizmo: ResMut<Gizmo>,
+     mut ev_history: EventWriter<HistoryRequest>,
+     mut grid: ResMut<GridData>,
+     mut viewports: ResMut<EditorViewports>,
+     windows: Res<Windows>,
+     egui_ctx: Res<EguiContext>,
+     mut console: ResMut<ConsoleWindow>,
     ) {
     if input_map.just_active(EditorAction::Editor) {
         let result = match state.current() {
@@ -94,6 +155,47 @@ fn run_actions(
     if input_map.just_active(EditorAction::Keys) {
         action_window.enabled = !action_window.enabled;
     }
+
+    // gizmo keys only mean something while the editor is up
+    if *state.current() == EditorState::Playing {
+        if input_map.just_active(EditorAction::Console) {
+            console.toggle();
+        }
+        let snap_held = input_map.active(EditorAction::Snap);
+        if grid.snap_held != snap_held {
+            grid.snap_held = snap_held;
+        }
+        // letters typed into the console or a text box aren't shortcuts
+        if egui_ctx.ctx().wants_keyboard_input() {
+            return;
+        }
+        if input_map.just_active(EditorAction::GizmoTranslate) {
+            gizmo.mode = GizmoMode::Translate;
+        }
+        if input_map.just_active(EditorAction::GizmoRotate) {
+            gizmo.mode = GizmoMode::Rotate;
+        }
+        if input_map.just_active(EditorAction::GizmoScale) {
+            gizmo.mode = GizmoMode::Scale;
+        }
+        if input_map.just_active(EditorAction::GizmoSpace) {
+            gizmo.toggle_space();
+        }
+        // ctrl+z is held as part of redo too, so redo wins
+        if input_map.just_active(EditorAction::Redo) {
+            ev_history.send(HistoryRequest::Redo);
+        } else if input_map.just_active(EditorAction::Undo) {
+            ev_history.send(HistoryRequest::Undo);
+        }
+        if input_map.just_active(EditorAction::Maximize) {
+            let under_cursor = windows
+                .get_primary()
+                .and_then(|w| w.cursor_position())
+                .and_then(|cursor| viewports.at(cursor))
+                .map(|viewport| viewport.view);
+            viewports.layout = viewports.layout.toggled(under_cursor);
+        }
+    }
 }
 
 // first frame after 'Loading', we set state to 'Playing'
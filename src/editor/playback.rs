@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+
+use crate::GameStages;
+
+use super::{ConsoleCommand, EditorState, RegisterConsoleCommand};
+
+/// Pause, step and slow down gameplay from the editor. Gameplay systems live in the
+/// `GameStages::Game` stage, which only runs while the game is playing, and read `GameTime`
+/// instead of `Time` so they can be slowed down. Everything else, the editor, picking and the
+/// game's own menus, keeps running on real time
+pub struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Playback::default())
+            .insert_resource(GameTime::default())
+            .add_system_to_stage(
+                GameStages::Game,
+                tick_game_time.exclusive_system().at_start(),
+            )
+            .add_system_to_stage(GameStages::Game, finish_step.exclusive_system().at_end())
+            .register_console_command(ConsoleCommand::new(
+                "pause",
+                "Pause or resume gameplay",
+                toggle_pause,
+            ))
+            // nothing to unpause with once the editor is gone
+            .add_system_set(SystemSet::on_exit(EditorState::Playing).with_system(resume.system()));
+    }
+}
+
+/// Time moved on by when stepping a paused game, before scaling
+const STEP: f32 = 1.0 / 60.0;
+
+pub struct Playback {
+    pub paused: bool,
+    /// Run gameplay for a single frame while paused
+    pub step: bool,
+    /// How fast gameplay runs, 1.0 is normal speed
+    pub time_scale: f32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            paused: false,
+            step: false,
+            time_scale: 1.0,
+        }
+    }
+}
+
+impl Playback {
+    pub fn is_running(&self) -> bool {
+        !self.paused || self.step
+    }
+
+    /// Game time passed for a frame that took this long
+    pub fn scaled(&self, delta: Duration) -> Duration {
+        let delta = if self.step {
+            Duration::from_secs_f32(STEP)
+        } else {
+            delta
+        };
+        delta.mul_f32(self.time_scale.max(0.0))
+    }
+}
+
+/// Like `Time`, but paused and scaled by the editor. Gameplay should read this one
+#[derive(Default)]
+pub struct GameTime {
+    delta: Duration,
+    seconds_since_startup: f64,
+}
+
+impl GameTime {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Only counts time spent playing
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.seconds_since_startup
+    }
+
+    fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.seconds_since_startup += delta.as_secs_f64();
+    }
+}
+
+/// Run criteria for the gameplay stage
+pub fn run_if_game_running(playback: Res<Playback>) -> ShouldRun {
+    if playback.is_running() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn tick_game_time(time: Res<Time>, playback: Res<Playback>, mut game_time: ResMut<GameTime>) {
+    game_time.advance(playback.scaled(time.delta()));
+}
+
+fn finish_step(mut playback: ResMut<Playback>) {
+    if playback.step {
+        playback.step = false;
+    }
+}
+
+fn resume(mut playback: ResMut<Playback>) {
+    *playback = Playback::default();
+}
+
+fn toggle_pause(world: &mut World, _args: &[String]) -> Result<String> {
+    let mut playback = world
+        .get_resource_mut::<Playback>()
+        .ok_or_else(|| anyhow!("Playback not found"))?;
+    playback.paused = !playback.paused;
+    Ok(if playback.paused { "Paused" } else { "Playing" }.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_games_only_run_to_step() {
+        let mut playback = Playback::default();
+        assert!(playback.is_running());
+        playback.paused = true;
+        assert!(!playback.is_running());
+        playback.step = true;
+        assert!(playback.is_running());
+    }
+
+    #[test]
+    fn time_is_scaled() {
+        let frame = Duration::from_millis(100);
+        let mut playback = Playback {
+            time_scale: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(playback.scaled(frame), Duration::from_millis(50));
+        // steps are the same length however long the frame took
+        playback.step = true;
+        assert_eq!(
+            playback.scaled(frame),
+            Duration::from_secs_f32(STEP).mul_f32(0.5)
+        );
+
+        let mut game_time = GameTime::default();
+        game_time.advance(Duration::from_millis(50));
+        game_time.advance(Duration::from_millis(50));
+        assert_eq!(game_time.delta_seconds(), 0.05);
+        assert!((game_time.seconds_since_startup() - 0.1).abs() < 0.0001);
+    }
+}
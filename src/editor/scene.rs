@@ -0,0 +1,573 @@
+use std::{any::TypeId, collections::HashSet, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use bevy::{
+    ecs::component::Component,
+    prelude::*,
+    reflect::{GetTypeRegistration, TypeRegistryArc},
+    scene::serde::SceneDeserializer,
+    transform::hierarchy::despawn_with_children_recursive,
+};
+use bevy_egui::{
+    egui::{TextEdit, Window},
+    EguiContext,
+};
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
+
+use crate::{ConfigPath, GameStages};
+
+use super::{EditorHistory, EditorState};
+
+/// Saves the game's entities to `.scn.ron` files and opens them again, from the editor's App
+/// menu. Only entities with a component registered through `register_scene_component` are
+/// saved, anything marked `EditorOnly` is left out
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SceneEvent>()
+            .insert_resource(SceneWindow::default())
+            // assets that were loaded from a file keep the same id between runs, so meshes
+            // and materials come back with the entities, meshes built in code are saved as a
+            // `SceneMesh` and colours as a `SceneColor` instead
+            .register_type::<Handle<Mesh>>()
+            .register_type::<Handle<ColorMaterial>>()
+            .register_type::<Handle<StandardMaterial>>()
+            .register_scene_component::<SceneMesh>()
+            .register_scene_component::<SceneColor>()
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(draw_scene_window.system())
+                    .with_system(run_scene_events.exclusive_system()),
+            )
+            // same place models get their scene, after anything opened has spawned
+            .add_system_to_stage(CoreStage::Last, build_scene_meshes.system())
+            .add_system_to_stage(CoreStage::Last, build_scene_colors.system());
+    }
+}
+
+/// Marker for entities that belong to the editor, like the grid and its camera, these and their
+/// children never end up in a scene
+pub struct EditorOnly;
+
+/// Components that make an entity part of the game's scene
+#[derive(Default)]
+pub struct SceneComponents(HashSet<TypeId>);
+
+pub trait RegisterSceneComponent {
+    /// Register reflection for a component, and save any entity that has it in scenes
+    fn register_scene_component<T: Component + GetTypeRegistration>(&mut self) -> &mut Self;
+}
+
+impl RegisterSceneComponent for AppBuilder {
+    fn register_scene_component<T: Component + GetTypeRegistration>(&mut self) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(SceneComponents::default)
+            .0
+            .insert(TypeId::of::<T>());
+        self.register_type::<T>()
+    }
+}
+
+/// Mesh built in code rather than loaded from a file. A handle to an added asset gets a new id
+/// every run, so the shape is saved instead and the mesh is built again when the entity spawns
+/// without one
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
+pub enum SceneMesh {
+    Box { x: f32, y: f32, z: f32 },
+    Plane { size: f32 },
+    Icosphere { radius: f32, subdivisions: usize },
+}
+
+impl Default for SceneMesh {
+    fn default() -> Self {
+        SceneMesh::Box {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        }
+    }
+}
+
+impl SceneMesh {
+    /// Unit sized shapes the asset browser can place
+    pub const SHAPES: [SceneMesh; 3] = [
+        SceneMesh::Box {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        },
+        SceneMesh::Plane { size: 1.0 },
+        SceneMesh::Icosphere {
+            radius: 0.5,
+            subdivisions: 3,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SceneMesh::Box { .. } => "Box",
+            SceneMesh::Plane { .. } => "Plane",
+            SceneMesh::Icosphere { .. } => "Sphere",
+        }
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        match *self {
+            SceneMesh::Box { x, y, z } => Mesh::from(shape::Box::new(x, y, z)),
+            SceneMesh::Plane { size } => Mesh::from(shape::Plane { size }),
+            SceneMesh::Icosphere {
+                radius,
+                subdivisions,
+            } => Mesh::from(shape::Icosphere {
+                radius,
+                subdivisions,
+            }),
+        }
+    }
+}
+
+/// Build the mesh for shapes that were placed or opened without one, keeping where they are
+fn build_scene_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, &SceneMesh, Option<&Transform>), Without<Handle<Mesh>>>,
+) {
+    for (entity, scene_mesh, transform) in query.iter() {
+        commands.entity(entity).insert_bundle(PbrBundle {
+            mesh: meshes.add(scene_mesh.mesh()),
+            material: materials.add(StandardMaterial::default()),
+            transform: transform.copied().unwrap_or_default(),
+            ..Default::default()
+        });
+    }
+}
+
+/// Colour material built in code, saved as its colour for the same reason as [`SceneMesh`], the
+/// material is built again when the entity spawns without one
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct SceneColor {
+    pub color: Color,
+}
+
+/// Build the material for sprites that were opened without one
+fn build_scene_colors(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &SceneColor), Without<Handle<ColorMaterial>>>,
+) {
+    for (entity, scene_color) in query.iter() {
+        commands
+            .entity(entity)
+            .insert(materials.add(scene_color.color.into()));
+    }
+}
+
+pub enum SceneEvent {
+    Save(String),
+    Open(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneMode {
+    Save,
+    Open,
+}
+
+pub struct SceneWindow {
+    pub open: bool,
+    pub mode: SceneMode,
+    pub name: String,
+    /// Scenes already saved, refreshed when the window opens
+    pub files: Vec<String>,
+    /// Scene last saved or opened
+    pub current: Option<String>,
+    /// Result of the last save or open
+    pub message: Option<String>,
+}
+
+impl Default for SceneWindow {
+    fn default() -> Self {
+        SceneWindow {
+            open: false,
+            mode: SceneMode::Save,
+            name: "scene".to_string(),
+            files: Vec::new(),
+            current: None,
+            message: None,
+        }
+    }
+}
+
+impl SceneWindow {
+    pub fn show(&mut self, mode: SceneMode, config: &ConfigPath) {
+        self.open = true;
+        self.mode = mode;
+        self.files = list_scenes(config);
+    }
+}
+
+const SCENE_EXTENSION: &str = ".scn.ron";
+
+fn scenes_dir(config: &ConfigPath) -> PathBuf {
+    [config.path.clone(), "scenes".to_string()].iter().collect()
+}
+
+pub fn scene_path(config: &ConfigPath, name: &str) -> PathBuf {
+    scenes_dir(config).join(format!("{}{}", name, SCENE_EXTENSION))
+}
+
+/// Names of the saved scenes, without the extension
+pub fn list_scenes(config: &ConfigPath) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(scenes_dir(config))
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    name.strip_suffix(SCENE_EXTENSION)
+                        .map(|name| name.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn draw_scene_window(
+    egui_ctx: Res<EguiContext>,
+    mut window: ResMut<SceneWindow>,
+    mut ev_scene: EventWriter<SceneEvent>,
+) {
+    let mut open = window.open;
+    let title = match window.mode {
+        SceneMode::Save => "Save Scene",
+        SceneMode::Open => "Open Scene",
+    };
+
+    Window::new(title)
+        .open(&mut open)
+        .show(egui_ctx.ctx(), |ui| {
+            let mut picked = None;
+            for file in window.files.iter() {
+                if ui
+                    .selectable_label(*file == window.name, file.as_str())
+                    .clicked()
+                {
+                    picked = Some(file.clone());
+                }
+            }
+            if let Some(file) = picked {
+                window.name = file;
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(&mut window.name).desired_width(150.0));
+                let name = window.name.trim().to_string();
+                if ui.button(title).clicked() && !name.is_empty() {
+                    ev_scene.send(match window.mode {
+                        SceneMode::Save => SceneEvent::Save(name),
+                        SceneMode::Open => SceneEvent::Open(name),
+                    });
+                    window.open = false;
+                }
+            });
+            if let Some(message) = &window.message {
+                ui.label(message);
+            }
+        });
+
+    window.open &= open;
+}
+
+fn run_scene_events(world: &mut World) {
+    let events: Vec<SceneEvent> = world
+        .get_resource_mut::<Events<SceneEvent>>()
+        .expect("Events<SceneEvent> not found.")
+        .drain()
+        .collect();
+
+    for event in events {
+        let result = match &event {
+            SceneEvent::Save(name) => save_scene(world, name),
+            SceneEvent::Open(name) => open_scene(world, name),
+        };
+        let mut window = world
+            .get_resource_mut::<SceneWindow>()
+            .expect("SceneWindow not found.");
+        let message = match (event, result) {
+            (SceneEvent::Save(name), Ok(count)) => {
+                let message = format!("Saved {} entities to {}", count, name);
+                window.current = Some(name);
+                message
+            }
+            (SceneEvent::Open(name), Ok(count)) => {
+                let message = format!("Opened {} entities from {}", count, name);
+                window.current = Some(name);
+                message
+            }
+            // back to the window so the name can be fixed
+            (SceneEvent::Save(name), Err(e)) => {
+                window.open = true;
+                format!("Failed to save {}: {}", name, e)
+            }
+            (SceneEvent::Open(name), Err(e)) => {
+                window.open = true;
+                format!("Failed to open {}: {}", name, e)
+            }
+        };
+        println!("{}", message);
+        window.message = Some(message);
+    }
+}
+
+/// Entities that belong in a scene, they have a scene component and aren't part of the editor
+fn scene_entities(world: &mut World) -> Vec<Entity> {
+    let components = match world.get_resource::<SceneComponents>() {
+        Some(components) => components.0.clone(),
+        None => return Vec::new(),
+    };
+
+    let mut query = world.query::<Entity>();
+    let entities: Vec<Entity> = query.iter(world).collect();
+    entities
+        .into_iter()
+        .filter(|entity| {
+            let entity = world.entity(*entity);
+            components.iter().any(|id| entity.contains_type_id(*id))
+        })
+        .filter(|entity| !is_editor_only(world, *entity))
+        .collect()
+}
+
+/// Marked `EditorOnly`, or under something that is
+pub fn is_editor_only(world: &World, entity: Entity) -> bool {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if world.get::<EditorOnly>(entity).is_some() {
+            return true;
+        }
+        current = world.get::<Parent>(entity).map(|parent| parent.0);
+    }
+    false
+}
+
+fn save_scene(world: &mut World, name: &str) -> Result<usize> {
+    let entities = scene_entities(world);
+    let saved: HashSet<u32> = entities.iter().map(|entity| entity.id()).collect();
+    let registry = world
+        .get_resource::<TypeRegistryArc>()
+        .ok_or_else(|| anyhow!("no type registry"))?
+        .clone();
+
+    let mut scene = DynamicScene::from_world(world, &registry);
+    scene
+        .entities
+        .retain(|entity| saved.contains(&entity.entity));
+
+    // children are rebuilt from their parents when the scene spawns, parents outside the scene
+    // would have nothing to point at
+    let children = std::any::type_name::<Children>();
+    let previous = std::any::type_name::<PreviousParent>();
+    let parent = std::any::type_name::<Parent>();
+    // shapes get a new mesh and material when they spawn, and colours a new colour material
+    let mesh = std::any::type_name::<Handle<Mesh>>();
+    let material = std::any::type_name::<Handle<StandardMaterial>>();
+    let color_material = std::any::type_name::<Handle<ColorMaterial>>();
+    for scene_entity in scene.entities.iter_mut() {
+        let entity = Entity::new(scene_entity.entity);
+        let keep_parent = world
+            .get::<Parent>(entity)
+            .map_or(false, |parent| saved.contains(&parent.0.id()));
+        let shape = world.get::<SceneMesh>(entity).is_some();
+        let colored = world.get::<SceneColor>(entity).is_some();
+        scene_entity.components.retain(|component| {
+            let name = component.type_name();
+            name != children
+                && name != previous
+                && (name != parent || keep_parent)
+                && !(shape && (name == mesh || name == material))
+                && !(colored && name == color_material)
+        });
+    }
+
+    let config = world
+        .get_resource::<ConfigPath>()
+        .ok_or_else(|| anyhow!("no config path"))?;
+    let path = scene_path(config, name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, scene.serialize_ron(&registry)?)?;
+    Ok(scene.entities.len())
+}
+
+/// Replaces the entities in the current scene with the ones in the file
+fn open_scene(world: &mut World, name: &str) -> Result<usize> {
+    let config = world
+        .get_resource::<ConfigPath>()
+        .ok_or_else(|| anyhow!("no config path"))?;
+    let text = std::fs::read_to_string(scene_path(config, name))?;
+    let registry = world
+        .get_resource::<TypeRegistryArc>()
+        .ok_or_else(|| anyhow!("no type registry"))?
+        .clone();
+
+    let mut deserializer = ron::de::Deserializer::from_str(&text)?;
+    let scene = SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)?;
+    let count = scene.entities.len();
+
+    for entity in scene_entities(world) {
+        if world.get_entity(entity).is_some() {
+            despawn_with_children_recursive(world, entity);
+        }
+    }
+    // edits point at the entities that were just despawned
+    if let Some(mut history) = world.get_resource_mut::<EditorHistory>() {
+        history.clear();
+    }
+
+    let handle = world
+        .get_resource_mut::<Assets<DynamicScene>>()
+        .ok_or_else(|| anyhow!("no scene assets"))?
+        .add(scene);
+    world
+        .get_resource_mut::<SceneSpawner>()
+        .ok_or_else(|| anyhow!("no scene spawner"))?
+        .spawn_dynamic(handle);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default, Reflect)]
+    #[reflect(Component)]
+    struct Piece {
+        size: u32,
+    }
+
+    fn scene_app(config: ConfigPath) -> App {
+        let mut app = App::build();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::transform::TransformPlugin::default())
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_plugin(bevy::scene::ScenePlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<ColorMaterial>()
+            .register_type::<Handle<Mesh>>()
+            .register_type::<Handle<ColorMaterial>>()
+            .register_type::<Handle<StandardMaterial>>()
+            .register_scene_component::<SceneMesh>()
+            .register_scene_component::<SceneColor>()
+            .register_scene_component::<Piece>()
+            .insert_resource(config);
+        app.app
+    }
+
+    #[test]
+    fn save_then_open_brings_back_the_entities() {
+        let config = ConfigPath {
+            path: std::env::temp_dir()
+                .join(format!("editor_scene_test_{}", std::process::id()))
+                .into_os_string()
+                .into_string()
+                .unwrap(),
+        };
+        let mut app = scene_app(config);
+        let world = &mut app.world;
+
+        let shape = SceneMesh::Icosphere {
+            radius: 2.0,
+            subdivisions: 2,
+        };
+        let mesh = world
+            .get_resource_mut::<Assets<Mesh>>()
+            .unwrap()
+            .add(shape.mesh());
+        let color = SceneColor {
+            color: Color::rgb(0.2, 0.4, 0.6),
+        };
+        let material = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .unwrap()
+            .add(color.color.into());
+        world
+            .spawn()
+            .insert(Piece { size: 3 })
+            .insert(Transform::from_xyz(1.0, 2.0, 3.0))
+            .insert(shape)
+            .insert(mesh.clone())
+            .insert(color)
+            .insert(material.clone());
+        let editor_only = world
+            .spawn()
+            .insert(Piece { size: 9 })
+            .insert(EditorOnly)
+            .id();
+
+        let saved = save_scene(world, "round_trip");
+        let text = std::fs::read_to_string(scene_path(
+            world.get_resource::<ConfigPath>().unwrap(),
+            "round_trip",
+        ));
+        let opened = open_scene(world, "round_trip");
+        world
+            .resource_scope(|world, mut spawner: Mut<SceneSpawner>| {
+                spawner.spawn_queued_scenes(world)
+            })
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&world.get_resource::<ConfigPath>().unwrap().path);
+
+        assert_eq!(saved.unwrap(), 1);
+        assert_eq!(opened.unwrap(), 1);
+        // the handle would point at nothing next run
+        let text = text.unwrap();
+        assert!(text.contains("Icosphere"));
+        assert!(!text.contains(std::any::type_name::<Handle<Mesh>>()));
+        assert!(!text.contains(std::any::type_name::<Handle<ColorMaterial>>()));
+
+        let mut stage = SystemStage::single(build_scene_meshes.system());
+        stage.run(world);
+        let mut stage = SystemStage::single(build_scene_colors.system());
+        stage.run(world);
+
+        let mut query = world.query::<(Entity, &Piece, &SceneMesh, &Transform, &Handle<Mesh>)>();
+        let opened: Vec<_> = query.iter(world).collect();
+        assert_eq!(opened.len(), 1);
+        let (entity, piece, scene_mesh, transform, handle) = opened[0];
+        assert_ne!(entity, editor_only);
+        assert_eq!(piece.size, 3);
+        assert_eq!(*scene_mesh, shape);
+        assert_eq!(transform.translation, Vec3::new(1.0, 2.0, 3.0));
+        assert_ne!(*handle, mesh);
+        assert!(world
+            .get_resource::<Assets<Mesh>>()
+            .unwrap()
+            .get(handle)
+            .is_some());
+
+        let mut query = world.query::<(&SceneColor, &Handle<ColorMaterial>)>();
+        let (scene_color, handle) = query.iter(world).next().unwrap();
+        assert_eq!(*scene_color, color);
+        assert_ne!(*handle, material);
+        let rebuilt = world
+            .get_resource::<Assets<ColorMaterial>>()
+            .unwrap()
+            .get(handle)
+            .unwrap();
+        assert_eq!(rebuilt.color, color.color);
+        // editor entities are left alone
+        assert_eq!(world.get::<Piece>(editor_only).unwrap().size, 9);
+    }
+}
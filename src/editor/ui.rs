@@ -4,15 +4,15 @@ use bevy::{
     prelude::*,
 };
 use bevy_egui::{
-    egui::{menu, Checkbox, TopBottomPanel, Ui, Window},
+    egui::{menu, Button, Checkbox, Slider, TopBottomPanel, Ui, Window},
     EguiContext, EguiSettings,
 };
 use bevy_inspector_egui::{WorldInspectorParams, plugin::InspectorWindows};
 
-use crate::{GameStages, GameState, pong::PongData, tanks::TanksData};
+use crate::{ConfigPath, GameStages, GameState, pong::PongData, tanks::{artillery::ArtilleryRules, minesweeper::MinesweeperSettings, minimap::MinimapSettings, streaming::MapStreaming, TanksData}};
 use bevy_inspector_egui::{Inspectable, InspectorPlugin};
 
-use super::{grid::GridData, EditorCamera, EditorState};
+use super::{grid::GridData, AssetBrowser, EditorCamera, EditorHistory, EditorState, HierarchyWindow, Gizmo, GizmoMode, GizmoSpace, Playback, SceneMode, SceneWindow};
 use strum::IntoEnumIterator;
 
 #[derive(Inspectable)]
@@ -98,6 +98,15 @@ fn draw_editor_topbar(
     mut ui_data: ResMut<UIData>,
     mut world_inspection: ResMut<WorldInspectorParams>,
     mut inspector_windows: ResMut<InspectorWindows>,
+    mut streaming: ResMut<MapStreaming>,
+    mut scene_window: ResMut<SceneWindow>,
+    config: Res<ConfigPath>,
+    mut gizmo: ResMut<Gizmo>,
+    mut history: ResMut<EditorHistory>,
+    mut hierarchy: ResMut<HierarchyWindow>,
+    mut asset_browser: ResMut<AssetBrowser>,
+    mut grid: ResMut<GridData>,
+    mut playback: ResMut<Playback>,
     diagnostics: Res<Diagnostics>,
 ) {
     TopBottomPanel::top("top_panel")
@@ -105,6 +114,13 @@ fn draw_editor_topbar(
         .show(egui_ctx.ctx(), |ui| {
             menu::bar(ui, |ui| {
                 menu::menu(ui, "App", |ui| {
+                    if ui.button("Save Scene").clicked() {
+                        scene_window.show(SceneMode::Save, &config);
+                    }
+                    if ui.button("Open Scene").clicked() {
+                        scene_window.show(SceneMode::Open, &config);
+                    }
+                    ui.separator();
                     if ui.button("Quit").clicked() {
                         exit.send(AppExit);
                     }
@@ -113,15 +129,34 @@ fn draw_editor_topbar(
                 menu::menu(ui, "Windows", |ui| {
                     ui.add(Checkbox::new(&mut world_inspection.enabled, "World"));
                     ui.add(Checkbox::new(&mut ui_data.fps, "FPS"));
+                    ui.add(Checkbox::new(&mut streaming.show_chunks, "Map Chunks"));
+                    ui.add(Checkbox::new(&mut hierarchy.show, "Hierarchy"));
+                    ui.add(Checkbox::new(&mut asset_browser.show, "Assets"));
+                    ui.add(Checkbox::new(&mut history.show, "History"));
                 });
 
                 menu::menu(ui, "Resources", |ui| {
                     draw_menu_item::<TanksData>(&mut inspector_windows, ui);
+                    draw_menu_item::<ArtilleryRules>(&mut inspector_windows, ui);
+                    draw_menu_item::<MinesweeperSettings>(&mut inspector_windows, ui);
+                    draw_menu_item::<MinimapSettings>(&mut inspector_windows, ui);
+                    draw_menu_item::<MapStreaming>(&mut inspector_windows, ui);
                     draw_menu_item::<PongData>(&mut inspector_windows, ui);
                     draw_menu_item::<UIData>(&mut inspector_windows, ui);
                     draw_menu_item::<GridData>(&mut inspector_windows, ui);
                 });
 
+                menu::menu(ui, "Gizmo", |ui| {
+                    ui.radio_value(&mut gizmo.mode, GizmoMode::Translate, "Translate (T)");
+                    ui.radio_value(&mut gizmo.mode, GizmoMode::Rotate, "Rotate (R)");
+                    ui.radio_value(&mut gizmo.mode, GizmoMode::Scale, "Scale (Y)");
+                    ui.separator();
+                    ui.radio_value(&mut gizmo.space, GizmoSpace::World, "World (G)");
+                    ui.radio_value(&mut gizmo.space, GizmoSpace::Local, "Local (G)");
+                    ui.separator();
+                    ui.add(Checkbox::new(&mut grid.snap, "Snap (hold Alt to flip)"));
+                });
+
                 menu::menu(ui, "Egui", |ui| {
                     ui.add(Checkbox::new(&mut ui_data.egui_settings.enabled, "Egui Settings"));
                     ui.add(Checkbox::new(
@@ -130,6 +165,29 @@ fn draw_editor_topbar(
                     ));
                 });
 
+                ui.separator();
+                // gameplay only, the editor keeps going either way
+                let label = if playback.paused { "Play" } else { "Pause" };
+                if ui.button(label).clicked() {
+                    playback.paused = !playback.paused;
+                }
+                if ui
+                    .add(Button::new("Step").enabled(playback.paused))
+                    .on_hover_text("Run one frame of gameplay")
+                    .clicked()
+                {
+                    playback.step = true;
+                }
+                ui.add(Slider::new(&mut playback.time_scale, 0.0..=4.0).text("Speed"));
+                if ui.small_button("1x").clicked() {
+                    playback.time_scale = 1.0;
+                }
+
+                if let Some(message) = &scene_window.message {
+                    ui.separator();
+                    ui.label(message);
+                }
+
                 // TODO: Figure out better way to align right
                 let desired_size = ui.available_width();
                 ui.add_space(desired_size - 200.0);
@@ -0,0 +1,692 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::{
+            ActiveCameras, Camera, CameraProjection, DepthCalculation, OrthographicProjection,
+            PerspectiveProjection, ScalingMode,
+        },
+        mesh::VertexAttributeValues,
+        render_graph::RenderGraph,
+    },
+    window::WindowResized,
+};
+use bevy_egui::{
+    egui::{self, Area, Order, Pos2, TextureId},
+    EguiContext, EguiSettings,
+};
+use bevy_input_actionmap::InputMap;
+
+use crate::{
+    helpers::render::{add_texture_camera, refresh_egui_textures},
+    GameStages,
+};
+
+use super::{
+    EditorCamera, EditorCameraAction, EditorCameraConfig, EditorOnly, EditorState, PanOrbitCamera,
+};
+
+/// Top, front and side orthographic views next to the perspective one. Like the tanks split
+/// screen, the extra cameras render into textures that egui lays out over the window. The
+/// single perspective view is still the normal window camera, so picking there is unchanged,
+/// and the extra cameras are only around while their views are on screen
+pub struct ViewportsPlugin;
+
+impl Plugin for ViewportsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(EditorViewports::default())
+            .add_startup_system(setup_render_graph.system())
+            .add_system_set(
+                SystemSet::on_enter(EditorState::Loading).with_system(register_textures.system()),
+            )
+            .add_system_set_to_stage(
+                GameStages::Editor,
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(sync_view_cameras.system().before("viewports"))
+                    .with_system(update_viewports.system().label("viewports")),
+            )
+            .add_system_set(
+                SystemSet::on_update(EditorState::Playing)
+                    .with_system(follow_editor_camera.system())
+                    .with_system(pan_zoom_ortho_views.system())
+                    .with_system(draw_viewports.system())
+                    .with_system(refresh_textures.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(EditorState::Playing).with_system(clear_viewports.system()),
+            );
+    }
+}
+
+/// In the same order as `EditorCamera::index`
+pub const VIEW_CAMERAS: [&str; 4] = [
+    "editor_perspective",
+    "editor_top",
+    "editor_front",
+    "editor_side",
+];
+
+const VIEW_TEXTURES: [HandleUntyped; 4] = [
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5e1d_70a2_c4b8_0001),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5e1d_70a2_c4b8_0002),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5e1d_70a2_c4b8_0003),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x5e1d_70a2_c4b8_0004),
+];
+
+/// Egui ids for the view textures, clear of the tanks player views
+const EGUI_TEXTURE_BASE: u64 = 0x5e1d_0000;
+
+/// Orthographic cameras sit this far back from what they look at, and see twice as far
+const ORTHO_DISTANCE: f32 = 50000.0;
+
+/// Half the height of an orthographic view in world units, before zooming
+const ORTHO_SCALE: f32 = 600.0;
+
+/// The perspective view in the quad layout, copying wherever the window camera goes
+pub struct FollowsEditorCamera;
+
+impl EditorCamera {
+    pub fn index(&self) -> usize {
+        match self {
+            EditorCamera::Perspective => 0,
+            EditorCamera::Top => 1,
+            EditorCamera::Front => 2,
+            EditorCamera::Side => 3,
+        }
+    }
+
+    /// Direction an orthographic camera sits in from what it looks at, and its up
+    pub fn ortho_axes(&self) -> Option<(Vec3, Vec3)> {
+        match self {
+            EditorCamera::Perspective => None,
+            EditorCamera::Top => Some((Vec3::Y, -Vec3::Z)),
+            EditorCamera::Front => Some((Vec3::Z, Vec3::Y)),
+            EditorCamera::Side => Some((Vec3::X, Vec3::Y)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportLayout {
+    Quad,
+    /// One view filling the window
+    Single(EditorCamera),
+}
+
+impl Default for ViewportLayout {
+    fn default() -> Self {
+        ViewportLayout::Single(EditorCamera::Perspective)
+    }
+}
+
+impl ViewportLayout {
+    /// Window rectangles for each view, as the view, its bottom left and size
+    pub fn rects(&self, window: Vec2) -> Vec<(EditorCamera, Vec2, Vec2)> {
+        match self {
+            ViewportLayout::Single(view) => vec![(*view, Vec2::ZERO, window)],
+            ViewportLayout::Quad => {
+                let half = window * 0.5;
+                vec![
+                    (EditorCamera::Top, Vec2::new(0.0, half.y), half),
+                    (EditorCamera::Perspective, half, half),
+                    (EditorCamera::Front, Vec2::ZERO, half),
+                    (EditorCamera::Side, Vec2::new(half.x, 0.0), half),
+                ]
+            }
+        }
+    }
+
+    /// Maximize the view under the cursor, or go back to all four
+    pub fn toggled(&self, under_cursor: Option<EditorCamera>) -> Self {
+        match self {
+            ViewportLayout::Quad => {
+                ViewportLayout::Single(under_cursor.unwrap_or(EditorCamera::Perspective))
+            }
+            ViewportLayout::Single(_) => ViewportLayout::Quad,
+        }
+    }
+
+    /// Views that need a camera rendering into their texture, a single perspective view is
+    /// the window camera
+    pub fn texture_views(&self) -> Vec<EditorCamera> {
+        match self {
+            ViewportLayout::Quad => vec![
+                EditorCamera::Perspective,
+                EditorCamera::Top,
+                EditorCamera::Front,
+                EditorCamera::Side,
+            ],
+            ViewportLayout::Single(EditorCamera::Perspective) => Vec::new(),
+            ViewportLayout::Single(view) => vec![*view],
+        }
+    }
+}
+
+/// Part of the window showing one of the editor's views, in logical pixels with bevy's bottom
+/// left origin
+#[derive(Debug, Clone)]
+pub struct EditorViewport {
+    pub view: EditorCamera,
+    pub camera: Entity,
+    pub min: Vec2,
+    pub size: Vec2,
+}
+
+impl EditorViewport {
+    pub fn contains(&self, position: Vec2) -> bool {
+        let max = self.min + self.size;
+        position.x >= self.min.x
+            && position.y >= self.min.y
+            && position.x < max.x
+            && position.y < max.y
+    }
+
+    /// Normalized device coordinates for a window position inside this viewport
+    pub fn to_ndc(&self, position: Vec2) -> Vec2 {
+        (position - self.min) / self.size * 2.0 - Vec2::ONE
+    }
+
+    /// Window position for normalized device coordinates of this viewport's camera
+    pub fn from_ndc(&self, ndc: Vec2) -> Vec2 {
+        self.min + (ndc + Vec2::ONE) * 0.5 * self.size
+    }
+
+    /// Ray from the camera through a window position, as origin and direction
+    pub fn ray(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        position: Vec2,
+    ) -> (Vec3, Vec3) {
+        let ndc = self.to_ndc(position);
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+        let near = ndc_to_world.project_point3(ndc.extend(0.0));
+        // the perspective views have their far plane at f32::MAX, which doesn't unproject to
+        // anything finite, so go partway there instead
+        let further = ndc_to_world.project_point3(ndc.extend(0.5));
+        (near, (further - near).normalize())
+    }
+
+    /// Where the ray through a window position hits the ground plane
+    pub fn ground(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        position: Vec2,
+    ) -> Option<Vec3> {
+        let (origin, direction) = self.ray(camera, camera_transform, position);
+        if direction.y.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = -origin.y / direction.y;
+        if t < 0.0 {
+            return None;
+        }
+        Some(origin + direction * t)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EditorViewports {
+    pub layout: ViewportLayout,
+    pub viewports: Vec<EditorViewport>,
+}
+
+impl EditorViewports {
+    pub fn at(&self, position: Vec2) -> Option<&EditorViewport> {
+        self.viewports.iter().find(|v| v.contains(position))
+    }
+
+    pub fn for_camera(&self, camera: Entity) -> Option<&EditorViewport> {
+        self.viewports.iter().find(|v| v.camera == camera)
+    }
+
+    /// Whether the mouse should be working this view. With a single view that's anywhere
+    pub fn is_over(&self, view: EditorCamera, cursor: Option<Vec2>) -> bool {
+        match self.layout {
+            ViewportLayout::Single(single) => single == view,
+            ViewportLayout::Quad => cursor
+                .and_then(|cursor| self.at(cursor))
+                .map_or(false, |viewport| viewport.view == view),
+        }
+    }
+
+    /// Bevy picking only knows about the window camera, so anything else picks for itself
+    pub fn uses_window_camera(&self) -> bool {
+        self.layout == ViewportLayout::Single(EditorCamera::Perspective)
+    }
+}
+
+/// Where the cursor is on the ground plane, through whichever view it is over
+pub fn cursor_on_ground(
+    windows: &Windows,
+    viewports: &EditorViewports,
+    cameras: &Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+) -> Option<Vec3> {
+    let cursor = windows.get_primary()?.cursor_position()?;
+    let viewport = viewports.at(cursor)?;
+    let (camera, transform) = cameras.get(viewport.camera).ok()?;
+    viewport.ground(camera, transform, cursor)
+}
+
+/// How far along a ray it first hits a box, None if it misses or the box is behind it
+pub fn ray_hits_box(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<f32> {
+    let mut near = f32::MIN;
+    let mut far = f32::MAX;
+    for i in 0..3 {
+        if direction[i].abs() <= f32::EPSILON {
+            // parallel to these sides, so it has to start between them
+            if origin[i] < min[i] || origin[i] > max[i] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[i] - origin[i]) / direction[i];
+        let b = (max[i] - origin[i]) / direction[i];
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+    }
+    if near > far || far < 0.0 {
+        return None;
+    }
+    Some(near.max(0.0))
+}
+
+/// Box around a mesh in its own space
+pub fn mesh_bounds(mesh: &Mesh) -> Option<(Vec3, Vec3)> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float3(positions)) if !positions.is_empty() => positions,
+        _ => return None,
+    };
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for position in positions.iter() {
+        min = min.min(Vec3::from(*position));
+        max = max.max(Vec3::from(*position));
+    }
+    Some((min, max))
+}
+
+fn setup_render_graph(
+    mut render_graph: ResMut<RenderGraph>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    msaa: Res<Msaa>,
+) {
+    // Whole window size, so a maximized view is as sharp as the window camera. The quarters
+    // keep the window's aspect, so the projections bevy sizes to the window fit them too
+    for (i, camera) in VIEW_CAMERAS.iter().enumerate() {
+        add_texture_camera(
+            &mut render_graph,
+            &mut active_cameras,
+            &msaa,
+            camera,
+            VIEW_TEXTURES[i].clone(),
+            Vec2::ONE,
+        );
+    }
+}
+
+fn egui_textures() -> Vec<(u64, HandleUntyped)> {
+    VIEW_TEXTURES
+        .iter()
+        .enumerate()
+        .map(|(i, texture)| (EGUI_TEXTURE_BASE + i as u64, texture.clone()))
+        .collect()
+}
+
+fn register_textures(mut egui_ctx: ResMut<EguiContext>) {
+    for (id, texture) in egui_textures() {
+        egui_ctx.set_egui_texture(id, texture.typed());
+    }
+}
+
+/// The render graph makes the view textures again when the window resizes, egui needs to
+/// pick up the new ones
+fn refresh_textures(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut ev_resized: EventReader<WindowResized>,
+    mut removed: Local<bool>,
+) {
+    let resized = ev_resized.iter().count() > 0;
+    refresh_egui_textures(&mut egui_ctx, &egui_textures(), resized, &mut removed);
+}
+
+/// Cameras for the views in the layout that aren't the window camera, views that aren't shown
+/// lose theirs so they don't render every frame for nothing
+fn sync_view_cameras(
+    mut commands: Commands,
+    viewports: Res<EditorViewports>,
+    camera_config: Res<EditorCameraConfig>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    editor_cameras: Query<&PanOrbitCamera>,
+    cameras: Query<(Entity, &EditorCamera, Option<&FollowsEditorCamera>)>,
+) {
+    let wanted = viewports.layout.texture_views();
+    let mut existing = Vec::new();
+    for (entity, view, follows) in cameras.iter() {
+        if !is_view_camera(*view, follows.is_some()) {
+            continue;
+        }
+        if wanted.contains(view) {
+            existing.push(*view);
+        } else {
+            despawn_view_camera(&mut commands, &mut active_cameras, entity, *view);
+        }
+    }
+
+    // new orthographic views start out looking at whatever the editor camera is
+    let focus = editor_cameras
+        .iter()
+        .next()
+        .map_or(camera_config.view.focus, |camera| camera.focus);
+    for view in wanted.into_iter().filter(|view| !existing.contains(view)) {
+        spawn_view_camera(&mut commands, view, focus);
+    }
+}
+
+/// The window camera is an `EditorCamera` too, the perspective view that isn't following it
+fn is_view_camera(view: EditorCamera, follows: bool) -> bool {
+    view != EditorCamera::Perspective || follows
+}
+
+fn despawn_view_camera(
+    commands: &mut Commands,
+    active_cameras: &mut ActiveCameras,
+    entity: Entity,
+    view: EditorCamera,
+) {
+    commands.entity(entity).despawn();
+    // bevy only looks a camera up by name while it has none, so the next one has to be found
+    if let Some(active) = active_cameras.get_mut(VIEW_CAMERAS[view.index()]) {
+        active.entity = None;
+    }
+}
+
+fn spawn_view_camera(commands: &mut Commands, view: EditorCamera, focus: Vec3) {
+    let camera = Camera {
+        name: Some(VIEW_CAMERAS[view.index()].to_string()),
+        ..Default::default()
+    };
+    let mut entity = match view.ortho_axes() {
+        None => {
+            let mut entity = commands.spawn_bundle(PerspectiveCameraBundle {
+                camera,
+                perspective_projection: PerspectiveProjection {
+                    far: f32::MAX,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            entity
+                .insert(FollowsEditorCamera)
+                .insert(Name::new("Editor Quad Perspective Camera"));
+            entity
+        }
+        Some((back, up)) => {
+            let mut entity = commands.spawn_bundle(OrthographicCameraBundle {
+                camera,
+                orthographic_projection: OrthographicProjection {
+                    far: ORTHO_DISTANCE * 2.0,
+                    scale: ORTHO_SCALE,
+                    scaling_mode: ScalingMode::FixedVertical,
+                    depth_calculation: DepthCalculation::Distance,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(focus + back * ORTHO_DISTANCE)
+                    .looking_at(focus, up),
+                ..OrthographicCameraBundle::new_3d()
+            });
+            entity.insert(Name::new(format!("Editor {:?} Camera", view)));
+            entity
+        }
+    };
+    entity.insert(view).insert(EditorOnly);
+}
+
+fn follow_editor_camera(
+    editor_cameras: Query<&Transform, With<PanOrbitCamera>>,
+    mut followers: Query<&mut Transform, (With<FollowsEditorCamera>, Without<PanOrbitCamera>)>,
+) {
+    if let Some(editor_camera) = editor_cameras.iter().next() {
+        for mut transform in followers.iter_mut() {
+            *transform = *editor_camera;
+        }
+    }
+}
+
+/// Lay the views out for the current layout, the perspective view is the window camera when
+/// it has the window to itself
+fn update_viewports(
+    windows: Res<Windows>,
+    mut viewports: ResMut<EditorViewports>,
+    cameras: Query<(Entity, &EditorCamera, Option<&FollowsEditorCamera>)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => Vec2::new(window.width(), window.height()),
+        None => return,
+    };
+    let quad = viewports.layout == ViewportLayout::Quad;
+
+    let mut laid_out = Vec::new();
+    for (view, min, size) in viewports.layout.rects(window) {
+        let camera = cameras.iter().find(|(_, camera_view, follows)| {
+            **camera_view == view
+                && (view != EditorCamera::Perspective || follows.is_some() == quad)
+        });
+        if let Some((camera, _, _)) = camera {
+            laid_out.push(EditorViewport {
+                view,
+                camera,
+                min,
+                size,
+            });
+        }
+    }
+    viewports.viewports = laid_out;
+}
+
+/// Drag with the right or middle mouse button to pan, scroll to zoom in on the cursor
+fn pan_zoom_ortho_views(
+    windows: Res<Windows>,
+    egui_ctx: Res<EguiContext>,
+    viewports: Res<EditorViewports>,
+    input_map: Res<InputMap<EditorCameraAction>>,
+    mut ev_motion: EventReader<MouseMotion>,
+    mut ev_scroll: EventReader<MouseWheel>,
+    mut cameras: Query<(
+        &EditorCamera,
+        &mut Transform,
+        &mut OrthographicProjection,
+        &mut Camera,
+    )>,
+) {
+    let motion = ev_motion
+        .iter()
+        .fold(Vec2::ZERO, |motion, ev| motion + ev.delta);
+    let scroll: f32 = ev_scroll.iter().map(|ev| ev.y).sum();
+
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    if egui_ctx.ctx().is_pointer_over_area() {
+        return;
+    }
+    let viewport = match viewports.at(cursor) {
+        Some(viewport) if viewport.view.ortho_axes().is_some() => viewport,
+        _ => return,
+    };
+    let (_, mut transform, mut projection, mut camera) = match cameras.get_mut(viewport.camera) {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    // world units across a pixel, the view is twice the scale high
+    let per_pixel = projection.scale * 2.0 / viewport.size.y;
+    let right = transform.rotation * Vec3::X;
+    let up = transform.rotation * Vec3::Y;
+
+    let dragging =
+        input_map.active(EditorCameraAction::Orbit) || input_map.active(EditorCameraAction::Pan);
+    if dragging && motion.length_squared() > 0.0 {
+        transform.translation += (right * -motion.x + up * motion.y) * per_pixel;
+    }
+
+    if scroll.abs() > 0.0 {
+        projection.scale = (projection.scale * (1.0 - scroll * 0.2)).clamp(1.0, ORTHO_DISTANCE);
+        camera.projection_matrix = projection.get_projection_matrix();
+        // keep what's under the cursor there
+        let new_per_pixel = projection.scale * 2.0 / viewport.size.y;
+        let offset = cursor - (viewport.min + viewport.size * 0.5);
+        transform.translation += (right * offset.x + up * offset.y) * (per_pixel - new_per_pixel);
+    }
+}
+
+fn draw_viewports(
+    egui_ctx: Res<EguiContext>,
+    egui_settings: Res<EguiSettings>,
+    windows: Res<Windows>,
+    viewports: Res<EditorViewports>,
+) {
+    // the window camera draws itself
+    if viewports.uses_window_camera() {
+        return;
+    }
+    let window_height = match windows.get_primary() {
+        Some(window) => window.height(),
+        None => return,
+    };
+    let scale = egui_settings.scale_factor as f32;
+
+    for viewport in viewports.viewports.iter() {
+        // egui starts top left
+        let top_left = Pos2::new(
+            viewport.min.x / scale,
+            (window_height - viewport.min.y - viewport.size.y) / scale,
+        );
+        let size = egui::Vec2::new(viewport.size.x / scale, viewport.size.y / scale);
+
+        Area::new(format!("editor_view_{}", viewport.view.index()))
+            .order(Order::Background)
+            .fixed_pos(top_left)
+            .interactable(false)
+            .show(egui_ctx.ctx(), |ui| {
+                ui.image(
+                    TextureId::User(EGUI_TEXTURE_BASE + viewport.view.index() as u64),
+                    size,
+                );
+            });
+    }
+}
+
+fn clear_viewports(
+    mut commands: Commands,
+    mut viewports: ResMut<EditorViewports>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    cameras: Query<(Entity, &EditorCamera, Option<&FollowsEditorCamera>)>,
+) {
+    viewports.viewports.clear();
+    for (entity, view, follows) in cameras.iter() {
+        if is_view_camera(*view, follows.is_some()) {
+            despawn_view_camera(&mut commands, &mut active_cameras, entity, *view);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::CameraView;
+
+    #[test]
+    fn quad_splits_the_window_in_four() {
+        let window = Vec2::new(800.0, 600.0);
+        let rects = ViewportLayout::Quad.rects(window);
+        assert_eq!(rects.len(), 4);
+        let area: f32 = rects.iter().map(|(_, _, size)| size.x * size.y).sum();
+        assert_eq!(area, window.x * window.y);
+        // each quarter keeps the window's shape, so window sized projections still fit
+        for (_, _, size) in rects.iter() {
+            assert_eq!(size.x / size.y, window.x / window.y);
+        }
+
+        let single = ViewportLayout::Single(EditorCamera::Top).rects(window);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].2, window);
+    }
+
+    #[test]
+    fn maximizing_toggles_the_view_under_the_cursor() {
+        let quad = ViewportLayout::Quad;
+        assert_eq!(
+            quad.toggled(Some(EditorCamera::Side)),
+            ViewportLayout::Single(EditorCamera::Side)
+        );
+        assert_eq!(
+            quad.toggled(None),
+            ViewportLayout::Single(EditorCamera::Perspective)
+        );
+        assert_eq!(
+            ViewportLayout::Single(EditorCamera::Top).toggled(Some(EditorCamera::Top)),
+            quad
+        );
+    }
+
+    #[test]
+    fn only_views_on_screen_get_cameras() {
+        assert!(ViewportLayout::default().texture_views().is_empty());
+        assert_eq!(ViewportLayout::Quad.texture_views().len(), 4);
+        assert_eq!(
+            ViewportLayout::Single(EditorCamera::Front).texture_views(),
+            vec![EditorCamera::Front]
+        );
+    }
+
+    #[test]
+    fn editor_camera_rays_reach_the_ground() {
+        let mut projection = PerspectiveProjection {
+            far: f32::MAX,
+            ..Default::default()
+        };
+        projection.update(800.0, 600.0);
+        let camera = Camera {
+            projection_matrix: projection.get_projection_matrix(),
+            ..Default::default()
+        };
+        let view = CameraView::default();
+        let transform = GlobalTransform {
+            translation: view.focus + view.rotation * Vec3::new(0.0, 0.0, view.radius),
+            rotation: view.rotation,
+            ..Default::default()
+        };
+        let viewport = EditorViewport {
+            view: EditorCamera::Perspective,
+            camera: Entity::new(0),
+            min: Vec2::ZERO,
+            size: Vec2::new(800.0, 600.0),
+        };
+
+        let center = Vec2::new(400.0, 300.0);
+        let (_, direction) = viewport.ray(&camera, &transform, center);
+        assert!(direction.is_finite());
+        // the middle of the view is what it is looking at
+        let ground = viewport.ground(&camera, &transform, center).unwrap();
+        assert!(ground.distance(view.focus) < 0.1, "{}", ground);
+    }
+
+    #[test]
+    fn rays_hit_the_near_side_of_boxes() {
+        let min = Vec3::splat(-1.0);
+        let max = Vec3::splat(1.0);
+        let origin = Vec3::new(0.0, 0.0, 10.0);
+        assert_eq!(ray_hits_box(origin, -Vec3::Z, min, max), Some(9.0));
+        assert_eq!(ray_hits_box(origin, Vec3::Z, min, max), None);
+        assert_eq!(
+            ray_hits_box(origin + Vec3::X * 2.0, -Vec3::Z, min, max),
+            None
+        );
+        // starting inside counts as a hit right away
+        assert_eq!(ray_hits_box(Vec3::ZERO, Vec3::X, min, max), Some(0.0));
+    }
+}
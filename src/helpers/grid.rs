@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+
+/// A rectangle of square cells on a plane, for going between positions and cells. Cells are
+/// addressed by column and row from the corner at `origin`, signed coordinates are used for
+/// anything that may fall outside the grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSpace {
+    /// Corner of cell (0, 0)
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl GridSpace {
+    /// Grid centered on zero
+    pub fn new(width: u32, height: u32, cell_size: f32) -> Self {
+        GridSpace {
+            origin: Vec2::new(width as f32, height as f32) * cell_size * -0.5,
+            cell_size,
+            width,
+            height,
+        }
+    }
+
+    /// Same grid with cell (0, 0) starting at `origin`
+    pub fn with_origin(self, origin: Vec2) -> Self {
+        GridSpace { origin, ..self }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * self.cell_size
+    }
+
+    /// Smallest and largest corner of the whole grid
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        (self.origin, self.origin + self.size())
+    }
+
+    /// Center of a cell
+    pub fn cell_to_world(&self, x: u32, y: u32) -> Vec2 {
+        self.cell_center(x as i64, y as i64)
+    }
+
+    /// Center of a cell, carrying on past the edges of the grid
+    pub fn cell_center(&self, x: i64, y: i64) -> Vec2 {
+        self.origin + (Vec2::new(x as f32, y as f32) + Vec2::splat(0.5)) * self.cell_size
+    }
+
+    /// Where grid lines cross, `corner(width, height)` is the far corner of the grid
+    pub fn corner(&self, x: u32, y: u32) -> Vec2 {
+        self.origin + Vec2::new(x as f32, y as f32) * self.cell_size
+    }
+
+    /// Cell containing a position, carrying on past the edges of the grid
+    pub fn cell_at(&self, position: Vec2) -> (i64, i64) {
+        let local = (position - self.origin) / self.cell_size;
+        (local.x.floor() as i64, local.y.floor() as i64)
+    }
+
+    /// Cell containing a position, None if its off the grid
+    pub fn world_to_cell(&self, position: Vec2) -> Option<(u32, u32)> {
+        let (x, y) = self.cell_at(position);
+        self.checked(x, y)
+    }
+
+    /// Closest place where grid lines cross, lines carry on past the edges of the grid
+    pub fn snap(&self, position: Vec2) -> Vec2 {
+        let local = ((position - self.origin) / self.cell_size).round();
+        self.origin + local * self.cell_size
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i64 && y < self.height as i64
+    }
+
+    /// Cell coordinates if they are on the grid
+    pub fn checked(&self, x: i64, y: i64) -> Option<(u32, u32)> {
+        if self.contains(x, y) {
+            Some((x as u32, y as u32))
+        } else {
+            None
+        }
+    }
+
+    /// Closest cell on the grid, the grid must not be empty
+    pub fn clamp(&self, x: i64, y: i64) -> (u32, u32) {
+        (
+            x.max(0).min(self.width as i64 - 1) as u32,
+            y.max(0).min(self.height as i64 - 1) as u32,
+        )
+    }
+
+    /// Position of a cell in a row by row array of the grid
+    pub fn index(&self, x: i64, y: i64) -> Option<usize> {
+        self.checked(x, y)
+            .map(|(x, y)| (y * self.width + x) as usize)
+    }
+
+    /// Cells sharing an edge, and a corner as well if `diagonal`, that are on the grid
+    pub fn neighbours(&self, x: u32, y: u32, diagonal: bool) -> Vec<(u32, u32)> {
+        const EDGES: [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+        const ALL: [(i64, i64); 8] = [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        let offsets: &[(i64, i64)] = if diagonal { &ALL } else { &EDGES };
+        self.offset_cells(x, y, offsets)
+    }
+
+    /// Cells at each offset from a cell that are on the grid
+    pub fn offset_cells(&self, x: u32, y: u32, offsets: &[(i64, i64)]) -> Vec<(u32, u32)> {
+        offsets
+            .iter()
+            .filter_map(|(dx, dy)| self.checked(x as i64 + dx, y as i64 + dy))
+            .collect()
+    }
+
+    /// Cells in a rectangle, corners included, clipped to the grid, row by row
+    pub fn rect(&self, min: (i64, i64), max: (i64, i64)) -> impl Iterator<Item = (u32, u32)> {
+        let x_range = min.0.max(0)..=max.0.min(self.width as i64 - 1);
+        let y_range = min.1.max(0)..=max.1.min(self.height as i64 - 1);
+        y_range.flat_map(move |y| x_range.clone().map(move |x| (x as u32, y as u32)))
+    }
+
+    /// Every cell on the grid, row by row
+    pub fn cells(&self) -> impl Iterator<Item = (u32, u32)> {
+        self.rect((0, 0), (self.width as i64 - 1, self.height as i64 - 1))
+    }
+}
+
+/// Cells on a straight line between two cells, both ends included, using Bresenham's line.
+/// Not clipped to any grid
+pub fn line(from: (i64, i64), to: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+    let sx = if from.0 < to.0 { 1 } else { -1 };
+    let sy = if from.1 < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut next = Some(from);
+
+    std::iter::from_fn(move || {
+        let current = next?;
+        next = if current == to {
+            None
+        } else {
+            let (mut x, mut y) = current;
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            Some((x, y))
+        };
+        Some(current)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centered_on_zero() {
+        let grid = GridSpace::new(4, 2, 10.0);
+        assert_eq!(grid.origin, Vec2::new(-20.0, -10.0));
+        assert_eq!(
+            grid.bounds(),
+            (Vec2::new(-20.0, -10.0), Vec2::new(20.0, 10.0))
+        );
+        assert_eq!(grid.size(), Vec2::new(40.0, 20.0));
+        assert_eq!(grid.corner(4, 2), Vec2::new(20.0, 10.0));
+    }
+
+    #[test]
+    fn world_and_cell_round_trip() {
+        let grid = GridSpace::new(5, 3, 2.0).with_origin(Vec2::new(1.0, -1.0));
+        for (x, y) in grid.cells() {
+            let center = grid.cell_to_world(x, y);
+            assert_eq!(grid.world_to_cell(center), Some((x, y)));
+            // the low edges belong to the cell, the high edges to the next one
+            assert_eq!(grid.world_to_cell(grid.corner(x, y)), Some((x, y)));
+        }
+        assert_eq!(grid.cell_to_world(0, 0), Vec2::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn off_the_grid() {
+        let grid = GridSpace::new(3, 3, 1.0);
+        assert_eq!(grid.world_to_cell(Vec2::new(-1.6, 0.0)), None);
+        assert_eq!(grid.world_to_cell(Vec2::new(0.0, 1.5)), None);
+        assert_eq!(grid.cell_at(Vec2::new(-1.6, 0.0)), (-1, 1));
+        assert_eq!(grid.cell_at(Vec2::new(0.0, 1.5)), (1, 3));
+        assert_eq!(grid.cell_center(-1, 3), Vec2::new(-2.0, 2.0));
+        assert!(!grid.contains(3, 0));
+        assert!(!grid.contains(0, -1));
+        assert!(grid.contains(2, 2));
+        assert_eq!(grid.checked(-1, 0), None);
+        assert_eq!(grid.clamp(-4, 7), (0, 2));
+        assert_eq!(grid.clamp(1, 1), (1, 1));
+    }
+
+    #[test]
+    fn indices_are_row_by_row() {
+        let grid = GridSpace::new(3, 2, 1.0);
+        assert_eq!(grid.index(0, 0), Some(0));
+        assert_eq!(grid.index(2, 0), Some(2));
+        assert_eq!(grid.index(0, 1), Some(3));
+        assert_eq!(grid.index(3, 0), None);
+        assert_eq!(grid.index(0, 2), None);
+    }
+
+    #[test]
+    fn snaps_to_line_crossings() {
+        let grid = GridSpace::new(4, 4, 10.0);
+        assert_eq!(grid.snap(Vec2::new(3.0, -6.0)), Vec2::new(0.0, -10.0));
+        assert_eq!(grid.snap(Vec2::new(14.0, 16.0)), Vec2::new(10.0, 20.0));
+        // past the edge
+        assert_eq!(grid.snap(Vec2::new(61.0, 0.0)), Vec2::new(60.0, 0.0));
+    }
+
+    #[test]
+    fn neighbours_stay_on_the_grid() {
+        let grid = GridSpace::new(3, 3, 1.0);
+        assert_eq!(grid.neighbours(1, 1, true).len(), 8);
+        assert_eq!(grid.neighbours(1, 1, false).len(), 4);
+        assert_eq!(grid.neighbours(0, 0, true), vec![(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(grid.neighbours(2, 2, false), vec![(2, 1), (1, 2)]);
+        assert!(!grid.neighbours(1, 1, true).contains(&(1, 1)));
+    }
+
+    #[test]
+    fn rects_are_clipped() {
+        let grid = GridSpace::new(4, 3, 1.0);
+        let cells: Vec<_> = grid.rect((1, 1), (2, 2)).collect();
+        assert_eq!(cells, vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let clipped: Vec<_> = grid.rect((-5, 2), (0, 9)).collect();
+        assert_eq!(clipped, vec![(0, 2)]);
+        assert_eq!(grid.rect((5, 5), (9, 9)).count(), 0);
+        assert_eq!(grid.cells().count(), 12);
+        assert_eq!(GridSpace::new(0, 0, 1.0).cells().count(), 0);
+    }
+
+    #[test]
+    fn lines_include_both_ends() {
+        let cells: Vec<_> = line((0, 0), (3, 0)).collect();
+        assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let cells: Vec<_> = line((0, 0), (-2, -2)).collect();
+        assert_eq!(cells, vec![(0, 0), (-1, -1), (-2, -2)]);
+        assert_eq!(line((4, 4), (4, 4)).collect::<Vec<_>>(), vec![(4, 4)]);
+
+        // steep lines take one cell per row
+        let cells: Vec<_> = line((0, 0), (1, 5)).collect();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells.first(), Some(&(0, 0)));
+        assert_eq!(cells.last(), Some(&(1, 5)));
+        for step in cells.windows(2) {
+            assert_eq!(step[1].1 - step[0].1, 1);
+            assert!((step[1].0 - step[0].0).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn lines_are_symmetric_in_length() {
+        for to in [(7, 3), (-2, 9), (5, -5), (0, -4)].iter() {
+            let there = line((0, 0), *to).count();
+            let back = line(*to, (0, 0)).count();
+            assert_eq!(there, back);
+        }
+    }
+}
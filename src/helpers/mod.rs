@@ -1,4 +1,6 @@
 pub mod camera;
+pub mod grid;
+pub mod render;
 
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::ActiveCameras,
+        pass::{
+            LoadOp, Operations, PassDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+            TextureAttachment,
+        },
+        render_graph::{
+            self, base, CameraNode, PassNode, RenderGraph, ResourceSlotInfo, ResourceSlots,
+        },
+        renderer::{RenderContext, RenderResourceId, RenderResourceType},
+        texture::{
+            Extent3d, SamplerDescriptor, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsage, SAMPLER_ASSET_INDEX, TEXTURE_ASSET_INDEX,
+        },
+    },
+};
+use bevy_egui::EguiContext;
+
+/// Texture size for part of a window, never empty
+pub fn view_texture_size(window: &Window, fraction: Vec2) -> Extent3d {
+    Extent3d::new(
+        ((window.physical_width() as f32 * fraction.x) as u32).max(1),
+        ((window.physical_height() as f32 * fraction.y) as u32).max(1),
+        1,
+    )
+}
+
+/// Like bevy's `TextureNode`, but sized to part of the primary window and made again whenever
+/// the window changes size
+pub struct ViewTextureNode {
+    descriptor: TextureDescriptor,
+    sampler: Option<SamplerDescriptor>,
+    handle: Option<HandleUntyped>,
+    /// Part of the window's width and height the texture covers
+    fraction: Vec2,
+}
+
+impl ViewTextureNode {
+    pub const TEXTURE: &'static str = "texture";
+
+    pub fn new(
+        descriptor: TextureDescriptor,
+        fraction: Vec2,
+        sampler: Option<SamplerDescriptor>,
+        handle: Option<HandleUntyped>,
+    ) -> Self {
+        ViewTextureNode {
+            descriptor,
+            sampler,
+            handle,
+            fraction,
+        }
+    }
+}
+
+impl render_graph::Node for ViewTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed(ViewTextureNode::TEXTURE),
+            resource_type: RenderResourceType::Texture,
+        }];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        world: &World,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        let window = match world
+            .get_resource::<Windows>()
+            .and_then(|w| w.get_primary())
+        {
+            Some(window) => window,
+            None => return,
+        };
+        let size = view_texture_size(window, self.fraction);
+        let current = output.get(0);
+        if current.is_some() && size == self.descriptor.size {
+            return;
+        }
+
+        let resources = render_context.resources_mut();
+        if let Some(RenderResourceId::Texture(old)) = current {
+            resources.remove_texture(old);
+        }
+        self.descriptor.size = size;
+        let texture = resources.create_texture(self.descriptor);
+        if let Some(handle) = &self.handle {
+            resources.set_asset_resource_untyped(
+                handle.clone(),
+                RenderResourceId::Texture(texture),
+                TEXTURE_ASSET_INDEX,
+            );
+            if let Some(sampler) = self.sampler {
+                let sampler = resources.create_sampler(&sampler);
+                resources.set_asset_resource_untyped(
+                    handle.clone(),
+                    RenderResourceId::Sampler(sampler),
+                    SAMPLER_ASSET_INDEX,
+                );
+            }
+        }
+        output.set(0, RenderResourceId::Texture(texture));
+    }
+}
+
+/// Egui keeps drawing whatever texture it first saw for a handle, so after the window resizes
+/// and the view textures are made again they are taken off egui for a frame and handed back
+pub fn refresh_egui_textures(
+    egui_ctx: &mut EguiContext,
+    textures: &[(u64, HandleUntyped)],
+    resized: bool,
+    removed: &mut bool,
+) {
+    if resized {
+        for (id, _) in textures.iter() {
+            egui_ctx.remove_egui_texture(*id);
+        }
+        *removed = true;
+    } else if *removed {
+        for (id, texture) in textures.iter() {
+            egui_ctx.set_egui_texture(*id, texture.clone().typed());
+        }
+        *removed = false;
+    }
+}
+
+/// Render the camera with this name into a texture instead of the window, `fraction` is how
+/// much of the window's width and height the texture covers. Bevy can't render a camera to
+/// part of the window yet, so anything with more than one view draws these with egui
+pub fn add_texture_camera(
+    render_graph: &mut RenderGraph,
+    active_cameras: &mut ActiveCameras,
+    msaa: &Msaa,
+    camera: &str,
+    texture: HandleUntyped,
+    fraction: Vec2,
+) {
+    let texture_node = format!("{}_texture", camera);
+    let depth_node = format!("{}_depth", camera);
+    let pass_node = format!("{}_pass", camera);
+
+    render_graph.add_node(
+        texture_node.clone(),
+        ViewTextureNode::new(
+            TextureDescriptor {
+                format: TextureFormat::default(),
+                usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+                ..Default::default()
+            },
+            fraction,
+            Some(SamplerDescriptor::default()),
+            Some(texture),
+        ),
+    );
+    render_graph.add_node(
+        depth_node.clone(),
+        ViewTextureNode::new(
+            TextureDescriptor {
+                format: TextureFormat::Depth32Float,
+                usage: TextureUsage::OUTPUT_ATTACHMENT,
+                sample_count: msaa.samples,
+                ..Default::default()
+            },
+            fraction,
+            None,
+            None,
+        ),
+    );
+    render_graph.add_system_node(camera.to_string(), CameraNode::new(camera.to_string()));
+
+    let mut pass = PassNode::<&base::MainPass>::new(PassDescriptor {
+        color_attachments: vec![msaa.color_attachment_descriptor(
+            TextureAttachment::Input("color_attachment".to_string()),
+            TextureAttachment::Input("color_resolve_target".to_string()),
+            Operations {
+                load: LoadOp::Clear(Color::BLACK),
+                store: true,
+            },
+        )],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: msaa.samples,
+    });
+    // cleared to `ClearColor` each frame like the window, so views follow edits to it
+    pass.use_default_clear_color(0);
+    pass.add_camera(camera);
+    render_graph.add_node(pass_node.clone(), pass);
+
+    if msaa.samples > 1 {
+        let msaa_node = format!("{}_msaa", camera);
+        render_graph.add_node(
+            msaa_node.clone(),
+            ViewTextureNode::new(
+                TextureDescriptor {
+                    sample_count: msaa.samples,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::default(),
+                    usage: TextureUsage::OUTPUT_ATTACHMENT,
+                    ..Default::default()
+                },
+                fraction,
+                None,
+                None,
+            ),
+        );
+        render_graph
+            .add_slot_edge(
+                msaa_node,
+                ViewTextureNode::TEXTURE,
+                pass_node.clone(),
+                "color_attachment",
+            )
+            .unwrap();
+        render_graph
+            .add_slot_edge(
+                texture_node,
+                ViewTextureNode::TEXTURE,
+                pass_node.clone(),
+                "color_resolve_target",
+            )
+            .unwrap();
+    } else {
+        render_graph
+            .add_slot_edge(
+                texture_node,
+                ViewTextureNode::TEXTURE,
+                pass_node.clone(),
+                "color_attachment",
+            )
+            .unwrap();
+    }
+    render_graph
+        .add_slot_edge(
+            depth_node,
+            ViewTextureNode::TEXTURE,
+            pass_node.clone(),
+            "depth",
+        )
+        .unwrap();
+    render_graph
+        .add_node_edge(camera.to_string(), pass_node.clone())
+        .unwrap();
+    // draw texture views before the main pass so egui can show them the same frame
+    render_graph
+        .add_node_edge(pass_node, base::node::MAIN_PASS)
+        .unwrap();
+
+    active_cameras.add(camera);
+}
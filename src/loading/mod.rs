@@ -15,6 +15,7 @@ impl Plugin for LoadingPlugin {
             .with_collection::<FontAssets>()
             .with_collection::<MusicAssets>()
             .with_collection::<PongAssets>()
+            .with_collection::<TanksAssets>()
             .build(app);
     }
 }
@@ -40,3 +41,18 @@ pub struct PongAssets {
     #[asset(path = "audio/pong/lowDown.wav")]
     pub pong_goal: Handle<AudioSource>,
 }
+
+#[derive(AssetCollection)]
+pub struct TanksAssets {
+    #[asset(path = "retro_medieval/detail_crate.glb#Scene0")]
+    pub crate_box: Handle<Scene>,
+
+    #[asset(path = "retro_medieval/wall.glb#Scene0")]
+    pub wall: Handle<Scene>,
+
+    #[asset(path = "retro_medieval/column.glb#Scene0")]
+    pub column: Handle<Scene>,
+
+    #[asset(path = "audio/tanks/explosion.wav")]
+    pub explosion: Handle<AudioSource>,
+}
@@ -7,6 +7,7 @@ mod menu;
 mod pong;
 mod state;
 mod tanks;
+mod tween;
 mod window_config;
 
 use actions::ActionsPlugin;
@@ -17,7 +18,7 @@ use std::fmt;
 use bevy_prototype_debug_lines::DebugLinesPlugin;
 
 use bevy_kira_audio::AudioPlugin;
-use editor::EditorPlugin;
+use editor::{run_if_game_running, EditorPlugin};
 use loading::LoadingPlugin;
 
 use bevy::ecs::{archetype::Archetypes, component::Components};
@@ -32,6 +33,7 @@ use pong::PongPlugin;
 use state::StatePlugin;
 use strum::EnumIter;
 use tanks::TanksPlugin;
+use tween::TweenPlugin;
 use window_config::WindowConfigPlugin;
 
 // See https://bevy-cheatbook.github.io/ for about everything
@@ -60,6 +62,7 @@ impl fmt::Display for GameState {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
 pub enum GameStages {
     Editor, // only used for ui currently
+    Game,   // gameplay, runs after update and stops while the editor has it paused
 }
 
 
@@ -91,6 +94,13 @@ fn main() {
 
         // Add States
         .add_state(GameState::Loading)
+        .add_stage_after(
+            CoreStage::Update,
+            GameStages::Game,
+            SystemStage::parallel().with_run_criteria(run_if_game_running.system()),
+        )
+        // on_update needs the state driver in the same stage, same as the editor stage
+        .add_state_to_stage(GameStages::Game, GameState::Loading)
         // Load Assets, and once Load start
         .add_plugin(LoadingPlugin)
         // Add our plugins
@@ -98,14 +108,15 @@ fn main() {
         .add_plugin(WindowConfigPlugin)
         .add_plugin(ActionsPlugin)
         .add_plugin(StatePlugin)
+        .add_plugin(TweenPlugin)
         .add_plugin(PongPlugin)
         .add_plugin(TanksPlugin::new(GameState::Tanks))
         .add_plugin(MenuPlugin)
         .run();
 }
 
-#[allow(dead_code)]
-fn print_resources(archetypes: &Archetypes, components: &Components) {
+/// Short names of every resource, sorted alphabetically
+pub fn resource_names(archetypes: &Archetypes, components: &Components) -> Vec<String> {
     let mut r: Vec<String> = archetypes
         .resource()
         .components()
@@ -115,7 +126,14 @@ fn print_resources(archetypes: &Archetypes, components: &Components) {
 
     // sort list alphebetically
     r.sort();
-    r.iter().for_each(|name| println!("{}", name));
+    r
+}
+
+#[allow(dead_code)]
+fn print_resources(archetypes: &Archetypes, components: &Components) {
+    resource_names(archetypes, components)
+        .iter()
+        .for_each(|name| println!("{}", name));
 }
 
 #[allow(dead_code)]
@@ -1,5 +1,10 @@
 use crate::loading::MusicAssets;
-use crate::GameState;
+use crate::tanks::{
+    records::{Date, DailyChallenge, RecordsWindow},
+    save::{read_slot, PendingLoad, SaveSlots},
+};
+use crate::tween::{ColorLens, Ease, Tween, TweenMode};
+use crate::{ConfigPath, GameState};
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy_egui::{egui::*, *};
@@ -12,23 +17,64 @@ impl Plugin for MenuPlugin {
         app.insert_resource(MenuAudio {
             background: AudioChannel::new("menu-bg".to_owned()),
         })
+        .insert_resource(MenuTitle::default())
         .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(start_audio.system()))
         .add_system_set(SystemSet::on_update(GameState::Menu).with_system(draw_menu.system()))
         .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(stop_audio.system()));
     }
 }
 
+/// Title color slowly shifts back and forth, ticked by hand since egui has no entities
+struct MenuTitle {
+    tween: Tween<ColorLens>,
+}
+
+impl Default for MenuTitle {
+    fn default() -> Self {
+        MenuTitle {
+            tween: Tween::new(
+                ColorLens {
+                    from: Color::rgb(0.9, 0.9, 0.9),
+                    to: Color::rgb(0.3, 0.8, 1.0),
+                },
+                Ease::SineInOut,
+                2.0,
+            )
+            .with_mode(TweenMode::PingPong),
+        }
+    }
+}
+
 fn draw_menu(
     egui_ctx: Res<EguiContext>,
+    time: Res<Time>,
+    mut title: ResMut<MenuTitle>,
     mut exit: EventWriter<AppExit>,
     mut state: ResMut<State<GameState>>,
+    config: Res<ConfigPath>,
+    slots: Res<SaveSlots>,
+    mut pending: ResMut<PendingLoad>,
+    mut daily: ResMut<DailyChallenge>,
+    mut records: ResMut<RecordsWindow>,
 ) {
 
     SidePanel::left("menu")
         .default_width(200.0)
         .resizable(false)
         .show(egui_ctx.ctx(), |ui| {
-            ui.heading("Bevy Template");
+            title.tween.tick(time.delta_seconds());
+            let mut color = Color::WHITE;
+            title.tween.apply(&mut color);
+            let [r, g, b, _] = color.as_rgba_f32();
+            ui.add(
+                Label::new("Bevy Template")
+                    .heading()
+                    .text_color(Color32::from_rgb(
+                        (r * 255.0) as u8,
+                        (g * 255.0) as u8,
+                        (b * 255.0) as u8,
+                    )),
+            );
             ui.separator();
             ui.label("Games");
             for s in GameState::iter() {
@@ -38,8 +84,30 @@ fn draw_menu(
                      }
                 }
             }
+            if ui.button("Daily Challenge").clicked() {
+                daily.date = Some(Date::today());
+                state.set(GameState::Tanks).unwrap();
+            }
+            if !slots.slots.is_empty() {
+                ui.separator();
+                ui.label("Continue Tanks");
+                for (slot, summary) in slots.slots.iter() {
+                    if ui.button(format!("Slot {} ({})", slot, summary)).clicked() {
+                        match read_slot(&config, *slot) {
+                            Ok(save) => {
+                                pending.0 = Some(save);
+                                state.set(GameState::Tanks).unwrap();
+                            }
+                            Err(e) => println!("Failed to load slot {}: {}", slot, e),
+                        }
+                    }
+                }
+            }
             ui.separator();
             if ui.button("Settings").clicked() { }
+            if ui.button("Records").clicked() {
+                records.open = !records.open;
+            }
 
             ui.separator();
             if ui.button("Exit").clicked() {
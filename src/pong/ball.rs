@@ -1,5 +1,7 @@
 use super::events::BallBounceEvent;
-use super::{Collider, Pong};
+use super::{primary_color, Collider, Pong};
+use crate::editor::GameTime;
+use crate::tween::{Ease, ScaleLens, Tween};
 use bevy::prelude::*;
 
 use bevy::ecs::system::{Commands, Query, Res};
@@ -13,6 +15,8 @@ use bevy::transform::components::Transform;
 use bevy::window::WindowResized;
 use rand::Rng;
 
+#[derive(Reflect)]
+#[reflect(Component)]
 pub struct Ball {
     speed: f32,
     direction: Vec2,
@@ -85,12 +89,22 @@ pub fn spawn_ball(commands: &mut Commands, material: Handle<ColorMaterial>, wind
             material: material,
             ..Default::default()
         })
+        .insert(primary_color())
         .insert(ball)
+        // collisions use the sprite size, so popping in is only for show
+        .insert(Tween::new(
+            ScaleLens {
+                from: Vec3::ZERO,
+                to: Vec3::ONE,
+            },
+            Ease::BackOut,
+            0.3,
+        ))
         .insert(Name::new("Ball"))
         .insert(Pong);
 }
 
-pub fn ball_movement_system(time: Res<Time>, mut query: Query<(&Ball, &mut Transform)>) {
+pub fn ball_movement_system(time: Res<GameTime>, mut query: Query<(&Ball, &mut Transform)>) {
     let time_delta = time.delta_seconds();
     for (ball, mut transform) in query.iter_mut() {
         transform.translation += time_delta * ball.velocity().extend(0.0);
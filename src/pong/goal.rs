@@ -3,11 +3,13 @@ use crate::pong::ball;
 use super::ball::Ball;
 use super::events::GoalEvent;
 use super::score::Score;
-use super::{Player, Pong, PongData};
+use super::{primary_color, Player, Pong, PongData};
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
 use bevy::window::WindowResized;
 
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 pub struct Goal;
 
 impl Goal {
@@ -46,6 +48,7 @@ fn spawn_goal(commands: &mut Commands, player: Player, material: Handle<ColorMat
             material: material,
             ..Default::default()
         })
+        .insert(primary_color())
         .insert(Goal)
         .insert(player)
         .insert(Name::new(format!("{:?} Goal", player)))
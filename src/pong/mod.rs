@@ -7,8 +7,9 @@ mod paddle;
 mod score;
 mod wall;
 
-use crate::GameState;
+use crate::{GameStages, GameState};
 use crate::audio::AudioState;
+use crate::editor::{RegisterSceneComponent, SceneColor};
 use crate::helpers::*;
 use audio::*;
 use bevy::prelude::*;
@@ -16,6 +17,7 @@ use bevy::window::WindowResized;
 use bevy_input_actionmap::*;
 use bevy_inspector_egui::widgets::ResourceInspector;
 use bevy_inspector_egui::*;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use self::ball::*;
@@ -25,6 +27,9 @@ use self::paddle::*;
 use self::score::*;
 use self::wall::*;
 
+/// Colour of every sprite, saved with them in scenes as a `SceneColor`
+const PRIMARY_COLOR: Color = Color::WHITE;
+
 #[derive(Inspectable, Debug)]
 pub struct PongData {
     #[inspectable(label = "Background Color")]
@@ -49,7 +54,7 @@ impl FromWorld for PongData {
 
         PongData {
             background: Color::BLACK,
-            primary_material: materials.add(Color::WHITE.into()),
+            primary_material: materials.add(PRIMARY_COLOR.into()),
             score: ResourceInspector::<Score>::default(),
             audio: AudioState::default(),
         }
@@ -64,17 +69,28 @@ impl Plugin for PongPlugin {
             .add_event::<BallBounceEvent>()
             .add_plugin(InspectorPlugin::<PongData>::new().open(false))
             .add_plugin(ActionPlugin::<PongAction>::default())
+            .register_scene_component::<Pong>()
+            .register_scene_component::<Paddle>()
+            .register_scene_component::<Ball>()
+            .register_scene_component::<Wall>()
+            .register_scene_component::<Goal>()
+            .register_scene_component::<Player>()
+            .register_scene_component::<Collider>()
             .add_system_set(
                 SystemSet::on_enter(GameState::Pong)
                     .with_system(setup.system())
             )
             .add_system_set(
                 SystemSet::on_update(GameState::Pong)
-                    .with_system(handle_audio_events.system())
                     .with_system(update_clear_color_system.system())
+                    .with_system(window_resize_listener.system())
+            )
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(GameState::Pong)
+                    .with_system(handle_audio_events.system())
                     .with_system(paddle_movement_system.system().label("input"))
                     .with_system(ball_movement_system.system().after("input"))
-                    .with_system(window_resize_listener.system())
                     .with_system(ball_collision_system.system())
                     .with_system(goal_collision_system.system().label("goal"))
                     .with_system(goal_scored_event.system().after("goal"))
@@ -108,12 +124,19 @@ impl fmt::Display for PongAction {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
 pub enum Player {
     Left,
     Right,
 }
 
+impl Default for Player {
+    fn default() -> Self {
+        Player::Left
+    }
+}
+
 impl Player {
     fn movement_actions(&self) -> (PongAction, PongAction) {
         match self {
@@ -123,10 +146,20 @@ impl Player {
     }
 }
 
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 struct Pong;
 
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 pub struct Collider;
 
+fn primary_color() -> SceneColor {
+    SceneColor {
+        color: PRIMARY_COLOR,
+    }
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
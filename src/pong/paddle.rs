@@ -1,10 +1,12 @@
 use super::wall::Wall;
-use super::{Collider, Player, Pong, PongAction};
+use super::{primary_color, Collider, Player, Pong, PongAction};
+use crate::editor::GameTime;
 use bevy::prelude::*;
 use bevy::window::{WindowDescriptor, WindowResized};
 use bevy_input_actionmap::*;
 
-#[derive(Default)]
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 pub struct Paddle {
     speed: f32,
 }
@@ -48,6 +50,7 @@ fn spawn_paddle(commands: &mut Commands, player: Player, material: Handle<ColorM
             material: material,
             ..Default::default()
         })
+        .insert(primary_color())
         .insert(Paddle::default())
         .insert(player)
         .insert(Collider)
@@ -57,7 +60,7 @@ fn spawn_paddle(commands: &mut Commands, player: Player, material: Handle<ColorM
 
 
 pub fn paddle_movement_system(
-    time: Res<Time>,
+    time: Res<GameTime>,
     input_map: Res<InputMap<PongAction>>,
     mut query: Query<(&Paddle, &Player, &mut Transform)>,
     window_desc: Res<WindowDescriptor>,
@@ -1,17 +1,26 @@
-use super::{Collider, Pong};
+use super::{primary_color, Collider, Pong};
 use bevy::core::Name;
 use bevy::ecs::system::Commands;
 use bevy::math::{Vec2, Vec3};
-use bevy::prelude::Handle;
+use bevy::prelude::{Handle, Reflect, ReflectComponent};
 use bevy::sprite::entity::SpriteBundle;
 use bevy::sprite::ColorMaterial;
 use bevy::window::WindowResized;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
 pub enum Wall {
     Top,
     Bottom,
 }
 
+impl Default for Wall {
+    fn default() -> Self {
+        Wall::Top
+    }
+}
+
 impl Wall {
     pub const THICKNESS: f32 = 20.0;
 
@@ -47,6 +56,7 @@ fn spawn_wall(commands: &mut Commands, wall: Wall, material: Handle<ColorMateria
             material: material,
             ..Default::default()
         })
+        .insert(primary_color())
         .insert(wall)
         .insert(Collider)
         .insert(Name::new("Wall"))
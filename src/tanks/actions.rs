@@ -0,0 +1,102 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy_input_actionmap::{ActionPlugin, InputMap};
+
+use crate::{helpers::cleanup_actions_system, GameState};
+
+use super::tank::PlayerId;
+
+pub struct TankActionsPlugin {
+    state: GameState,
+}
+
+impl TankActionsPlugin {
+    pub fn new(state: GameState) -> Self {
+        TankActionsPlugin { state: state }
+    }
+}
+
+impl Plugin for TankActionsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(ActionPlugin::<TankAction>::default())
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone()).with_system(setup_tank_actions.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone())
+                    .with_system(cleanup_actions_system::<TankAction>.system()),
+            );
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum TankControl {
+    Forward,
+    Back,
+    TurnLeft,
+    TurnRight,
+    Fire,
+    /// Artillery only, forward and back raise and lower the barrel there
+    PowerUp,
+    PowerDown,
+}
+
+/// Each local player gets their own set of controls
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct TankAction {
+    pub player: PlayerId,
+    pub control: TankControl,
+}
+
+impl TankAction {
+    pub fn new(player: PlayerId, control: TankControl) -> Self {
+        TankAction { player, control }
+    }
+}
+
+impl fmt::Display for TankAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let player = self.player.0 + 1;
+        match self.control {
+            TankControl::Forward => write!(f, "Player {} - Tank Forward", player),
+            TankControl::Back => write!(f, "Player {} - Tank Back", player),
+            TankControl::TurnLeft => write!(f, "Player {} - Tank Turn Left", player),
+            TankControl::TurnRight => write!(f, "Player {} - Tank Turn Right", player),
+            TankControl::Fire => write!(f, "Player {} - Tank Fire", player),
+            TankControl::PowerUp => write!(f, "Player {} - Tank Power Up", player),
+            TankControl::PowerDown => write!(f, "Player {} - Tank Power Down", player),
+        }
+    }
+}
+
+fn setup_tank_actions(mut input: ResMut<InputMap<TankAction>>) {
+    // player 1 is on the keyboard, the arrow keys already move the camera
+    let one = PlayerId(0);
+    input
+        .bind(TankAction::new(one, TankControl::Forward), KeyCode::Numpad8)
+        .bind(TankAction::new(one, TankControl::Back), KeyCode::Numpad5)
+        .bind(TankAction::new(one, TankControl::TurnLeft), KeyCode::Numpad4)
+        .bind(TankAction::new(one, TankControl::TurnRight), KeyCode::Numpad6)
+        .bind(TankAction::new(one, TankControl::Fire), KeyCode::Space)
+        .bind(TankAction::new(one, TankControl::PowerUp), KeyCode::PageUp)
+        .bind(TankAction::new(one, TankControl::PowerDown), KeyCode::PageDown);
+
+    // player 2 gets a gamepad, with a few keys as a fallback
+    let two = PlayerId(1);
+    input
+        .bind(TankAction::new(two, TankControl::Forward), GamepadButtonType::DPadUp)
+        .bind(TankAction::new(two, TankControl::Back), GamepadButtonType::DPadDown)
+        .bind(TankAction::new(two, TankControl::TurnLeft), GamepadButtonType::DPadLeft)
+        .bind(TankAction::new(two, TankControl::TurnRight), GamepadButtonType::DPadRight)
+        .bind(TankAction::new(two, TankControl::Fire), GamepadButtonType::South)
+        .bind(TankAction::new(two, TankControl::PowerUp), GamepadButtonType::RightTrigger)
+        .bind(TankAction::new(two, TankControl::PowerDown), GamepadButtonType::LeftTrigger)
+        .bind(TankAction::new(two, TankControl::Forward), KeyCode::I)
+        .bind(TankAction::new(two, TankControl::Back), KeyCode::K)
+        .bind(TankAction::new(two, TankControl::TurnLeft), KeyCode::J)
+        .bind(TankAction::new(two, TankControl::TurnRight), KeyCode::L)
+        .bind(TankAction::new(two, TankControl::Fire), KeyCode::U)
+        .bind(TankAction::new(two, TankControl::PowerUp), KeyCode::O)
+        .bind(TankAction::new(two, TankControl::PowerDown), KeyCode::P);
+}
@@ -0,0 +1,756 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_egui::{
+    egui::{self, Align2, Area},
+    EguiContext,
+};
+use bevy_input_actionmap::InputMap;
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+use bevy_prototype_debug_lines::DebugLines;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{editor::{GameTime, RegisterConsoleCommand}, GameStages, GameState};
+
+use super::{
+    actions::{TankAction, TankControl},
+    map::{MapLayout, MapSeed},
+    minimap::ScreenAxes,
+    projectile::{ballistic_hit, spawn_shell, Ballistic, ProjectileData, Shell},
+    split_screen::PlayerViewports,
+    tank::{PlayerId, Tank, TankData},
+    visibility::FogOfWar,
+    TanksData, TanksMode,
+};
+
+/// Turn based mode, players take turns lobbing shells at each other
+pub struct ArtilleryPlugin {
+    state: GameState,
+}
+
+impl ArtilleryPlugin {
+    pub fn new(state: GameState) -> Self {
+        ArtilleryPlugin { state: state }
+    }
+}
+
+impl Plugin for ArtilleryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<ArtilleryRules>::new().open(false))
+            .register_console_resource::<ArtilleryRules>("artillery")
+            .insert_resource(Artillery::default())
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone()).with_system(reset_turns.system()),
+            )
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(add_turrets.system())
+                    .with_system(run_turns.system().label("artillery_turns"))
+                    .with_system(
+                        aim_turret
+                            .system()
+                            .label("artillery_aim")
+                            .after("artillery_turns"),
+                    )
+                    .with_system(
+                        computer_turn
+                            .system()
+                            .label("artillery_aim")
+                            .after("artillery_turns"),
+                    ),
+            )
+            // still drawn while paused, gameplay runs after these so they show last frame's aim
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(draw_trajectory.system())
+                    .with_system(draw_turn_panel.system()),
+            );
+    }
+}
+
+#[derive(Inspectable, Debug, Reflect)]
+pub struct ArtilleryRules {
+    /// World units per second squared
+    #[inspectable(min = 0.0)]
+    pub gravity: f32,
+    /// Strongest wind a turn can roll, world units per second squared
+    #[inspectable(min = 0.0)]
+    pub max_wind: f32,
+    /// Shell speed in world units per second
+    #[inspectable(min = 0.0)]
+    pub min_power: f32,
+    #[inspectable(min = 0.0)]
+    pub max_power: f32,
+    /// Radians per second
+    #[inspectable(min = 0.0)]
+    pub aim_speed: f32,
+    /// Power per second
+    #[inspectable(min = 0.0)]
+    pub power_speed: f32,
+    /// Seconds of flight shown by the trajectory preview
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub preview_time: f32,
+    /// Seconds to let explosions play out before the next turn
+    #[inspectable(min = 0.0)]
+    pub settle_time: f32,
+    /// Seconds before a shell still in the air goes off on its own
+    #[inspectable(min = 0.0)]
+    pub shell_lifetime: f32,
+    /// How far off computer players are, as a fraction of their power
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub computer_error: f32,
+}
+
+impl Default for ArtilleryRules {
+    fn default() -> Self {
+        ArtilleryRules {
+            gravity: 1500.0,
+            max_wind: 250.0,
+            min_power: 300.0,
+            max_power: 2500.0,
+            aim_speed: 0.8,
+            power_speed: 800.0,
+            preview_time: 1.5,
+            settle_time: 1.5,
+            shell_lifetime: 10.0,
+            computer_error: 0.05,
+        }
+    }
+}
+
+/// Steepest the barrel goes, straight up would land on ourselves
+const MAX_ELEVATION: f32 = FRAC_PI_2 - 0.1;
+
+/// Seconds between points when simulating a trajectory
+const SIM_STEP: f32 = 1.0 / 60.0;
+
+/// Barrel of a tank in artillery mode, it points the way the tank faces
+#[derive(Debug, Clone, Copy)]
+pub struct Turret {
+    /// Radians above the ground
+    pub elevation: f32,
+    pub power: f32,
+}
+
+impl Turret {
+    pub fn direction(&self, transform: &Transform) -> Vec3 {
+        transform.forward() * self.elevation.cos() + Vec3::Y * self.elevation.sin()
+    }
+}
+
+#[derive(Debug)]
+pub enum TurnPhase {
+    /// Waiting on the current player to fire
+    Aiming,
+    /// The turn ends once this shell comes down
+    InFlight(Entity),
+    /// Explosions and chain reactions play out before the next turn
+    Settling(Timer),
+}
+
+/// Turn state machine, only runs while the game is in artillery mode
+#[derive(Debug)]
+pub struct Artillery {
+    pub phase: TurnPhase,
+    /// Whose turn it is
+    pub player: Option<PlayerId>,
+    pub turn: u32,
+    /// Rolled each turn, world units per second squared
+    pub wind: Vec3,
+}
+
+impl Default for Artillery {
+    fn default() -> Self {
+        Artillery {
+            phase: TurnPhase::Settling(Timer::from_seconds(0.5, false)),
+            player: None,
+            turn: 0,
+            wind: Vec3::ZERO,
+        }
+    }
+}
+
+impl Artillery {
+    /// Pull on every shell this turn
+    pub fn acceleration(&self, rules: &ArtilleryRules) -> Vec3 {
+        Vec3::new(0.0, -rules.gravity, 0.0) + self.wind
+    }
+}
+
+/// What a turn's dice are rolled for, each has its own stream so one doesn't shift the other
+#[derive(Debug, Clone, Copy)]
+enum TurnRoll {
+    Wind = 0,
+    Aim = 1,
+}
+
+/// Randomness for one turn, the same for the same map seed and turn so games play out the
+/// same way again
+fn turn_rng(seed: u64, turn: u32, roll: TurnRoll) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(turn as u64 * 2 + roll as u64);
+    rng
+}
+
+fn roll_wind(rules: &ArtilleryRules, seed: u64, turn: u32) -> Vec3 {
+    let mut rng = turn_rng(seed, turn, TurnRoll::Wind);
+    let angle = rng.gen_range(0.0..TAU);
+    let strength = rng.gen::<f32>() * rules.max_wind;
+    Vec3::new(angle.cos(), 0.0, angle.sin()) * strength
+}
+
+/// Steps a lobbed shell until it comes down or runs out of time, returning the points it
+/// passes through, shells in flight move the same way
+pub fn simulate(
+    layout: &MapLayout,
+    fog: &FogOfWar,
+    start: Vec3,
+    velocity: Vec3,
+    acceleration: Vec3,
+    max_time: f32,
+) -> Vec<Vec3> {
+    let mut points = vec![start];
+    let mut position = start;
+    let mut velocity = velocity;
+    let mut time = 0.0;
+    while time < max_time {
+        velocity += acceleration * SIM_STEP;
+        position += velocity * SIM_STEP;
+        time += SIM_STEP;
+        points.push(position);
+        if ballistic_hit(layout, fog, position) {
+            break;
+        }
+    }
+    points
+}
+
+fn muzzle(layout: &MapLayout, transform: &Transform) -> Vec3 {
+    transform.translation + transform.forward() * layout.cell_size * 0.4
+}
+
+fn fire_turret(
+    commands: &mut Commands,
+    layout: &MapLayout,
+    projectiles: &ProjectileData,
+    rules: &ArtilleryRules,
+    artillery: &mut Artillery,
+    tank: Entity,
+    turret: &Turret,
+    transform: &Transform,
+) {
+    let velocity = turret.direction(transform) * turret.power;
+    let shell = spawn_shell(
+        commands,
+        projectiles,
+        Some(tank),
+        muzzle(layout, transform),
+        velocity,
+    );
+    commands
+        .entity(shell)
+        .insert(Shell {
+            velocity,
+            owner: Some(tank),
+            lifetime: Timer::from_seconds(rules.shell_lifetime, false),
+        })
+        .insert(Ballistic {
+            acceleration: artillery.acceleration(rules),
+        });
+    artillery.phase = TurnPhase::InFlight(shell);
+}
+
+fn reset_turns(mut artillery: ResMut<Artillery>) {
+    *artillery = Artillery::default();
+}
+
+fn add_turrets(
+    mut commands: Commands,
+    rules: Res<ArtilleryRules>,
+    query: Query<Entity, (Added<Tank>, Without<Turret>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(Turret {
+            elevation: FRAC_PI_4,
+            power: (rules.min_power + rules.max_power) * 0.5,
+        });
+    }
+}
+
+/// Moves the turn along, after a shell lands and things settle it's the next player's go
+fn run_turns(
+    time: Res<GameTime>,
+    tanks_data: Res<TanksData>,
+    rules: Res<ArtilleryRules>,
+    seed: Res<MapSeed>,
+    mut artillery: ResMut<Artillery>,
+    tanks: Query<&Tank>,
+    shells: Query<(), With<Shell>>,
+) {
+    if tanks_data.mode != TanksMode::Artillery {
+        return;
+    }
+    let artillery = &mut *artillery;
+
+    let landed = match artillery.phase {
+        TurnPhase::InFlight(shell) => shells.get(shell).is_err(),
+        _ => false,
+    };
+    if landed {
+        artillery.phase = TurnPhase::Settling(Timer::from_seconds(rules.settle_time, false));
+        return;
+    }
+
+    let turn_over = match &mut artillery.phase {
+        // the tank whose turn it was is gone
+        TurnPhase::Aiming => {
+            let player = artillery.player;
+            !tanks.iter().any(|tank| Some(tank.player) == player)
+        }
+        TurnPhase::InFlight(_) => false,
+        TurnPhase::Settling(timer) => timer.tick(time.delta()).finished(),
+    };
+    if !turn_over {
+        return;
+    }
+
+    let mut players: Vec<PlayerId> = tanks.iter().map(|tank| tank.player).collect();
+    players.sort();
+    let next = players
+        .iter()
+        .find(|player| Some(**player) > artillery.player)
+        .or_else(|| players.first())
+        .copied();
+
+    match next {
+        Some(player) => {
+            artillery.player = Some(player);
+            artillery.turn += 1;
+            artillery.wind = roll_wind(&rules, seed.current, artillery.turn);
+            artillery.phase = TurnPhase::Aiming;
+        }
+        // everyone is waiting to respawn
+        None => {
+            artillery.phase = TurnPhase::Settling(Timer::from_seconds(rules.settle_time, false))
+        }
+    }
+}
+
+/// Local players turn the tank, raise the barrel, set the power and fire
+fn aim_turret(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    tanks_data: Res<TanksData>,
+    tank_data: Res<TankData>,
+    rules: Res<ArtilleryRules>,
+    layout: Res<MapLayout>,
+    projectiles: Res<ProjectileData>,
+    input: Res<InputMap<TankAction>>,
+    mut artillery: ResMut<Artillery>,
+    mut tanks: Query<(Entity, &Tank, &mut Turret, &mut Transform)>,
+) {
+    if tanks_data.mode != TanksMode::Artillery {
+        return;
+    }
+    if !matches!(artillery.phase, TurnPhase::Aiming) {
+        return;
+    }
+
+    for (entity, tank, mut turret, mut transform) in tanks.iter_mut() {
+        if Some(tank.player) != artillery.player || !tanks_data.is_local(tank.player) {
+            continue;
+        }
+        let active = |control| input.active(TankAction::new(tank.player, control));
+        let delta = time.delta_seconds();
+
+        let mut turn = 0.0;
+        if active(TankControl::TurnLeft) {
+            turn += 1.0;
+        }
+        if active(TankControl::TurnRight) {
+            turn -= 1.0;
+        }
+        transform.rotate(Quat::from_rotation_y(turn * tank_data.turn_speed * delta));
+
+        if active(TankControl::Forward) {
+            turret.elevation += rules.aim_speed * delta;
+        }
+        if active(TankControl::Back) {
+            turret.elevation -= rules.aim_speed * delta;
+        }
+        turret.elevation = turret.elevation.clamp(0.0, MAX_ELEVATION);
+
+        if active(TankControl::PowerUp) {
+            turret.power += rules.power_speed * delta;
+        }
+        if active(TankControl::PowerDown) {
+            turret.power -= rules.power_speed * delta;
+        }
+        turret.power = turret.power.clamp(rules.min_power, rules.max_power);
+
+        if input.just_active(TankAction::new(tank.player, TankControl::Fire)) {
+            fire_turret(
+                &mut commands,
+                &layout,
+                &projectiles,
+                &rules,
+                &mut artillery,
+                entity,
+                &turret,
+                &transform,
+            );
+        }
+    }
+}
+
+/// Players nobody is sitting at turn to the nearest enemy and try a spread of powers, firing
+/// whichever lands closest, give or take a little
+fn computer_turn(
+    mut commands: Commands,
+    tanks_data: Res<TanksData>,
+    rules: Res<ArtilleryRules>,
+    seed: Res<MapSeed>,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    projectiles: Res<ProjectileData>,
+    mut artillery: ResMut<Artillery>,
+    mut tanks: Query<(Entity, &Tank, &mut Turret, &mut Transform)>,
+) {
+    if tanks_data.mode != TanksMode::Artillery {
+        return;
+    }
+    if !matches!(artillery.phase, TurnPhase::Aiming) {
+        return;
+    }
+    let player = match artillery.player {
+        Some(player) if !tanks_data.is_local(player) => player,
+        _ => return,
+    };
+
+    let (entity, team, position) = match tanks
+        .iter_mut()
+        .find(|(_, tank, ..)| tank.player == player)
+    {
+        Some((entity, tank, _, transform)) => (entity, tank.team, transform.translation),
+        None => return,
+    };
+    let target = tanks
+        .iter_mut()
+        .filter(|(_, tank, ..)| tank.team != team)
+        .map(|(.., transform)| transform.translation)
+        .min_by(|a, b| {
+            a.distance_squared(position)
+                .partial_cmp(&b.distance_squared(position))
+                .unwrap()
+        });
+    let target = match target {
+        Some(target) => target,
+        // nobody to shoot at, pass
+        None => {
+            artillery.phase = TurnPhase::Settling(Timer::from_seconds(rules.settle_time, false));
+            return;
+        }
+    };
+
+    let (_, _, mut turret, mut transform) = tanks.get_mut(entity).unwrap();
+    transform.look_at(Vec3::new(target.x, position.y, target.z), Vec3::Y);
+    turret.elevation = FRAC_PI_4;
+
+    let acceleration = artillery.acceleration(&rules);
+    let steps = 40;
+    let mut best = (f32::MAX, turret.power);
+    for step in 0..=steps {
+        let power =
+            rules.min_power + (rules.max_power - rules.min_power) * step as f32 / steps as f32;
+        let velocity = turret.direction(&transform) * power;
+        let points = simulate(
+            &layout,
+            &fog,
+            muzzle(&layout, &transform),
+            velocity,
+            acceleration,
+            rules.shell_lifetime,
+        );
+        let landing = *points.last().unwrap();
+        let miss = Vec2::new(landing.x - target.x, landing.z - target.z).length();
+        if miss < best.0 {
+            best = (miss, power);
+        }
+    }
+
+    let error = turn_rng(seed.current, artillery.turn, TurnRoll::Aim).gen_range(-1.0..=1.0)
+        * rules.computer_error;
+    turret.power = (best.1 * (1.0 + error)).clamp(rules.min_power, rules.max_power);
+    fire_turret(
+        &mut commands,
+        &layout,
+        &projectiles,
+        &rules,
+        &mut artillery,
+        entity,
+        &turret,
+        &transform,
+    );
+}
+
+/// Where the current local player's shell would go, for the first part of the flight
+fn draw_trajectory(
+    tanks_data: Res<TanksData>,
+    rules: Res<ArtilleryRules>,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    artillery: Res<Artillery>,
+    mut lines: ResMut<DebugLines>,
+    tanks: Query<(&Tank, &Turret, &Transform)>,
+) {
+    if tanks_data.mode != TanksMode::Artillery {
+        return;
+    }
+    if !matches!(artillery.phase, TurnPhase::Aiming) {
+        return;
+    }
+
+    for (tank, turret, transform) in tanks.iter() {
+        if Some(tank.player) != artillery.player || !tanks_data.is_local(tank.player) {
+            continue;
+        }
+        let points = simulate(
+            &layout,
+            &fog,
+            muzzle(&layout, transform),
+            turret.direction(transform) * turret.power,
+            artillery.acceleration(&rules),
+            rules.preview_time,
+        );
+        for pair in points.windows(2) {
+            lines.line(pair[0], pair[1], 0.0);
+        }
+    }
+}
+
+/// Arrow for which way a direction on the ground goes on screen
+fn wind_arrow(wind: Vec3, axes: &ScreenAxes) -> &'static str {
+    const ARROWS: [&str; 8] = ["→", "↗", "↑", "↖", "←", "↙", "↓", "↘"];
+    let angle = wind.dot(axes.up).atan2(wind.dot(axes.right));
+    let index = (angle / FRAC_PI_4).round() as i32;
+    ARROWS[index.rem_euclid(8) as usize]
+}
+
+fn draw_turn_panel(
+    egui_ctx: Res<EguiContext>,
+    tanks_data: Res<TanksData>,
+    artillery: Res<Artillery>,
+    viewports: Res<PlayerViewports>,
+    tanks: Query<(&Tank, &Turret)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    if tanks_data.mode != TanksMode::Artillery {
+        return;
+    }
+    let axes = ScreenAxes::of_views(&viewports, &cameras);
+
+    Area::new("artillery_turn")
+        .anchor(Align2::CENTER_TOP, [0.0, 10.0])
+        .show(egui_ctx.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let player = match artillery.player {
+                    Some(player) => player,
+                    None => {
+                        ui.label("Waiting for players");
+                        return;
+                    }
+                };
+                ui.heading(format!("Turn {} - Player {}", artillery.turn, player.0 + 1));
+                ui.label(format!(
+                    "Wind {:.0} {}",
+                    artillery.wind.length(),
+                    wind_arrow(artillery.wind, &axes)
+                ));
+                if let Some((_, turret)) = tanks.iter().find(|(tank, _)| tank.player == player) {
+                    ui.label(format!(
+                        "Elevation {:.0}°  Power {:.0}",
+                        turret.elevation * 180.0 / PI,
+                        turret.power
+                    ));
+                }
+                match artillery.phase {
+                    TurnPhase::Aiming if tanks_data.is_local(player) => ui.label("Aim and fire"),
+                    TurnPhase::Aiming => ui.label("Computer is aiming"),
+                    TurnPhase::InFlight(_) => ui.label("Shell away"),
+                    TurnPhase::Settling(_) => ui.label("Next turn"),
+                };
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_map() -> (MapLayout, FogOfWar) {
+        let layout = MapLayout::default();
+        let fog = FogOfWar::new(layout.width, layout.height);
+        (layout, fog)
+    }
+
+    #[test]
+    fn shells_fall_under_gravity() {
+        let (layout, fog) = open_map();
+        let start = Vec3::new(0.0, 300.0, 0.0);
+        let points = simulate(
+            &layout,
+            &fog,
+            start,
+            Vec3::ZERO,
+            Vec3::new(0.0, -1500.0, 0.0),
+            10.0,
+        );
+
+        let landing = *points.last().unwrap();
+        assert!(landing.y <= 0.0);
+        assert!(points[points.len() - 2].y > 0.0);
+        assert_eq!((landing.x, landing.z), (0.0, 0.0));
+        // velocity is updated before position, so after n steps it has dropped g dt² n(n+1)/2
+        let n = (points.len() - 1) as f32;
+        let drop = 1500.0 * SIM_STEP * SIM_STEP * n * (n + 1.0) * 0.5;
+        assert!((start.y - landing.y - drop).abs() < 0.1);
+    }
+
+    #[test]
+    fn wind_pushes_shells_sideways() {
+        let (layout, fog) = open_map();
+        let points = simulate(
+            &layout,
+            &fog,
+            Vec3::new(0.0, 300.0, 0.0),
+            Vec3::ZERO,
+            Vec3::new(250.0, -1500.0, 0.0),
+            10.0,
+        );
+
+        let landing = *points.last().unwrap();
+        assert_eq!(landing.z, 0.0);
+        // starting from rest, drift and drop grow together in the same ratio as wind and gravity
+        let ratio = landing.x / (300.0 - landing.y);
+        assert!((ratio - 250.0 / 1500.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn acceleration_adds_to_velocity() {
+        let (layout, fog) = open_map();
+        let velocity = Vec3::new(0.0, 0.0, 100.0);
+        let acceleration = Vec3::new(0.0, 0.0, 60.0);
+        let points = simulate(
+            &layout,
+            &fog,
+            Vec3::new(0.0, 100.0, 0.0),
+            velocity,
+            acceleration,
+            0.5,
+        );
+
+        // about 30 steps, give or take one from the time adding up
+        let steps = points.len() - 1;
+        assert!((29..=31).contains(&steps));
+        for (n, pair) in points.windows(2).enumerate() {
+            let expected = (velocity + acceleration * SIM_STEP * (n + 1) as f32) * SIM_STEP;
+            assert!((pair[1] - pair[0] - expected).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn shells_stop_at_obstacles_they_cant_clear() {
+        let (layout, mut fog) = open_map();
+        fog.set_blocked(6, 5, true);
+        let start = layout.cell_to_world(4, 5);
+        let velocity = Vec3::new(1000.0, 0.0, 0.0);
+
+        let low = simulate(
+            &layout,
+            &fog,
+            start + Vec3::Y * 50.0,
+            velocity,
+            Vec3::ZERO,
+            10.0,
+        );
+        assert_eq!(layout.world_to_cell(*low.last().unwrap()), Some((6, 5)));
+
+        // higher than the obstacle it flies over and off the map
+        let high = simulate(
+            &layout,
+            &fog,
+            start + Vec3::Y * 200.0,
+            velocity,
+            Vec3::ZERO,
+            10.0,
+        );
+        assert_eq!(layout.world_to_cell(*high.last().unwrap()), None);
+    }
+
+    #[test]
+    fn artillery_pull_is_gravity_plus_wind() {
+        let rules = ArtilleryRules::default();
+        let artillery = Artillery {
+            wind: Vec3::new(30.0, 0.0, -40.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            artillery.acceleration(&rules),
+            Vec3::new(30.0, -rules.gravity, -40.0)
+        );
+    }
+
+    #[test]
+    fn wind_arrows_point_the_way_the_wind_blows_on_screen() {
+        // the free tanks camera looks down with +z up the screen, so +x is to the left
+        let mut camera = Transform::from_xyz(0.0, 3500.0, 0.0);
+        camera.look_at(Vec3::ZERO, Vec3::Z);
+        let axes = ScreenAxes::of(&GlobalTransform::from(camera));
+        for wind in [
+            Vec3::X,
+            Vec3::Z,
+            -Vec3::X,
+            -Vec3::Z,
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(-1.0, 0.0, 1.0),
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+        ]
+        .iter()
+        {
+            // the same wind as the camera sees it, +x right and +y up
+            let seen = camera.rotation.inverse() * *wind;
+            let expected = match (seen.x.round() as i32, seen.y.round() as i32) {
+                (1, 0) => "→",
+                (1, 1) => "↗",
+                (0, 1) => "↑",
+                (-1, 1) => "↖",
+                (-1, 0) => "←",
+                (-1, -1) => "↙",
+                (0, -1) => "↓",
+                (1, -1) => "↘",
+                _ => unreachable!(),
+            };
+            assert_eq!(wind_arrow(*wind, &axes), expected, "for {}", wind);
+        }
+        assert_eq!(wind_arrow(Vec3::X, &axes), "←");
+        assert_eq!(wind_arrow(Vec3::Z, &axes), "↑");
+        // rounds to the nearest of the eight
+        assert_eq!(wind_arrow(Vec3::new(1.0, 0.0, 0.3), &axes), "←");
+        assert_eq!(wind_arrow(Vec3::new(-1.0, 0.0, -0.1), &axes), "→");
+    }
+
+    #[test]
+    fn turns_roll_the_same_wind_for_the_same_seed() {
+        let rules = ArtilleryRules::default();
+        assert_eq!(roll_wind(&rules, 42, 3), roll_wind(&rules, 42, 3));
+        assert_ne!(roll_wind(&rules, 42, 3), roll_wind(&rules, 42, 4));
+        assert_ne!(roll_wind(&rules, 42, 3), roll_wind(&rules, 43, 3));
+        assert!(roll_wind(&rules, 42, 3).length() <= rules.max_wind);
+        // aiming draws from a stream of its own
+        let wind = turn_rng(42, 3, TurnRoll::Wind).gen::<f32>();
+        assert_ne!(turn_rng(42, 3, TurnRoll::Aim).gen::<f32>(), wind);
+    }
+}
@@ -0,0 +1,240 @@
+use std::{env::var, time::Instant};
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::GameState;
+
+use super::{
+    minesweeper::{Difficulty, MinesweeperSettings},
+    streaming::{LoadedChunks, MapStreaming},
+};
+
+/// Runs the tanks map at a given size, reports frame times and exits
+///
+/// ```bash
+/// TANKS_BENCHMARK=256 cargo run --release
+/// ```
+///
+/// Optionally followed by how many seconds to measure, `TANKS_BENCHMARK=256,20`. The process
+/// exits with status 1 when the average frame rate is below [`TARGET_FPS`], so CI can run it
+///
+/// Streaming normally keeps only the chunks around the cameras and tanks loaded, the benchmark
+/// widens its radius so every chunk of the map is spawned and measured
+pub struct BenchmarkPlugin {
+    state: GameState,
+}
+
+impl BenchmarkPlugin {
+    pub fn new(state: GameState) -> Self {
+        BenchmarkPlugin { state: state }
+    }
+}
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(start_benchmark.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone()).with_system(enter_map.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(record_frames.system().after("streaming")),
+            );
+    }
+}
+
+/// Frame rate we consider interactive
+pub const TARGET_FPS: f32 = 30.0;
+
+/// Frames right after spawning aren't representative, skip this many seconds
+const WARMUP: f32 = 1.0;
+
+struct Benchmark {
+    size: u32,
+    duration: f32,
+    entered: Option<Instant>,
+    /// Seconds from entering the map until streaming has spawned every chunk
+    spawn_time: Option<f32>,
+    elapsed: f32,
+    frames: Vec<f32>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut settings: ResMut<MinesweeperSettings>,
+    mut streaming: ResMut<MapStreaming>,
+) {
+    let setting = match var("TANKS_BENCHMARK") {
+        Ok(setting) => setting,
+        Err(_) => return,
+    };
+
+    let mut parts = setting.split(',');
+    let size = parts
+        .next()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(256);
+    let duration = parts
+        .next()
+        .and_then(|s| s.trim().parse::<f32>().ok())
+        .unwrap_or(10.0);
+
+    println!("benchmark: {}x{} map for {} seconds", size, size, duration);
+    settings.difficulty = Difficulty::Custom;
+    settings.custom_width = size;
+    settings.custom_height = size;
+    settings.custom_mines = size * size / 10;
+    settings.no_guess = false;
+    // from any chunk this reaches every other one, so the whole map stays loaded
+    streaming.load_radius = (size + streaming.chunk_size - 1) / streaming.chunk_size.max(1);
+    commands.insert_resource(Benchmark {
+        size,
+        duration,
+        entered: None,
+        spawn_time: None,
+        elapsed: 0.0,
+        frames: Vec::new(),
+    });
+}
+
+/// Go straight into the game once loading is done
+fn start_benchmark(benchmark: Option<Res<Benchmark>>, mut state: ResMut<State<GameState>>) {
+    if let Some(benchmark) = benchmark {
+        if benchmark.entered.is_none() {
+            state.set(GameState::Tanks).unwrap();
+        }
+    }
+}
+
+fn enter_map(benchmark: Option<ResMut<Benchmark>>) {
+    if let Some(mut benchmark) = benchmark {
+        benchmark.entered = Some(Instant::now());
+    }
+}
+
+/// Average and 1% low frame times in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    pub frames: usize,
+    pub average: f32,
+    /// Average of the slowest 1% of frames
+    pub low: f32,
+}
+
+impl FrameStats {
+    pub fn new(frames: &[f32]) -> Option<Self> {
+        if frames.is_empty() {
+            return None;
+        }
+        let mut frames = frames.to_vec();
+        frames.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let average = frames.iter().sum::<f32>() / frames.len() as f32;
+        let slowest = &frames[frames.len() - (frames.len() / 100).max(1)..];
+        let low = slowest.iter().sum::<f32>() / slowest.len() as f32;
+        Some(FrameStats {
+            frames: frames.len(),
+            average,
+            low,
+        })
+    }
+
+    pub fn fps(&self) -> f32 {
+        1.0 / self.average
+    }
+
+    pub fn passed(&self) -> bool {
+        self.fps() >= TARGET_FPS
+    }
+}
+
+fn record_frames(
+    mut commands: Commands,
+    time: Res<Time>,
+    benchmark: Option<ResMut<Benchmark>>,
+    loaded: Res<LoadedChunks>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let mut benchmark = match benchmark {
+        Some(benchmark) => benchmark,
+        None => return,
+    };
+
+    // streaming spawns a few chunks a frame, wait until it has all of them
+    if benchmark.spawn_time.is_none() {
+        if loaded.chunks.is_empty() || loaded.pending > 0 {
+            return;
+        }
+        benchmark.spawn_time = benchmark.entered.map(|e| e.elapsed().as_secs_f32());
+        return;
+    }
+
+    benchmark.elapsed += time.delta_seconds();
+    if benchmark.elapsed < WARMUP {
+        return;
+    }
+    benchmark.frames.push(time.delta_seconds());
+    if benchmark.elapsed < WARMUP + benchmark.duration {
+        return;
+    }
+
+    // only report once, the app takes a frame or two to close
+    commands.remove_resource::<Benchmark>();
+    let stats = match FrameStats::new(&benchmark.frames) {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    println!(
+        "benchmark: map {}x{}, all {} chunks loaded",
+        benchmark.size,
+        benchmark.size,
+        loaded.chunks.len()
+    );
+    println!(
+        "benchmark: spawn {:.1} ms",
+        benchmark.spawn_time.unwrap_or_default() * 1000.0
+    );
+    println!(
+        "benchmark: {} frames, average {:.2} ms ({:.1} fps), 1% low {:.2} ms ({:.1} fps)",
+        stats.frames,
+        stats.average * 1000.0,
+        stats.fps(),
+        stats.low * 1000.0,
+        1.0 / stats.low
+    );
+    if stats.passed() {
+        println!("benchmark: pass, above {} fps", TARGET_FPS);
+        app_exit.send(AppExit);
+    } else {
+        // AppExit always leaves with success, CI needs to see the failure
+        println!("benchmark: fail, below {} fps", TARGET_FPS);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_average_and_find_the_slowest_frames() {
+        assert_eq!(FrameStats::new(&[]), None);
+
+        let mut frames = vec![0.01; 198];
+        frames.push(0.05);
+        frames.push(0.07);
+        let stats = FrameStats::new(&frames).unwrap();
+        assert_eq!(stats.frames, 200);
+        assert!((stats.average - 0.0105).abs() < 0.0001);
+        // 1% of 200 frames is the slowest two
+        assert!((stats.low - 0.06).abs() < 0.0001);
+        assert!(stats.passed());
+
+        let slow = FrameStats::new(&[0.05, 0.05]).unwrap();
+        assert!((slow.fps() - 20.0).abs() < 0.01);
+        assert!(!slow.passed());
+    }
+}
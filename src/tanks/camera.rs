@@ -7,7 +7,9 @@ use bevy_prototype_debug_lines::DebugLines;
 
 use crate::{GameState, editor::EditorState, helpers::cleanup_system};
 
-struct CameraComponent {
+use super::TanksData;
+
+pub struct CameraComponent {
     movement_speed: f32,
 }
 struct CameraCleanup;
@@ -67,7 +69,16 @@ impl fmt::Display for CameraActions {
     }
 }
 
-fn startup(mut commands: Commands) {
+fn startup(mut commands: Commands, data: Res<TanksData>) {
+    commands
+    .spawn_bundle(UiCameraBundle::default())
+    .insert(CameraCleanup);
+
+    // split screen gives each player their own camera
+    if data.local_players > 1 {
+        return;
+    }
+
     let offset = 3500.0;
     let mut camera = PerspectiveCameraBundle {
     //let mut camera = OrthographicCameraBundle {
@@ -99,10 +110,6 @@ fn startup(mut commands: Commands) {
     .insert_bundle(PickingCameraBundle::default())
     .insert(CameraCleanup);
 
-    commands
-    .spawn_bundle(UiCameraBundle::default())
-    .insert(CameraCleanup);
-
 }
 
 fn setup_camera_actions(mut input: ResMut<InputMap<CameraActions>>) {
@@ -126,8 +133,11 @@ fn clear_camera_actions(mut input: ResMut<InputMap<CameraActions>>) {
 fn run_actions(
     time: Res<Time>,
     input: Res<InputMap<CameraActions>>,
+    keys: Res<Input<KeyCode>>,
     mut query: Query<(&mut Transform, &CameraComponent)>,
 ) {
+    // ctrl+z is undo and redo in the editor, not zoom
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
     for (mut transform, camera) in query.iter_mut() {
         let mut direction = Vec3::ZERO;
         let scale = transform.scale.x;
@@ -148,11 +158,11 @@ fn run_actions(
             direction -= transform.up();
         }
 
-        if input.active(CameraActions::In) {
+        if input.active(CameraActions::In) && !ctrl {
             direction -= transform.forward();
         }
 
-        if input.active(CameraActions::Out) {
+        if input.active(CameraActions::Out) && !ctrl {
             direction += transform.forward();
         }
 
@@ -0,0 +1,441 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+use bevy_kira_audio::Audio;
+
+use crate::{
+    editor::GameTime, helpers::cleanup_system, loading::TanksAssets, GameStages, GameState,
+};
+
+use super::{
+    health::{DamageEvent, DamageType},
+    map::{
+        Destructible, MapCells, MapLayout, MineLocation, MineStatus, Obstacle, ObstacleModel,
+        Rubble,
+    },
+    streaming::{CellStore, StoredCell, Terrain},
+    tank::Tank,
+};
+
+pub struct ExplosionPlugin {
+    state: GameState,
+}
+
+impl ExplosionPlugin {
+    pub fn new(state: GameState) -> Self {
+        ExplosionPlugin { state: state }
+    }
+}
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ExplosionEvent>()
+            .add_plugin(InspectorPlugin::<ExplosionData>::new().open(false))
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(trigger_mines.system().before("explosion"))
+                    .with_system(detonate_pending.system().before("explosion"))
+                    .with_system(handle_explosions.system().label("explosion"))
+                    .with_system(explosion_audio.system())
+                    .with_system(spawn_explosion_effects.system())
+                    .with_system(update_explosion_effects.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone())
+                    .with_system(cleanup_system::<ExplosionEffect>.system()),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplosionSource {
+    Mine,
+    Shell,
+}
+
+/// Sent whenever something blows up, drives damage, chain reactions, audio and effects
+#[derive(Debug, Clone)]
+pub struct ExplosionEvent {
+    pub position: Vec3,
+    /// World units
+    pub radius: f32,
+    /// Damage at the center, falls off to zero at the radius
+    pub damage: f32,
+    pub source: ExplosionSource,
+    /// Tank that caused it, if any, so kills can be credited
+    pub instigator: Option<Entity>,
+}
+
+impl From<ExplosionSource> for DamageType {
+    fn from(source: ExplosionSource) -> Self {
+        match source {
+            ExplosionSource::Mine => DamageType::Mine,
+            ExplosionSource::Shell => DamageType::Shell,
+        }
+    }
+}
+
+#[derive(Inspectable, Debug)]
+pub struct ExplosionData {
+    /// Radius in cells
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub mine_radius: f32,
+    #[inspectable(min = 0.0)]
+    pub mine_damage: f32,
+    /// Radius in cells
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub shell_radius: f32,
+    #[inspectable(min = 0.0)]
+    pub shell_damage: f32,
+    /// Seconds before a mine caught in a blast goes off, so chains ripple out instead of popping at once
+    #[inspectable(min = 0.0, max = 2.0)]
+    pub chain_delay: f32,
+    /// Seconds the explosion effect is shown
+    #[inspectable(min = 0.0, max = 5.0)]
+    pub effect_duration: f32,
+
+    pub effect_mesh: Handle<Mesh>,
+    pub effect_material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ExplosionData {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+
+        let mut meshes = world
+            .get_resource_mut::<Assets<Mesh>>()
+            .expect("ResMut<Assets<Mesh>> not found.");
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .expect("ResMut<Assets<StandardMaterial>> not found.");
+
+        ExplosionData {
+            mine_radius: 1.5,
+            mine_damage: 60.0,
+            shell_radius: 0.75,
+            shell_damage: 35.0,
+            chain_delay: 0.15,
+            effect_duration: 0.5,
+            effect_mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 1.0,
+                subdivisions: 3,
+            })),
+            effect_material: materials.add(StandardMaterial {
+                base_color: Color::rgb(1.0, 0.55, 0.1),
+                unlit: true,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl ExplosionData {
+    pub fn explosion(
+        &self,
+        source: ExplosionSource,
+        position: Vec3,
+        instigator: Option<Entity>,
+        layout: &MapLayout,
+    ) -> ExplosionEvent {
+        let (radius, damage) = match source {
+            ExplosionSource::Mine => (self.mine_radius, self.mine_damage),
+            ExplosionSource::Shell => (self.shell_radius, self.shell_damage),
+        };
+        ExplosionEvent {
+            position,
+            radius: radius * layout.cell_size,
+            damage,
+            source,
+            instigator,
+        }
+    }
+}
+
+/// Linear falloff, full damage at the center and nothing at or past the radius
+pub fn falloff(distance: f32, radius: f32) -> f32 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - distance / radius).clamp(0.0, 1.0)
+}
+
+/// Expanding fireball, purely visual
+pub struct ExplosionEffect {
+    timer: Timer,
+    radius: f32,
+}
+
+/// Tanks driving onto a mine set it off
+fn trigger_mines(
+    data: Res<ExplosionData>,
+    layout: Res<MapLayout>,
+    map_cells: Res<MapCells>,
+    tanks: Query<&Transform, With<Tank>>,
+    mut cells: Query<&mut MineLocation>,
+    mut ev_explosion: EventWriter<ExplosionEvent>,
+) {
+    for transform in tanks.iter() {
+        let (x, y) = match layout.world_to_cell(transform.translation) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        let mut cell = match map_cells.get(x, y).and_then(|e| cells.get_mut(e).ok()) {
+            Some(cell) => cell,
+            None => continue,
+        };
+
+        if cell.status == (MineStatus::Unknown { mine: true }) {
+            cell.status = MineStatus::Exploded;
+            ev_explosion.send(data.explosion(
+                ExplosionSource::Mine,
+                layout.cell_to_world(x, y),
+                None,
+                &layout,
+            ));
+        }
+    }
+}
+
+/// Mines caught in a blast go off after a short delay, the countdowns live in the
+/// [`CellStore`] so they pause while their chunk is unloaded
+fn detonate_pending(
+    time: Res<GameTime>,
+    data: Res<ExplosionData>,
+    layout: Res<MapLayout>,
+    map_cells: Res<MapCells>,
+    mut store: ResMut<CellStore>,
+    mut cells: Query<&mut MineLocation>,
+    mut ev_explosion: EventWriter<ExplosionEvent>,
+) {
+    if !store.has_pending() {
+        return;
+    }
+
+    let due = store.tick_pending(time.delta_seconds(), |x, y| map_cells.get(x, y).is_some());
+    for (x, y) in due {
+        let mut cell = match map_cells.get(x, y).and_then(|e| cells.get_mut(e).ok()) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        // something else could have set it off in the mean time
+        if cell.status == (MineStatus::Unknown { mine: true }) {
+            cell.status = MineStatus::Exploded;
+            ev_explosion.send(data.explosion(
+                ExplosionSource::Mine,
+                layout.cell_to_world(x, y),
+                None,
+                &layout,
+            ));
+        }
+    }
+}
+
+/// Applies each explosion to the world, damaging tanks, arming nearby mines and breaking destructible cells
+///
+/// Cells are read from and broken in the [`CellStore`] so blasts reach into unloaded chunks too,
+/// loaded cells get their entities updated as well since they won't be spawned again
+fn handle_explosions(
+    mut commands: Commands,
+    data: Res<ExplosionData>,
+    layout: Res<MapLayout>,
+    map_cells: Res<MapCells>,
+    mut store: ResMut<CellStore>,
+    mut ev_explosion: EventReader<ExplosionEvent>,
+    mut ev_damage: EventWriter<DamageEvent>,
+    tanks: Query<(Entity, &Transform), With<Tank>>,
+    cells: Query<(&MineLocation, Option<&Children>)>,
+    models: Query<(), With<ObstacleModel>>,
+) {
+    for explosion in ev_explosion.iter() {
+        for (entity, transform) in tanks.iter() {
+            let distance = transform.translation.distance(explosion.position);
+            let amount = explosion.damage * falloff(distance, explosion.radius);
+            if amount > 0.0 {
+                ev_damage.send(DamageEvent {
+                    target: entity,
+                    amount,
+                    kind: explosion.source.into(),
+                    instigator: explosion.instigator,
+                });
+            }
+        }
+
+        for (x, y) in layout.cells_within(explosion.position, explosion.radius) {
+            let stored = match store.get(x, y) {
+                Some(stored) => stored,
+                None => continue,
+            };
+            // a loaded cell can be ahead of the store until it is written back
+            let loaded = map_cells
+                .get(x, y)
+                .and_then(|entity| cells.get(entity).ok().map(|cell| (entity, cell)));
+            let status = loaded
+                .map(|(_, (cell, _))| cell.status)
+                .unwrap_or_else(|| stored.status());
+
+            if status == (MineStatus::Unknown { mine: true }) {
+                store.arm(x, y, data.chain_delay);
+            }
+
+            let destructible = match stored.terrain() {
+                Terrain::Obstacle(kind) => kind.destructible(),
+                _ => false,
+            };
+            if !destructible {
+                continue;
+            }
+            store.set(x, y, StoredCell::new(status, Terrain::Rubble));
+            if let Some((entity, (_, children))) = loaded {
+                commands
+                    .entity(entity)
+                    .remove::<Obstacle>()
+                    .remove::<Destructible>()
+                    .insert(Rubble);
+                for child in children.iter().flat_map(|c| c.iter()) {
+                    if models.get(*child).is_ok() {
+                        commands.entity(*child).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn explosion_audio(
+    mut ev_explosion: EventReader<ExplosionEvent>,
+    audio: Res<Audio>,
+    assets: Res<TanksAssets>,
+) {
+    for _ in ev_explosion.iter() {
+        audio.play(assets.explosion.clone());
+    }
+}
+
+fn spawn_explosion_effects(
+    mut commands: Commands,
+    data: Res<ExplosionData>,
+    mut ev_explosion: EventReader<ExplosionEvent>,
+) {
+    for explosion in ev_explosion.iter() {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: data.effect_mesh.clone(),
+                material: data.effect_material.clone(),
+                transform: Transform {
+                    translation: explosion.position,
+                    scale: Vec3::ZERO,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(ExplosionEffect {
+                timer: Timer::from_seconds(data.effect_duration, false),
+                radius: explosion.radius,
+            })
+            .insert(Name::new(format!("{:?} Explosion", explosion.source)));
+    }
+}
+
+/// Grow the fireball out to the blast radius then remove it
+fn update_explosion_effects(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut query: Query<(Entity, &mut ExplosionEffect, &mut Transform)>,
+) {
+    for (entity, mut effect, mut transform) in query.iter_mut() {
+        effect.timer.tick(time.delta());
+        if effect.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.scale = Vec3::splat(effect.radius * effect.timer.percent());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tanks::streaming::ObstacleKind;
+
+    #[test]
+    fn falloff_is_full_at_the_center_and_gone_at_the_edge() {
+        assert_eq!(falloff(0.0, 100.0), 1.0);
+        assert_eq!(falloff(50.0, 100.0), 0.5);
+        assert!((falloff(75.0, 100.0) - 0.25).abs() < 0.0001);
+        assert_eq!(falloff(100.0, 100.0), 0.0);
+    }
+
+    #[test]
+    fn nothing_outside_the_radius() {
+        assert_eq!(falloff(150.0, 100.0), 0.0);
+        assert_eq!(falloff(f32::MAX, 100.0), 0.0);
+        // a blast with no size hurts nothing, even right on top of it
+        assert_eq!(falloff(0.0, 0.0), 0.0);
+        assert_eq!(falloff(0.0, -5.0), 0.0);
+    }
+
+    #[test]
+    fn blasts_reach_into_unloaded_chunks() {
+        let layout = MapLayout::default();
+        let mut store = CellStore::new(layout.width, layout.height);
+        store.set(
+            5,
+            5,
+            StoredCell::new(
+                MineStatus::Unknown { mine: false },
+                Terrain::Obstacle(ObstacleKind::Crate),
+            ),
+        );
+        store.set(
+            6,
+            5,
+            StoredCell::new(MineStatus::Unknown { mine: true }, Terrain::Open),
+        );
+        store.set(
+            5,
+            6,
+            StoredCell::new(
+                MineStatus::Unknown { mine: false },
+                Terrain::Obstacle(ObstacleKind::Column),
+            ),
+        );
+
+        let mut world = World::default();
+        world.insert_resource(ExplosionData {
+            mine_radius: 1.5,
+            mine_damage: 60.0,
+            shell_radius: 0.75,
+            shell_damage: 35.0,
+            chain_delay: 0.15,
+            effect_duration: 0.5,
+            effect_mesh: Handle::default(),
+            effect_material: Handle::default(),
+        });
+        world.insert_resource(layout);
+        // nothing is spawned, as if the whole map was streamed out
+        world.insert_resource(MapCells::new(layout.width, layout.height));
+        world.insert_resource(store);
+        world.insert_resource(Events::<DamageEvent>::default());
+        let mut events = Events::<ExplosionEvent>::default();
+        let data = world.get_resource::<ExplosionData>().unwrap();
+        events.send(data.explosion(
+            ExplosionSource::Mine,
+            layout.cell_to_world(5, 5),
+            None,
+            &layout,
+        ));
+        world.insert_resource(events);
+
+        let mut stage = SystemStage::single(handle_explosions.system());
+        stage.run(&mut world);
+
+        let store = world.get_resource::<CellStore>().unwrap();
+        assert_eq!(store.get(5, 5).unwrap().terrain(), Terrain::Rubble);
+        assert!(store.is_pending(6, 5));
+        // columns don't break
+        assert_eq!(
+            store.get(5, 6).unwrap().terrain(),
+            Terrain::Obstacle(ObstacleKind::Column)
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+use crate::{editor::GameTime, GameStages, GameState};
+
+use super::{
+    map::MapLayout,
+    tank::{spawn_tank, PlayerId, SpawnPoint, Tank, TankData, Team},
+    TanksData,
+};
+
+pub struct HealthPlugin {
+    state: GameState,
+}
+
+impl HealthPlugin {
+    pub fn new(state: GameState) -> Self {
+        HealthPlugin { state: state }
+    }
+}
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<DamageEvent>()
+            .add_event::<TankDestroyedEvent>()
+            .insert_resource(Scoreboard::default())
+            .insert_resource(Respawns::default())
+            .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(reset.system()))
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(track_players.system())
+                    .with_system(tank_collisions.system().before("damage"))
+                    .with_system(apply_damage.system().label("damage"))
+                    .with_system(tick_invulnerable.system().after("damage"))
+                    .with_system(destroy_tanks.system().after("damage"))
+                    .with_system(respawn_tanks.system()),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageType {
+    Shell,
+    Mine,
+    Collision,
+}
+
+/// Damage for a single entity, before armor and [`DamageRules`] are applied
+#[derive(Debug, Clone)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageType,
+    /// Tank responsible, if any, so kills can be credited
+    pub instigator: Option<Entity>,
+}
+
+pub struct TankDestroyedEvent {
+    pub tank: Entity,
+    pub player: PlayerId,
+    pub team: Team,
+    pub killer: Option<PlayerId>,
+}
+
+/// How tanks take damage, lives in [`TanksData`] so it can be tweaked from the inspector
+#[derive(Inspectable, Debug, Reflect)]
+pub struct DamageRules {
+    #[inspectable(min = 1.0)]
+    pub max_health: f32,
+    /// Fraction of incoming damage ignored
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub armor: f32,
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub shell_multiplier: f32,
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub mine_multiplier: f32,
+    /// Damage each tank takes when two tanks run into each other
+    #[inspectable(min = 0.0)]
+    pub collision_damage: f32,
+    /// Seconds a tank ignores damage after being hit or respawning
+    #[inspectable(min = 0.0, max = 10.0)]
+    pub invulnerable_time: f32,
+    #[inspectable(min = 0.0, max = 30.0)]
+    pub respawn_time: f32,
+    #[inspectable(min = 1)]
+    pub lives: u32,
+    /// Score for destroying another player's tank
+    pub kill_score: u32,
+}
+
+impl Default for DamageRules {
+    fn default() -> Self {
+        DamageRules {
+            max_health: 100.0,
+            armor: 0.1,
+            shell_multiplier: 1.0,
+            mine_multiplier: 1.0,
+            collision_damage: 5.0,
+            invulnerable_time: 0.5,
+            respawn_time: 3.0,
+            lives: 3,
+            kill_score: 100,
+        }
+    }
+}
+
+impl DamageRules {
+    /// Damage actually taken from a hit
+    pub fn damage(&self, kind: DamageType, amount: f32, armor: f32) -> f32 {
+        let multiplier = match kind {
+            DamageType::Shell => self.shell_multiplier,
+            DamageType::Mine => self.mine_multiplier,
+            DamageType::Collision => 1.0,
+        };
+        (amount * multiplier * (1.0 - armor.clamp(0.0, 1.0))).max(0.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Fraction of incoming damage ignored
+    pub armor: f32,
+}
+
+impl Health {
+    pub fn new(max: f32, armor: f32) -> Self {
+        Health {
+            current: max,
+            max,
+            armor,
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            return 0.0;
+        }
+        (self.current / self.max).clamp(0.0, 1.0)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Tank ignores all damage until the timer runs out
+pub struct Invulnerable {
+    timer: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Invulnerable {
+            timer: Timer::from_seconds(seconds, false),
+        }
+    }
+
+    /// Seconds left
+    pub fn remaining(&self) -> f32 {
+        (self.timer.duration().as_secs_f32() - self.timer.elapsed_secs()).max(0.0)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRecord {
+    pub team: Option<Team>,
+    pub lives: u32,
+    pub score: u32,
+    pub deaths: u32,
+}
+
+/// Lives and score for each player in the current game
+#[derive(Debug, Default)]
+pub struct Scoreboard {
+    pub players: BTreeMap<PlayerId, PlayerRecord>,
+}
+
+impl Scoreboard {
+    pub fn player(&mut self, player: PlayerId, rules: &DamageRules) -> &mut PlayerRecord {
+        self.players.entry(player).or_insert_with(|| PlayerRecord {
+            lives: rules.lives,
+            ..Default::default()
+        })
+    }
+}
+
+struct PendingRespawn {
+    player: PlayerId,
+    team: Team,
+    timer: Timer,
+}
+
+/// Destroyed tanks waiting to come back
+#[derive(Default)]
+pub struct Respawns(Vec<PendingRespawn>);
+
+impl Respawns {
+    /// Player, team and seconds left for each pending respawn
+    pub fn pending(&self) -> impl Iterator<Item = (PlayerId, Team, f32)> + '_ {
+        self.0.iter().map(|respawn| {
+            let remaining =
+                respawn.timer.duration().as_secs_f32() - respawn.timer.elapsed_secs();
+            (respawn.player, respawn.team, remaining.max(0.0))
+        })
+    }
+
+    pub fn push(&mut self, player: PlayerId, team: Team, seconds: f32) {
+        self.0.push(PendingRespawn {
+            player,
+            team,
+            timer: Timer::from_seconds(seconds, false),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+fn reset(mut scoreboard: ResMut<Scoreboard>, mut respawns: ResMut<Respawns>) {
+    scoreboard.players.clear();
+    respawns.0.clear();
+}
+
+/// Every tank's player gets a record as soon as the tank shows up
+fn track_players(
+    data: Res<TanksData>,
+    mut scoreboard: ResMut<Scoreboard>,
+    query: Query<&Tank, Added<Tank>>,
+) {
+    for tank in query.iter() {
+        scoreboard.player(tank.player, &data.damage).team = Some(tank.team);
+    }
+}
+
+/// Pairs of tanks that have just run into each other, `touching` holds the pairs that were
+/// already touching and is updated to the ones touching now
+pub fn new_contacts(
+    tanks: &[(Entity, Vec3)],
+    distance: f32,
+    touching: &mut HashSet<(Entity, Entity)>,
+) -> Vec<(Entity, Entity)> {
+    let mut now = HashSet::new();
+    for (i, (a, a_pos)) in tanks.iter().enumerate() {
+        for (b, b_pos) in tanks.iter().skip(i + 1) {
+            if a_pos.distance(*b_pos) < distance {
+                now.insert(if a < b { (*a, *b) } else { (*b, *a) });
+            }
+        }
+    }
+
+    let mut started: Vec<(Entity, Entity)> = now.difference(touching).copied().collect();
+    started.sort();
+    *touching = now;
+    started
+}
+
+/// Tanks that run into each other both take collision damage, once per bump rather than
+/// every frame they stay in contact
+fn tank_collisions(
+    data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    mut touching: Local<HashSet<(Entity, Entity)>>,
+    query: Query<(Entity, &Transform), With<Tank>>,
+    mut ev_damage: EventWriter<DamageEvent>,
+) {
+    let tanks: Vec<(Entity, Vec3)> = query
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation))
+        .collect();
+
+    for (a, b) in new_contacts(&tanks, layout.cell_size * 0.6, &mut touching) {
+        ev_damage.send(DamageEvent {
+            target: a,
+            amount: data.damage.collision_damage,
+            kind: DamageType::Collision,
+            instigator: Some(b),
+        });
+        ev_damage.send(DamageEvent {
+            target: b,
+            amount: data.damage.collision_damage,
+            kind: DamageType::Collision,
+            instigator: Some(a),
+        });
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    data: Res<TanksData>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_destroyed: EventWriter<TankDestroyedEvent>,
+    mut query: Query<(&mut Health, &Tank, Option<&Invulnerable>)>,
+) {
+    for damage in ev_damage.iter() {
+        let killer = damage
+            .instigator
+            .and_then(|e| query.get_mut(e).ok())
+            .map(|(_, tank, _)| tank.player);
+
+        let (mut health, tank, invulnerable) = match query.get_mut(damage.target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        if invulnerable.is_some() || health.is_dead() {
+            continue;
+        }
+
+        health.current -= data.damage.damage(damage.kind, damage.amount, health.armor);
+        if health.is_dead() {
+            ev_destroyed.send(TankDestroyedEvent {
+                tank: damage.target,
+                player: tank.player,
+                team: tank.team,
+                killer,
+            });
+        } else {
+            commands
+                .entity(damage.target)
+                .insert(Invulnerable::new(data.damage.invulnerable_time));
+        }
+    }
+}
+
+fn tick_invulnerable(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        if invulnerable.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+/// Remove destroyed tanks, take a life and queue up a respawn if they have any left
+fn destroy_tanks(
+    mut commands: Commands,
+    data: Res<TanksData>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut respawns: ResMut<Respawns>,
+    mut ev_destroyed: EventReader<TankDestroyedEvent>,
+) {
+    for destroyed in ev_destroyed.iter() {
+        commands.entity(destroyed.tank).despawn_recursive();
+
+        if let Some(killer) = destroyed.killer {
+            if killer != destroyed.player {
+                scoreboard.player(killer, &data.damage).score += data.damage.kill_score;
+            }
+        }
+
+        let record = scoreboard.player(destroyed.player, &data.damage);
+        record.team = Some(destroyed.team);
+        record.deaths += 1;
+        record.lives = record.lives.saturating_sub(1);
+
+        if record.lives > 0 {
+            respawns.0.push(PendingRespawn {
+                player: destroyed.player,
+                team: destroyed.team,
+                timer: Timer::from_seconds(data.damage.respawn_time, false),
+            });
+        }
+    }
+}
+
+fn respawn_tanks(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    tank_data: Res<TankData>,
+    data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    mut respawns: ResMut<Respawns>,
+    spawn_points: Query<(&SpawnPoint, &Transform)>,
+) {
+    for respawn in respawns.0.iter_mut() {
+        respawn.timer.tick(time.delta());
+    }
+
+    let (ready, waiting): (Vec<_>, Vec<_>) = respawns
+        .0
+        .drain(..)
+        .partition(|respawn| respawn.timer.finished());
+    respawns.0 = waiting;
+
+    for respawn in ready {
+        let spawn = spawn_points
+            .iter()
+            .find(|(point, _)| point.player == respawn.player)
+            .map(|(_, transform)| *transform)
+            .unwrap_or_else(|| Transform::from_translation(layout.cell_to_world(0, 0)));
+
+        let tank = spawn_tank(
+            &mut commands,
+            &tank_data,
+            &data,
+            &layout,
+            respawn.team,
+            respawn.player,
+            spawn,
+        );
+        commands
+            .entity(tank)
+            .insert(Invulnerable::new(data.damage.invulnerable_time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tank(player: u32) -> Tank {
+        Tank {
+            team: Team::Friendly,
+            player: PlayerId(player),
+            reload: Timer::from_seconds(1.0, false),
+        }
+    }
+
+    fn setup() -> (World, SystemStage) {
+        let mut world = World::default();
+        let data = TanksData::from_world(&mut world);
+        world.insert_resource(data);
+        world.insert_resource(Scoreboard::default());
+        world.insert_resource(Respawns::default());
+        world.insert_resource(Events::<DamageEvent>::default());
+        world.insert_resource(Events::<TankDestroyedEvent>::default());
+        let stage = SystemStage::parallel()
+            .with_system(apply_damage.system().label("damage"))
+            .with_system(destroy_tanks.system().after("damage"));
+        (world, stage)
+    }
+
+    fn hit(world: &mut World, target: Entity, amount: f32, instigator: Option<Entity>) {
+        world
+            .get_resource_mut::<Events<DamageEvent>>()
+            .unwrap()
+            .send(DamageEvent {
+                target,
+                amount,
+                kind: DamageType::Shell,
+                instigator,
+            });
+    }
+
+    #[test]
+    fn armor_and_multipliers_reduce_damage() {
+        let rules = DamageRules {
+            shell_multiplier: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(rules.damage(DamageType::Shell, 10.0, 0.5), 10.0);
+        assert_eq!(rules.damage(DamageType::Collision, 10.0, 0.0), 10.0);
+        // armor past 1 can't heal
+        assert_eq!(rules.damage(DamageType::Mine, 10.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn hits_leave_tanks_briefly_invulnerable() {
+        let (mut world, mut stage) = setup();
+        let target = world
+            .spawn()
+            .insert_bundle((Health::new(100.0, 0.0), tank(0)))
+            .id();
+
+        hit(&mut world, target, 10.0, None);
+        stage.run(&mut world);
+        assert_eq!(world.get::<Health>(target).unwrap().current, 90.0);
+        assert!(world.get::<Invulnerable>(target).is_some());
+
+        // shrugged off until the timer runs out
+        hit(&mut world, target, 10.0, None);
+        stage.run(&mut world);
+        assert_eq!(world.get::<Health>(target).unwrap().current, 90.0);
+
+        world.entity_mut(target).remove::<Invulnerable>();
+        hit(&mut world, target, 10.0, None);
+        stage.run(&mut world);
+        assert_eq!(world.get::<Health>(target).unwrap().current, 80.0);
+    }
+
+    #[test]
+    fn destroyed_tanks_lose_a_life_and_credit_the_killer() {
+        let (mut world, mut stage) = setup();
+        let target = world
+            .spawn()
+            .insert_bundle((Health::new(50.0, 0.0), tank(0)))
+            .id();
+        let killer = world
+            .spawn()
+            .insert_bundle((Health::new(50.0, 0.0), tank(1)))
+            .id();
+
+        hit(&mut world, target, 60.0, Some(killer));
+        stage.run(&mut world);
+
+        assert!(world.get_entity(target).is_none());
+        let rules = DamageRules::default();
+        let scoreboard = world.get_resource::<Scoreboard>().unwrap();
+        let record = &scoreboard.players[&PlayerId(0)];
+        assert_eq!(record.lives, rules.lives - 1);
+        assert_eq!(record.deaths, 1);
+        assert_eq!(scoreboard.players[&PlayerId(1)].score, rules.kill_score);
+        let respawns = world.get_resource::<Respawns>().unwrap();
+        assert_eq!(respawns.pending().count(), 1);
+    }
+
+    #[test]
+    fn collisions_only_count_when_contact_starts() {
+        let (a, b, c) = (Entity::new(0), Entity::new(1), Entity::new(2));
+        let mut touching = HashSet::new();
+        let mut tanks = vec![
+            (a, Vec3::ZERO),
+            (b, Vec3::new(5.0, 0.0, 0.0)),
+            (c, Vec3::new(100.0, 0.0, 0.0)),
+        ];
+
+        assert_eq!(new_contacts(&tanks, 10.0, &mut touching), vec![(a, b)]);
+        // still touching, no more damage
+        assert!(new_contacts(&tanks, 10.0, &mut touching).is_empty());
+
+        // back off and bump again
+        tanks[1].1 = Vec3::new(50.0, 0.0, 0.0);
+        assert!(new_contacts(&tanks, 10.0, &mut touching).is_empty());
+        tanks[1].1 = Vec3::new(95.0, 0.0, 0.0);
+        assert_eq!(new_contacts(&tanks, 10.0, &mut touching), vec![(b, c)]);
+        tanks[1].1 = Vec3::new(5.0, 0.0, 0.0);
+        assert_eq!(new_contacts(&tanks, 10.0, &mut touching), vec![(a, b)]);
+    }
+}
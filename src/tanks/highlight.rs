@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+
+use crate::{
+    helpers::cleanup_system,
+    tween::{ColorLens, Ease, ScaleLens, TranslationLens, Tween, TweenCompleted, TweenMode},
+    GameStages, GameState,
+};
+
+use super::{
+    map::{build_cell_mesh, CellPicking, MapData, MapLayout},
+    minesweeper::RevealEvent,
+    streaming::LoadedChunks,
+};
+
+/// Markers floating over the hovered and selected cells, and cover tiles that flip away as
+/// cells are uncovered, all animated with tweens
+pub struct HighlightPlugin {
+    state: GameState,
+}
+
+impl HighlightPlugin {
+    pub fn new(state: GameState) -> Self {
+        HighlightPlugin { state: state }
+    }
+}
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(self.state.clone())
+                .with_system(spawn_markers.system().after("map_startup")),
+        )
+        .add_system_set(
+            SystemSet::on_update(self.state.clone())
+                .with_system(move_markers.system().after("pick_cells"))
+                // tweens run on real time, so flips finish while paused and have to be
+                // cleaned up then too
+                .with_system(despawn_flips.system()),
+        )
+        .add_system_set_to_stage(
+            GameStages::Game,
+            SystemSet::on_update(self.state.clone())
+                .with_system(flip_revealed.system().after("reveal")),
+        )
+        .add_system_set(
+            SystemSet::on_exit(self.state.clone())
+                .with_system(cleanup_system::<CellEffect>.system()),
+        );
+    }
+}
+
+/// Height above the map, so markers don't fight with the ground or each other
+const FLIP_LIFT: f32 = 1.0;
+const HOVER_LIFT: f32 = 2.0;
+const SELECTION_LIFT: f32 = 3.0;
+
+/// Big flood fills only flip the first few hundred cells
+const MAX_FLIPS: usize = 300;
+
+/// Seconds between each step out from the tank starting to flip, so it ripples
+const FLIP_STAGGER: f32 = 0.04;
+
+const FLIP_TAG: u32 = 1;
+
+/// Everything spawned here, cleaned up when the game ends
+struct CellEffect;
+
+/// Cell the marker is over, None while it is hidden
+struct HoverMarker(Option<(u32, u32)>);
+
+struct SelectionMarker(Option<(u32, u32)>);
+
+struct RevealFlip;
+
+/// Shared by every flip tile
+struct FlipAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn lifted(layout: &MapLayout, cell: (u32, u32), lift: f32) -> Vec3 {
+    layout.cell_to_world(cell.0, cell.1) + Vec3::Y * lift
+}
+
+/// Markers start shrunk to nothing, the mesh follows the map's cell shape
+fn spawn_markers(
+    mut commands: Commands,
+    data: Res<MapData>,
+    layout: Res<MapLayout>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(build_cell_mesh(&layout));
+    let material = |materials: &mut Assets<StandardMaterial>, color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            unlit: true,
+            ..Default::default()
+        })
+    };
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: mesh.clone(),
+            material: material(&mut materials, data.hover),
+            transform: Transform::from_scale(Vec3::ZERO),
+            ..Default::default()
+        })
+        .insert(HoverMarker(None))
+        .insert(CellEffect)
+        .insert(Name::new("Hover Marker"));
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: mesh.clone(),
+            material: material(&mut materials, data.selected),
+            transform: Transform::from_scale(Vec3::ZERO),
+            ..Default::default()
+        })
+        .insert(SelectionMarker(None))
+        .insert(CellEffect)
+        .insert(Name::new("Selection Marker"))
+        // slow glow so the selection stands out from the hover
+        .insert(
+            Tween::new(
+                ColorLens {
+                    from: data.selected,
+                    to: data.hover,
+                },
+                Ease::SineInOut,
+                0.8,
+            )
+            .with_mode(TweenMode::PingPong),
+        );
+
+    commands.insert_resource(FlipAssets {
+        mesh,
+        material: material(&mut materials, data.cell),
+    });
+}
+
+/// Hover glides between cells and pops in and out, selection pops in where it lands
+fn move_markers(
+    mut commands: Commands,
+    layout: Res<MapLayout>,
+    picking: Res<CellPicking>,
+    mut hover: Query<(Entity, &mut HoverMarker, &mut Transform), Without<SelectionMarker>>,
+    mut selection: Query<(Entity, &mut SelectionMarker, &mut Transform), Without<HoverMarker>>,
+) {
+    if !picking.is_changed() {
+        return;
+    }
+
+    for (entity, mut marker, mut transform) in hover.iter_mut() {
+        if marker.0 == picking.hovered {
+            continue;
+        }
+        let mut entity = commands.entity(entity);
+        match (marker.0, picking.hovered) {
+            (Some(_), Some(cell)) => {
+                entity.insert(Tween::new(
+                    TranslationLens {
+                        from: transform.translation,
+                        to: lifted(&layout, cell, HOVER_LIFT),
+                    },
+                    Ease::QuadOut,
+                    0.08,
+                ));
+            }
+            (None, Some(cell)) => {
+                transform.translation = lifted(&layout, cell, HOVER_LIFT);
+                entity.remove::<Tween<TranslationLens>>().insert(Tween::new(
+                    ScaleLens {
+                        from: transform.scale,
+                        to: Vec3::ONE,
+                    },
+                    Ease::BackOut,
+                    0.15,
+                ));
+            }
+            (_, None) => {
+                entity.insert(Tween::new(
+                    ScaleLens {
+                        from: transform.scale,
+                        to: Vec3::ZERO,
+                    },
+                    Ease::QuadIn,
+                    0.1,
+                ));
+            }
+        }
+        marker.0 = picking.hovered;
+    }
+
+    for (entity, mut marker, mut transform) in selection.iter_mut() {
+        if marker.0 == picking.selected {
+            continue;
+        }
+        let to = match picking.selected {
+            Some(cell) => {
+                transform.translation = lifted(&layout, cell, SELECTION_LIFT);
+                transform.scale = Vec3::splat(0.6);
+                Vec3::ONE
+            }
+            None => Vec3::ZERO,
+        };
+        commands.entity(entity).insert(Tween::new(
+            ScaleLens {
+                from: transform.scale,
+                to,
+            },
+            Ease::BackOut,
+            0.25,
+        ));
+        marker.0 = picking.selected;
+    }
+}
+
+/// Cover each uncovered cell in a loaded chunk with a tile that shrinks away, rippling out
+/// from where the tank drove on
+fn flip_revealed(
+    mut commands: Commands,
+    layout: Res<MapLayout>,
+    loaded: Res<LoadedChunks>,
+    assets: Option<Res<FlipAssets>>,
+    mut ev_reveal: EventReader<RevealEvent>,
+) {
+    let assets = match assets {
+        Some(assets) => assets,
+        None => return,
+    };
+
+    for event in ev_reveal.iter() {
+        for cell in event.cells.iter().take(MAX_FLIPS) {
+            if !loaded.chunks.contains_key(&layout.chunk_of(cell.0, cell.1)) {
+                continue;
+            }
+            let delay = layout.distance(event.origin, *cell) as f32 * FLIP_STAGGER;
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: assets.material.clone(),
+                    transform: Transform::from_translation(lifted(&layout, *cell, FLIP_LIFT)),
+                    ..Default::default()
+                })
+                .insert(RevealFlip)
+                .insert(CellEffect)
+                .insert(
+                    Tween::new(
+                        ScaleLens {
+                            from: Vec3::ONE,
+                            to: Vec3::ZERO,
+                        },
+                        Ease::QuadIn,
+                        0.2,
+                    )
+                    .with_delay(delay)
+                    .with_tag(FLIP_TAG),
+                );
+        }
+    }
+}
+
+fn despawn_flips(
+    mut commands: Commands,
+    flips: Query<(), With<RevealFlip>>,
+    mut ev_completed: EventReader<TweenCompleted>,
+) {
+    for event in ev_completed.iter() {
+        if event.tag == FLIP_TAG && flips.get(event.entity).is_ok() {
+            commands.entity(event.entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_egui::{
+    egui::{
+        self, Align2, Area, Color32, Id, LayerId, Order, Pos2, Rect, Vec2 as EguiVec2, Window,
+    },
+    EguiContext, EguiSettings,
+};
+
+use crate::GameState;
+
+use super::{
+    health::{Health, Invulnerable, Scoreboard},
+    split_screen::{PlayerViewport, PlayerViewports},
+    tank::{Tank, Team},
+};
+
+pub struct HudPlugin {
+    state: GameState,
+}
+
+impl HudPlugin {
+    pub fn new(state: GameState) -> Self {
+        HudPlugin { state: state }
+    }
+}
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(self.state.clone())
+                .with_system(draw_health_bars.system())
+                .with_system(draw_player_panels.system())
+                .with_system(draw_score_panel.system()),
+        );
+    }
+}
+
+const BAR_SIZE: (f32, f32) = (60.0, 6.0);
+
+/// Health bar floating above each visible tank, in every player's viewport
+fn draw_health_bars(
+    egui_ctx: Res<EguiContext>,
+    egui_settings: Res<EguiSettings>,
+    windows: Res<Windows>,
+    viewports: Res<PlayerViewports>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tanks: Query<(&GlobalTransform, &Health, &Visible, Option<&Invulnerable>), With<Tank>>,
+) {
+    let window_height = match windows.get_primary() {
+        Some(window) => window.height(),
+        None => return,
+    };
+
+    let painter = egui_ctx
+        .ctx()
+        .layer_painter(LayerId::new(Order::Middle, Id::new("tank_health_bars")));
+    let scale = egui_settings.scale_factor as f32;
+
+    for viewport in viewports.viewports.iter() {
+        let (camera, camera_transform) = match cameras.get(viewport.camera) {
+            Ok(camera) => camera,
+            Err(_) => continue,
+        };
+        let clip = viewport_rect(viewport, window_height, scale);
+        let painter = painter.with_clip_rect(clip);
+
+        for (transform, health, visible, invulnerable) in tanks.iter() {
+            if !visible.is_visible {
+                continue;
+            }
+
+            let position =
+                match viewport.world_to_window(camera, camera_transform, transform.translation) {
+                    Some(position) => position,
+                    None => continue,
+                };
+
+            // bevy screen space starts bottom left, egui top left
+            let center = Pos2::new(position.x / scale, (window_height - position.y) / scale - 30.0);
+            let size = EguiVec2::new(BAR_SIZE.0, BAR_SIZE.1);
+            let background = Rect::from_center_size(center, size);
+            let mut fill = background;
+            fill.max.x = fill.min.x + size.x * health.fraction();
+
+            let color = if invulnerable.is_some() {
+                Color32::from_rgb(220, 220, 220)
+            } else if health.fraction() > 0.5 {
+                Color32::from_rgb(40, 200, 40)
+            } else if health.fraction() > 0.25 {
+                Color32::from_rgb(220, 180, 30)
+            } else {
+                Color32::from_rgb(220, 40, 40)
+            };
+
+            painter.rect_filled(background, 1.0, Color32::from_black_alpha(180));
+            painter.rect_filled(fill, 1.0, color);
+        }
+    }
+}
+
+/// Egui rect covering a viewport
+pub fn viewport_rect(viewport: &PlayerViewport, window_height: f32, scale: f32) -> Rect {
+    let min = Pos2::new(
+        viewport.min.x / scale,
+        (window_height - viewport.min.y - viewport.size.y) / scale,
+    );
+    Rect::from_min_size(min, EguiVec2::new(viewport.size.x / scale, viewport.size.y / scale))
+}
+
+/// Each player's lives and score in the corner of their own viewport
+fn draw_player_panels(
+    egui_ctx: Res<EguiContext>,
+    egui_settings: Res<EguiSettings>,
+    windows: Res<Windows>,
+    viewports: Res<PlayerViewports>,
+    scoreboard: Res<Scoreboard>,
+) {
+    let window_height = match windows.get_primary() {
+        Some(window) => window.height(),
+        None => return,
+    };
+    let scale = egui_settings.scale_factor as f32;
+
+    for viewport in viewports.viewports.iter() {
+        let player = match viewport.player {
+            Some(player) => player,
+            None => continue,
+        };
+        let record = match scoreboard.players.get(&player) {
+            Some(record) => record,
+            None => continue,
+        };
+
+        let rect = viewport_rect(viewport, window_height, scale);
+        Area::new(format!("tanks_player_panel_{}", player.0))
+            .fixed_pos(rect.min + EguiVec2::new(10.0, 10.0))
+            .show(egui_ctx.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!("Player {}", player.0 + 1));
+                    ui.label(format!("Lives: {}", record.lives));
+                    ui.label(format!("Score: {}", record.score));
+                });
+            });
+    }
+}
+
+fn draw_score_panel(egui_ctx: Res<EguiContext>, scoreboard: Res<Scoreboard>) {
+    Window::new("Tanks")
+        .anchor(Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_ctx.ctx(), |ui| {
+            egui::Grid::new("tanks_score").striped(true).show(ui, |ui| {
+                ui.label("Player");
+                ui.label("Lives");
+                ui.label("Score");
+                ui.end_row();
+
+                for (player, record) in scoreboard.players.iter() {
+                    let name = match record.team {
+                        Some(Team::Enemy) => format!("Enemy {}", player.0),
+                        _ => format!("Player {}", player.0 + 1),
+                    };
+                    ui.label(name);
+                    ui.label(format!("{}", record.lives));
+                    ui.label(format!("{}", record.score));
+                    ui.end_row();
+                }
+            });
+        });
+}
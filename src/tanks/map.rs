@@ -1,53 +1,374 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        pipeline::PrimitiveTopology,
+        texture::{Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat},
+    },
+};
+use bevy_egui::EguiContext;
 use bevy_inspector_egui::{InspectorPlugin, Inspectable};
-use bevy_mod_picking::{MeshButtonMaterials, PickableBundle, PickingEvent};
-use rand::Rng;
+use bevy_mod_picking::{MeshButtonMaterials, PickingEvent};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{helpers::cleanup_system, GameState};
+use crate::{editor::RegisterSceneComponent, helpers::grid::GridSpace, GameState};
+
+use super::{
+    minesweeper::{generate, MinesweeperSettings},
+    records::DailyChallenge,
+    split_screen::ViewportCursor,
+    streaming::{CellStore, MapStreaming, Terrain},
+    topology::{find_path, GridShape, GridTopology},
+    visibility::{CellVisibility, FogOfWar},
+};
 
 pub struct MapPlugin {
     state: GameState,
 }
 
+/// Colors for each kind of cell, the whole map is drawn with one material that looks these up
+/// in a small palette texture, so changing a cell is just changing its uvs
 #[derive(Inspectable, Debug)]
-struct MapData {
-    pub cell: Handle<StandardMaterial>,
-    pub hover: Handle<StandardMaterial>,
-    pub selected: Handle<StandardMaterial>,
+pub struct MapData {
+    pub cell: Color,
+    /// Cells a friendly tank has uncovered, these show their mine count
+    pub revealed: Color,
+    pub hover: Color,
+    /// Safe cell suggested by the hint button
+    pub hint: Color,
+    pub selected: Color,
+    pub obstacle: Color,
+    pub rubble: Color,
+    pub explored: Color,
+    pub unexplored: Color,
+    /// Shared by every chunk
+    pub terrain: Handle<StandardMaterial>,
+    pub palette: Handle<Texture>,
+    /// Used by mouse picking for tanks
+    pub hover_material: Handle<StandardMaterial>,
+    pub selected_material: Handle<StandardMaterial>,
 }
 
 impl FromWorld for MapData {
     fn from_world(world: &mut World) -> Self {
-
+        let world = world.cell();
         let mut materials = world
             .get_resource_mut::<Assets<StandardMaterial>>()
             .expect("ResMut<Assets<StandardMaterial>> not found.");
+        let mut textures = world
+            .get_resource_mut::<Assets<Texture>>()
+            .expect("ResMut<Assets<Texture>> not found.");
+
+        let hover = Color::rgb(0.0, 0.7, 0.0);
+        let selected = Color::rgb(0.0, 0.5, 0.0);
+
+        let mut palette = Texture::new_fill(
+            Extent3d::new(CellTile::COUNT, 1, 1),
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        // no blending between neighbouring palette entries
+        palette.sampler = SamplerDescriptor {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        };
+        let palette = textures.add(palette);
 
-        let size = 10000;
-        MapData {
-            cell: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.7, 0.7, 0.7),
-                // vary key PBR parameters on a grid of spheres to show the effect
+        let mut data = MapData {
+            cell: Color::rgb(0.7, 0.7, 0.7),
+            revealed: Color::rgb(0.85, 0.85, 0.8),
+            hover,
+            hint: Color::rgb(0.9, 0.8, 0.1),
+            selected,
+            obstacle: Color::rgb(0.45, 0.3, 0.15),
+            rubble: Color::rgb(0.35, 0.3, 0.25),
+            // remembered cells are drawn darker than what we can currently see
+            explored: Color::rgb(0.3, 0.3, 0.3),
+            unexplored: Color::rgb(0.05, 0.05, 0.05),
+            terrain: materials.add(StandardMaterial {
+                base_color_texture: Some(palette.clone()),
                 unlit: true,
                 ..Default::default()
             }),
-            hover: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.0, 0.7, 0.0),
-                // vary key PBR parameters on a grid of spheres to show the effect
+            palette,
+            hover_material: materials.add(StandardMaterial {
+                base_color: hover,
                 unlit: true,
                 ..Default::default()
             }),
-            selected: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.0, 0.7, 0.0),
-                // vary key PBR parameters on a grid of spheres to show the effect
+            selected_material: materials.add(StandardMaterial {
+                base_color: selected,
                 unlit: true,
                 ..Default::default()
-            })
+            }),
+        };
+        if let Some(texture) = textures.get_mut(&data.palette) {
+            data.write_palette(texture);
+        }
+        data
+    }
+}
+
+impl MapData {
+    pub fn tile_color(&self, tile: CellTile) -> Color {
+        match tile {
+            CellTile::Unexplored => self.unexplored,
+            CellTile::Explored => self.explored,
+            CellTile::Cell => self.cell,
+            CellTile::Revealed => self.revealed,
+            CellTile::Obstacle => self.obstacle,
+            CellTile::Rubble => self.rubble,
+            CellTile::Hint => self.hint,
+        }
+    }
+
+    fn write_palette(&self, texture: &mut Texture) {
+        for tile in CellTile::ALL.iter() {
+            let color = self.tile_color(*tile);
+            let i = tile.index() * 4;
+            texture.data[i..i + 4].copy_from_slice(&[
+                (color.r() * 255.0) as u8,
+                (color.g() * 255.0) as u8,
+                (color.b() * 255.0) as u8,
+                255,
+            ]);
+        }
+    }
+}
+
+/// How a cell is drawn, each one is an entry in the palette texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellTile {
+    Unexplored,
+    Explored,
+    Cell,
+    Revealed,
+    Obstacle,
+    Rubble,
+    Hint,
+}
+
+impl CellTile {
+    pub const COUNT: u32 = 7;
+    pub const ALL: [CellTile; 7] = [
+        CellTile::Unexplored,
+        CellTile::Explored,
+        CellTile::Cell,
+        CellTile::Revealed,
+        CellTile::Obstacle,
+        CellTile::Rubble,
+        CellTile::Hint,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Center of this tile's texel in the palette
+    fn uv(&self) -> [f32; 2] {
+        [(self.index() as f32 + 0.5) / Self::COUNT as f32, 0.5]
+    }
+}
+
+/// Size of the map grid and how big each cell is in world units, the map is centered on the origin
+#[derive(Debug, Clone, Copy)]
+pub struct MapLayout {
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: f32,
+    /// Cells along each side of a terrain chunk
+    pub chunk_size: u32,
+    pub shape: GridShape,
+}
+
+impl Default for MapLayout {
+    fn default() -> Self {
+        MapLayout {
+            width: 10,
+            height: 10,
+            cell_size: 256.0,
+            chunk_size: 16,
+            shape: GridShape::Square,
+        }
+    }
+}
+
+impl MapLayout {
+    pub fn topology(&self) -> &'static dyn GridTopology {
+        self.shape.topology()
+    }
+
+    /// Size of the whole map in world units, x and z
+    pub fn size(&self) -> Vec2 {
+        self.topology().extent(self.width, self.height, self.cell_size)
+    }
+
+    /// Space one cell takes up in world units, x and z
+    pub fn footprint(&self) -> Vec2 {
+        self.topology().footprint(self.cell_size)
+    }
+
+    /// World position of the bottom left corner of the map
+    pub fn origin(&self) -> Vec3 {
+        let size = self.size();
+        Vec3::new(-size.x * 0.5, 0.0, -size.y * 0.5)
+    }
+
+    /// World position of the center of a cell
+    pub fn cell_to_world(&self, x: u32, y: u32) -> Vec3 {
+        let center = self.topology().cell_center(x, y, self.cell_size);
+        self.origin() + Vec3::new(center.x, 0.0, center.y)
+    }
+
+    /// Cell coordinates under a world position, carrying on past the edges of the map
+    pub fn cell_coords(&self, position: Vec3) -> (i64, i64) {
+        let local = position - self.origin();
+        self.topology().cell_at(Vec2::new(local.x, local.z), self.cell_size)
+    }
+
+    /// Cell under a world position, None if its off the map
+    pub fn world_to_cell(&self, position: Vec3) -> Option<(u32, u32)> {
+        let (x, y) = self.cell_coords(position);
+        self.bounds().checked(x, y)
+    }
+
+    /// Which cells are on the map, positions have to go through the topology though since
+    /// hex rows don't line up
+    pub fn bounds(&self) -> GridSpace {
+        GridSpace::new(self.width, self.height, self.cell_size)
+    }
+
+    /// Cells whose centers are within radius of a world position
+    pub fn cells_within(&self, position: Vec3, radius: f32) -> Vec<(u32, u32)> {
+        let footprint = self.footprint();
+        let reach_x = (radius / footprint.x).ceil() as i64 + 1;
+        let reach_y = (radius / footprint.y).ceil() as i64 + 1;
+        let (cx, cy) = self.cell_coords(position);
+
+        self.bounds()
+            .rect((cx - reach_x, cy - reach_y), (cx + reach_x, cy + reach_y))
+            .filter(|(x, y)| self.cell_to_world(*x, *y).distance(position) <= radius)
+            .collect()
+    }
+
+    /// Cells touching a cell
+    pub fn neighbours(&self, x: u32, y: u32) -> Vec<(u32, u32)> {
+        self.topology().neighbours(x, y, self.width, self.height)
+    }
+
+    /// Fewest steps between two cells
+    pub fn distance(&self, a: (u32, u32), b: (u32, u32)) -> u32 {
+        self.topology().distance(a, b)
+    }
+
+    /// Shortest route between two cells that avoids blocked ones, both ends included
+    pub fn find_path(
+        &self,
+        from: (u32, u32),
+        to: (u32, u32),
+        blocked: impl Fn(u32, u32) -> bool,
+    ) -> Option<Vec<(u32, u32)>> {
+        find_path(self.topology(), self.width, self.height, from, to, blocked)
+    }
+
+    /// Number of chunks across and down
+    pub fn chunks(&self) -> (u32, u32) {
+        let size = self.chunk_size.max(1);
+        (
+            (self.width + size - 1) / size,
+            (self.height + size - 1) / size,
+        )
+    }
+
+    /// Chunk a cell belongs to
+    pub fn chunk_of(&self, x: u32, y: u32) -> (u32, u32) {
+        let size = self.chunk_size.max(1);
+        (x / size, y / size)
+    }
+
+    /// Cells covered by a chunk, as min and max exclusive
+    pub fn chunk_cells(&self, chunk: (u32, u32)) -> ((u32, u32), (u32, u32)) {
+        let size = self.chunk_size.max(1);
+        let min = (chunk.0 * size, chunk.1 * size);
+        let max = (
+            (min.0 + size).min(self.width),
+            (min.1 + size).min(self.height),
+        );
+        (min, max)
+    }
+}
+
+/// Cell entity for each map coordinate, so systems can go straight to a cell instead of
+/// searching every cell, only cells in loaded chunks have one
+#[derive(Debug, Default)]
+pub struct MapCells {
+    width: u32,
+    cells: Vec<Option<Entity>>,
+}
+
+impl MapCells {
+    pub fn new(width: u32, height: u32) -> Self {
+        MapCells {
+            width,
+            cells: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<Entity> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells
+            .get((y * self.width + x) as usize)
+            .copied()
+            .flatten()
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, entity: Entity) {
+        if x < self.width {
+            if let Some(cell) = self.cells.get_mut((y * self.width + x) as usize) {
+                *cell = Some(entity);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, x: u32, y: u32) {
+        if x < self.width {
+            if let Some(cell) = self.cells.get_mut((y * self.width + x) as usize) {
+                *cell = None;
+            }
         }
     }
 }
 
-struct MapCleanup;
+/// Seed the map was generated from, set `next` to play a particular map
+#[derive(Debug, Default)]
+pub struct MapSeed {
+    pub next: Option<u64>,
+    pub current: u64,
+}
+
+/// Cell under the cursor and the last one clicked, only explored cells can be picked
+#[derive(Debug, Default, PartialEq)]
+pub struct CellPicking {
+    pub hovered: Option<(u32, u32)>,
+    pub selected: Option<(u32, u32)>,
+    /// Highlighted until it gets revealed
+    pub hint: Option<(u32, u32)>,
+}
+
+/// One batched mesh covering a square of cells
+#[derive(Debug)]
+pub struct MapChunk {
+    pub x: u32,
+    pub y: u32,
+    /// What each cell is currently drawn as, row by row, so we only touch the mesh when needed
+    tiles: Vec<CellTile>,
+}
 
 impl MapPlugin {
     pub fn new(state: GameState) -> Self {
@@ -58,90 +379,245 @@ impl MapPlugin {
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
+        .insert_resource(MapLayout::default())
+        .insert_resource(MapCells::default())
+        .insert_resource(MapSeed::default())
+        .insert_resource(CellPicking::default())
+        .register_type::<MineStatus>()
+        .register_scene_component::<MineLocation>()
+        .register_scene_component::<Obstacle>()
+        .register_scene_component::<Destructible>()
+        .register_scene_component::<Rubble>()
+        .register_scene_component::<ObstacleModel>()
         .add_plugin(InspectorPlugin::<MapData>::new().open(false))
-        .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(startup.system()))
-            .add_system_set(
-                SystemSet::on_update(self.state.clone()).with_system(mouse_interactions.system()),
-            )
+        .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(startup.system().label("map_startup")))
             .add_system_set(
-                SystemSet::on_enter(self.state.clone())
-                    .with_system(cleanup_system::<MapCleanup>.system()),
+                SystemSet::on_update(self.state.clone())
+                    .with_system(update_palette.system())
+                    .with_system(
+                        pick_cells
+                            .system()
+                            .label("pick_cells")
+                            .after("viewport_cursor")
+                            .after("fog"),
+                    )
+                    .with_system(paint_chunks.system().after("fog")),
             )
             .add_system_to_stage(CoreStage::PostUpdate, print_events.system());
     }
 }
 
-pub fn mouse_interactions(mut query: Query<(&mut Interaction, &mut Transform, &mut MineLocation), Changed<Interaction>>) {
-    for (i, mut t, m) in query.iter_mut() {
+pub fn print_events(mut events: EventReader<PickingEvent>, ) {
+    for event in events.iter() {
+        println!("This event happened! {:?}", event);
+    }
+}
 
-        if *i == Interaction::Clicked {
+/// Generate the map into the store, chunks are spawned from it as they come into range
+fn startup(
+    data: Res<MapData>,
+    streaming: Res<MapStreaming>,
+    settings: Res<MinesweeperSettings>,
+    daily: Res<DailyChallenge>,
+    mut layout: ResMut<MapLayout>,
+    mut seed: ResMut<MapSeed>,
+    mut store: ResMut<CellStore>,
+    mut cells: ResMut<MapCells>,
+    mut picking: ResMut<CellPicking>,
+    mut highlight_colors: ResMut<MeshButtonMaterials>,
+) {
+    seed.current = match daily.date {
+        Some(date) => date.seed(),
+        None => seed.next.take().unwrap_or_else(rand::random),
+    };
+    // StdRng may change between rand releases, which would change every seeded board
+    let mut rnd = ChaCha8Rng::seed_from_u64(seed.current);
+    highlight_colors.hovered = data.hover_material.clone();
+    highlight_colors.selected = data.selected_material.clone();
+    let (board, no_guess) = daily.board(&settings);
+    layout.width = board.width;
+    layout.height = board.height;
+    layout.shape = board.shape;
+    layout.chunk_size = streaming.chunk_size;
+    *store = CellStore::new(layout.width, layout.height);
+    *cells = MapCells::new(layout.width, layout.height);
+    *picking = CellPicking::default();
 
-        }
+    generate(&mut store, board, no_guess, &mut rnd);
+}
 
-        if *i == Interaction::Hovered {
-            let pos = t.up() * 50.0;
-            t.translation += pos;
-        }
+/// Flat polygon for each cell in a chunk, in world space, all starting unexplored
+pub fn build_chunk_mesh(layout: &MapLayout, chunk: (u32, u32)) -> (Mesh, MapChunk) {
+    let (min, max) = layout.chunk_cells(chunk);
+    // leave a small gap so the grid is still readable
+    let corners = layout.topology().corners(layout.cell_size * 0.99);
+    let sides = corners.len() as u32;
 
-        if *i == Interaction::None {
-            t.translation = Vec3::new(t.translation.x, 0.0, t.translation.z);
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    for y in min.1..max.1 {
+        for x in min.0..max.0 {
+            let center = layout.cell_to_world(x, y);
+            let start = positions.len() as u32;
+            for corner in corners.iter() {
+                positions.push([center.x + corner.x, 0.0, center.z + corner.y]);
+                normals.push([0.0, 1.0, 0.0]);
+                uvs.push(CellTile::Unexplored.uv());
+            }
+            // fan out from the first corner
+            for i in 1..sides - 1 {
+                indices.extend_from_slice(&[start, start + i, start + i + 1]);
+            }
         }
     }
+
+    let count = positions.len() / sides as usize;
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    (
+        mesh,
+        MapChunk {
+            x: chunk.0,
+            y: chunk.1,
+            tiles: vec![CellTile::Unexplored; count],
+        },
+    )
 }
 
-pub fn print_events(mut events: EventReader<PickingEvent>, ) {
-    for event in events.iter() {
-        println!("This event happened! {:?}", event);
+/// One cell centered on the origin, for things drawn over the map
+pub fn build_cell_mesh(layout: &MapLayout) -> Mesh {
+    let corners = layout.topology().corners(layout.cell_size);
+    let positions: Vec<[f32; 3]> = corners.iter().map(|c| [c.x, 0.0, c.y]).collect();
+    let normals = vec![[0.0, 1.0, 0.0]; corners.len()];
+    let uvs = vec![[0.5, 0.5]; corners.len()];
+    let indices = (1..corners.len() as u32 - 1)
+        .flat_map(|i| vec![0, i, i + 1])
+        .collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Keep the palette texture in step with the colors, so they can be tweaked in the inspector
+fn update_palette(data: Res<MapData>, mut textures: ResMut<Assets<Texture>>) {
+    if !data.is_changed() {
+        return;
+    }
+    if let Some(texture) = textures.get_mut(&data.palette) {
+        data.write_palette(texture);
     }
 }
 
-fn startup(
-    mut commands: Commands,
+/// Resolve the cursor to a cell, works out the cell from where the cursor ray hits the ground
+/// rather than picking cell meshes, since there aren't any
+fn pick_cells(
+    egui_ctx: Res<EguiContext>,
+    cursor: Res<ViewportCursor>,
+    mouse: Res<Input<MouseButton>>,
+    fog: Res<FogOfWar>,
+    mut picking: ResMut<CellPicking>,
+) {
+    let hovered = if egui_ctx.ctx().wants_pointer_input() {
+        None
+    } else {
+        cursor.cell.filter(|(x, y)| fog.is_explored(*x, *y))
+    };
+
+    let mut selected = picking.selected;
+    if hovered.is_some() && mouse.just_pressed(MouseButton::Left) {
+        selected = hovered;
+    }
+
+    // only touch the resource when something changed, painting keys off that
+    let next = CellPicking {
+        hovered,
+        selected,
+        hint: picking.hint,
+    };
+    if *picking != next {
+        *picking = next;
+    }
+}
+
+/// Work out what each cell should look like and update the chunk meshes that changed
+fn paint_chunks(
+    data: Res<MapData>,
+    fog: Res<FogOfWar>,
+    picking: Res<CellPicking>,
+    layout: Res<MapLayout>,
+    store: Res<CellStore>,
     mut meshes: ResMut<Assets<Mesh>>,
-    data: ResMut<MapData>,
-    mut highlight_colors: ResMut<MeshButtonMaterials>,
+    mut chunks: Query<(&mut MapChunk, &Handle<Mesh>)>,
+    added: Query<(), Added<MapChunk>>,
 ) {
-    let mut rnd = rand::thread_rng();
-    highlight_colors.hovered = data.hover.clone();
-    highlight_colors.selected = data.selected.clone();
-
-    // spawn floor
-    let rows = 10u32;
-    let columns = 10u32;
-    let size = 256.0;
-    let origin_bottom_offset = -size * 0.5 * rows as f32;
-    let origin_left_offset = -size * 0.5 * columns as f32;
-    for x in 0..rows {
-        for z in 0..columns {
-            commands
-                .spawn_bundle(PbrBundle {
-                    mesh: meshes.add(Mesh::from(shape::Plane { size: size })),
-                    material: data.cell.clone(),
-                    transform: Transform {
-                        translation: Vec3::new(
-                            origin_left_offset + (size * x as f32) + size * 0.5,
-                            0.0,
-                            origin_bottom_offset + size * z as f32 + size * 0.5,
-                        ),
-                        scale: Vec3::splat(0.99),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(MineLocation {
-                    x: x,
-                    y: z,
-                    status: MineStatus::Unknown {
-                        mine: rnd.gen_bool(0.10),
-                    },
-                })
-                .insert_bundle(PickableBundle::default())
-                .insert(MapCleanup);
+    if !fog.is_changed()
+        && !picking.is_changed()
+        && !store.is_changed()
+        && added.iter().next().is_none()
+    {
+        return;
+    }
+
+    for (mut chunk, mesh) in chunks.iter_mut() {
+        let (min, max) = layout.chunk_cells((chunk.x, chunk.y));
+        let mut tiles = Vec::with_capacity(chunk.tiles.len());
+        for y in min.1..max.1 {
+            for x in min.0..max.0 {
+                // hover and selection are markers floating over the map, see highlight.rs
+                let tile = if picking.hint == Some((x, y)) {
+                    CellTile::Hint
+                } else {
+                    match fog.get(x, y) {
+                        CellVisibility::Unexplored => CellTile::Unexplored,
+                        CellVisibility::Explored => CellTile::Explored,
+                        CellVisibility::Visible => match store.get(x, y) {
+                            Some(cell) => match (cell.terrain(), cell.status()) {
+                                (Terrain::Obstacle(_), _) => CellTile::Obstacle,
+                                (_, MineStatus::Count { .. }) => CellTile::Revealed,
+                                (Terrain::Rubble, _) => CellTile::Rubble,
+                                _ => CellTile::Cell,
+                            },
+                            None => CellTile::Cell,
+                        },
+                    }
+                };
+                tiles.push(tile);
+            }
+        }
+
+        if tiles == chunk.tiles {
+            continue;
+        }
+
+        let sides = layout.topology().corners(layout.cell_size).len();
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            if let Some(VertexAttributeValues::Float2(uvs)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+            {
+                for (i, tile) in tiles.iter().enumerate() {
+                    let uv = tile.uv();
+                    for vertex in uvs[i * sides..(i + 1) * sides].iter_mut() {
+                        *vertex = uv;
+                    }
+                }
+            }
         }
+        chunk.tiles = tiles;
     }
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
 pub enum MineStatus {
     Unknown { mine: bool },
     Count { count: u32 },
@@ -149,9 +625,38 @@ pub enum MineStatus {
     Exploded,
 }
 
-#[allow(dead_code)]
+impl Default for MineStatus {
+    fn default() -> Self {
+        MineStatus::Unknown { mine: false }
+    }
+}
+
+/// Cell in column and row, which the map layout's topology turns into neighbours and positions,
+/// so this is the same for square and hex maps
+#[derive(Debug, Default, Reflect)]
+#[reflect(Component)]
 pub struct MineLocation {
-    status: MineStatus,
-    x: u32,
-    y: u32,
+    pub status: MineStatus,
+    pub x: u32,
+    pub y: u32,
 }
+
+/// Marker for cells that block movement and line of sight
+#[derive(Default, Reflect)]
+#[reflect(Component)]
+pub struct Obstacle;
+
+/// Marker for obstacles that explosions can turn into rubble
+#[derive(Default, Reflect)]
+#[reflect(Component)]
+pub struct Destructible;
+
+/// What is left of a destructible cell, no longer blocks anything
+#[derive(Default, Reflect)]
+#[reflect(Component)]
+pub struct Rubble;
+
+/// Child entity holding the kit model for an obstacle
+#[derive(Default, Reflect)]
+#[reflect(Component)]
+pub struct ObstacleModel;
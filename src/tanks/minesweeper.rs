@@ -0,0 +1,949 @@
+use std::fmt;
+
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_egui::{
+    egui::{self, Align2, Color32, Id, LayerId, Order, Pos2, TextStyle, Window},
+    EguiContext, EguiSettings,
+};
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+use rand::{seq::SliceRandom, Rng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{editor::RegisterConsoleCommand, GameStages, GameState};
+
+use super::{
+    hud::viewport_rect,
+    map::{CellPicking, MapCells, MapLayout, MineLocation, MineStatus},
+    records::{DailyChallenge, MinesweeperClock},
+    split_screen::PlayerViewports,
+    streaming::{CellStore, LoadedChunks, ObstacleKind, StoredCell, Terrain},
+    tank::{Tank, Team},
+    topology::GridShape,
+    visibility::FogOfWar,
+    TanksData,
+};
+
+/// Minesweeper rules on the tanks grid, friendly tanks uncover the cells they drive onto
+pub struct MinesweeperPlugin {
+    state: GameState,
+}
+
+impl MinesweeperPlugin {
+    pub fn new(state: GameState) -> Self {
+        MinesweeperPlugin { state: state }
+    }
+}
+
+impl Plugin for MinesweeperPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<MinesweeperSettings>::new().open(false))
+            .register_console_resource::<MinesweeperSettings>("minesweeper")
+            .insert_resource(MinesweeperStatus::default())
+            .add_event::<RevealEvent>()
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone()).with_system(reset_status.system()),
+            )
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(reveal_cells.system().label("reveal"))
+                    .with_system(
+                        count_cells
+                            .system()
+                            .label("minesweeper_count")
+                            .after("reveal"),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(draw_counts.system())
+                    .with_system(draw_minesweeper_window.system()),
+            );
+    }
+}
+
+#[derive(
+    Inspectable, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Beginner => write!(f, "Beginner"),
+            Difficulty::Intermediate => write!(f, "Intermediate"),
+            Difficulty::Expert => write!(f, "Expert"),
+            Difficulty::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Size and shape of a board and how many mines are on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    pub mines: u32,
+    pub shape: GridShape,
+}
+
+/// Takes effect next time the game starts
+#[derive(Inspectable, Debug, Reflect)]
+pub struct MinesweeperSettings {
+    #[reflect(ignore)]
+    pub difficulty: Difficulty,
+    #[inspectable(min = 4, max = 1024)]
+    pub custom_width: u32,
+    #[inspectable(min = 4, max = 1024)]
+    pub custom_height: u32,
+    #[inspectable(min = 1)]
+    pub custom_mines: u32,
+    /// Only hand out boards that can be cleared by logic alone from the starting corner
+    pub no_guess: bool,
+    /// Hex cells have six neighbours, so counts stay lower on the same board
+    #[reflect(ignore)]
+    pub shape: GridShape,
+}
+
+impl Default for MinesweeperSettings {
+    fn default() -> Self {
+        MinesweeperSettings {
+            difficulty: Difficulty::Beginner,
+            custom_width: 20,
+            custom_height: 20,
+            custom_mines: 50,
+            no_guess: false,
+            shape: GridShape::Square,
+        }
+    }
+}
+
+impl MinesweeperSettings {
+    pub fn board(&self) -> Board {
+        match self.difficulty {
+            Difficulty::Beginner => Board {
+                width: 9,
+                height: 9,
+                mines: 10,
+                shape: self.shape,
+            },
+            Difficulty::Intermediate => Board {
+                width: 16,
+                height: 16,
+                mines: 40,
+                shape: self.shape,
+            },
+            Difficulty::Expert => Board {
+                width: 30,
+                height: 16,
+                mines: 99,
+                shape: self.shape,
+            },
+            Difficulty::Custom => Board {
+                width: self.custom_width,
+                height: self.custom_height,
+                mines: self.custom_mines,
+                shape: self.shape,
+            },
+        }
+    }
+}
+
+/// A tank uncovered cells, starting at `origin`
+pub struct RevealEvent {
+    pub origin: (u32, u32),
+    /// In the order the flood fill reached them
+    pub cells: Vec<(u32, u32)>,
+}
+
+/// Counts for the current board, kept up to date as cells change
+#[derive(Debug, Default)]
+pub struct MinesweeperStatus {
+    pub mines: u32,
+    /// Safe cells nobody has uncovered yet, the board is cleared when this hits zero
+    pub hidden: u32,
+    /// Why the last hint request came back empty
+    pub message: Option<String>,
+}
+
+/// Chance of a cell being an obstacle
+const OBSTACLE_CHANCE: f64 = 0.08;
+
+/// Boards to try before giving up on a no guess board
+const NO_GUESS_ATTEMPTS: u32 = 2000;
+
+/// Corners where the tanks start, never mined
+pub fn spawn_area(width: u32, height: u32, x: u32, y: u32) -> bool {
+    (x <= 1 && y <= 1) || (x + 2 >= width && y + 2 >= height)
+}
+
+/// Friendly tanks start here, it counts as the first click
+pub const START: (u32, u32) = (0, 0);
+
+/// Where the mines and obstacles are, before anything is uncovered
+#[derive(Debug, Clone)]
+pub struct Minefield {
+    pub width: u32,
+    pub height: u32,
+    pub shape: GridShape,
+    pub blocked: Vec<bool>,
+    pub mines: Vec<bool>,
+}
+
+impl Minefield {
+    pub fn random(board: Board, rng: &mut ChaCha8Rng) -> Self {
+        let size = (board.width * board.height) as usize;
+        let mut blocked = vec![false; size];
+        let mut open = Vec::with_capacity(size);
+        for y in 0..board.height {
+            for x in 0..board.width {
+                // keep the corners clear, thats where the tanks start
+                if spawn_area(board.width, board.height, x, y) {
+                    continue;
+                }
+                let i = (y * board.width + x) as usize;
+                if rng.gen_bool(OBSTACLE_CHANCE) {
+                    blocked[i] = true;
+                } else {
+                    open.push(i);
+                }
+            }
+        }
+
+        let mut mines = vec![false; size];
+        let count = (board.mines as usize).min(open.len());
+        let (chosen, _) = open.partial_shuffle(rng, count);
+        for i in chosen.iter() {
+            mines[*i] = true;
+        }
+
+        Minefield {
+            width: board.width,
+            height: board.height,
+            shape: board.shape,
+            blocked,
+            mines,
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn neighbours(&self, x: u32, y: u32) -> Vec<(u32, u32)> {
+        self.shape.topology().neighbours(x, y, self.width, self.height)
+    }
+
+    fn count(&self, x: u32, y: u32) -> u32 {
+        self.neighbours(x, y)
+            .into_iter()
+            .filter(|(nx, ny)| self.mines[self.index(*nx, *ny)])
+            .count() as u32
+    }
+
+    /// Uncover a cell like the game does, spreading out from cells with no mines around them
+    fn reveal(&self, solver: &mut Solver, x: u32, y: u32) {
+        let mut open = vec![(x, y)];
+        while let Some((x, y)) = open.pop() {
+            let i = self.index(x, y);
+            if self.mines[i] || self.blocked[i] || matches!(solver.cells[i], Knowledge::Revealed(_)) {
+                continue;
+            }
+            let count = self.count(x, y);
+            solver.cells[i] = Knowledge::Revealed(count);
+            if count == 0 {
+                open.extend(self.neighbours(x, y));
+            }
+        }
+    }
+
+    /// Can every safe cell be uncovered from the start without guessing
+    pub fn solvable(&self, start: (u32, u32)) -> bool {
+        self.cleared(&self.solve(start))
+    }
+
+    /// Everything a player could work out starting from `start`
+    fn solve(&self, start: (u32, u32)) -> Solver {
+        let mut solver = Solver::new(self.width, self.height, self.shape);
+        for (i, blocked) in self.blocked.iter().enumerate() {
+            if *blocked {
+                solver.cells[i] = Knowledge::Clear;
+            }
+        }
+
+        self.reveal(&mut solver, start.0, start.1);
+        loop {
+            let safe = solver.deduce();
+            if safe.is_empty() {
+                break;
+            }
+            for (x, y) in safe {
+                self.reveal(&mut solver, x, y);
+            }
+        }
+        solver
+    }
+
+    fn cleared(&self, solver: &Solver) -> bool {
+        solver.cells.iter().enumerate().all(|(i, knowledge)| {
+            self.mines[i] || matches!(knowledge, Knowledge::Revealed(_) | Knowledge::Clear)
+        })
+    }
+
+    /// Last resort when no random board could be solved, takes away mines and obstacles at the
+    /// edge of what can be uncovered until the rest can be worked out, returns how many mines
+    /// were taken away
+    fn clear_guesses(&mut self, start: (u32, u32)) -> u32 {
+        let mut removed = 0;
+        loop {
+            let solver = self.solve(start);
+            if self.cleared(&solver) {
+                return removed;
+            }
+
+            let mut mines = Vec::new();
+            let mut walls = Vec::new();
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if !matches!(solver.cells[self.index(x, y)], Knowledge::Revealed(_)) {
+                        continue;
+                    }
+                    for (nx, ny) in self.neighbours(x, y) {
+                        let i = self.index(nx, ny);
+                        if self.mines[i] && solver.cells[i] == Knowledge::Unknown {
+                            mines.push(i);
+                        } else if self.blocked[i] {
+                            walls.push(i);
+                        }
+                    }
+                }
+            }
+
+            // each pass takes something away, and an empty board always clears
+            if let Some(i) = mines.first().copied() {
+                self.mines[i] = false;
+                removed += 1;
+            } else if let Some(i) = walls.first().copied() {
+                self.blocked[i] = false;
+            } else if let Some(i) = self.mines.iter().position(|mine| *mine) {
+                self.mines[i] = false;
+                removed += 1;
+            } else if let Some(i) = self.blocked.iter().position(|blocked| *blocked) {
+                self.blocked[i] = false;
+            } else {
+                unreachable!("an empty board can always be cleared");
+            }
+        }
+    }
+
+    fn write(&self, store: &mut CellStore, rng: &mut ChaCha8Rng) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                let terrain = if self.blocked[i] {
+                    Terrain::Obstacle(match rng.gen_range(0..3) {
+                        0 => ObstacleKind::Crate,
+                        1 => ObstacleKind::Wall,
+                        _ => ObstacleKind::Column,
+                    })
+                } else {
+                    Terrain::Open
+                };
+                let status = MineStatus::Unknown {
+                    mine: self.mines[i],
+                };
+                store.set(x, y, StoredCell::new(status, terrain));
+            }
+        }
+    }
+}
+
+/// Fill the store with a new board, retrying until one can be solved without guessing if asked,
+/// no guess boards are always solvable even if some mines have to go to get there
+pub fn generate(store: &mut CellStore, board: Board, no_guess: bool, rng: &mut ChaCha8Rng) {
+    let mut field = Minefield::random(board, rng);
+    if no_guess {
+        let mut attempts = 1;
+        while !field.solvable(START) && attempts < NO_GUESS_ATTEMPTS {
+            field = Minefield::random(board, rng);
+            attempts += 1;
+        }
+        if !field.solvable(START) {
+            let removed = field.clear_guesses(START);
+            warn!(
+                "no guess board not found in {} attempts, took {} mines off the last one",
+                attempts, removed
+            );
+        }
+    }
+    field.write(store, rng);
+}
+
+/// What the player can know about a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Knowledge {
+    Unknown,
+    /// Proven to be a mine, or one that already went off
+    Mine,
+    /// Proven safe but not uncovered yet
+    Safe,
+    /// Not a mine and gives nothing away, obstacles
+    Clear,
+    Revealed(u32),
+}
+
+/// Works out which cells are safe from the uncovered counts, using the single cell rule and
+/// the subset rule between pairs of counts
+#[derive(Debug, Clone)]
+pub struct Solver {
+    pub width: u32,
+    pub height: u32,
+    pub shape: GridShape,
+    pub cells: Vec<Knowledge>,
+}
+
+impl Solver {
+    pub fn new(width: u32, height: u32, shape: GridShape) -> Self {
+        Solver {
+            width,
+            height,
+            shape,
+            cells: vec![Knowledge::Unknown; (width * height) as usize],
+        }
+    }
+
+    /// What the player knows about the board in play
+    pub fn from_store(store: &CellStore, shape: GridShape) -> Self {
+        let mut solver = Solver::new(store.width(), store.height(), shape);
+        for y in 0..store.height() {
+            for x in 0..store.width() {
+                let cell = match store.get(x, y) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                solver.cells[(y * store.width() + x) as usize] =
+                    match (cell.terrain(), cell.status()) {
+                        (_, MineStatus::Count { count }) => Knowledge::Revealed(count),
+                        (_, MineStatus::Exploded) => Knowledge::Mine,
+                        // obstacles are never mined, nor is what is left of them
+                        (Terrain::Obstacle(_), _) | (Terrain::Rubble, _) => Knowledge::Clear,
+                        _ => Knowledge::Unknown,
+                    };
+            }
+        }
+        solver
+    }
+
+    /// Unknown cells around each uncovered count and how many mines are still among them
+    fn constraints(&self) -> Vec<(Vec<usize>, u32)> {
+        let topology = self.shape.topology();
+        let mut constraints = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let count = match self.cells[(y * self.width + x) as usize] {
+                    Knowledge::Revealed(count) => count,
+                    _ => continue,
+                };
+                let mut unknown = Vec::new();
+                let mut mines = 0;
+                for (nx, ny) in topology.neighbours(x, y, self.width, self.height) {
+                    let i = (ny * self.width + nx) as usize;
+                    match self.cells[i] {
+                        Knowledge::Unknown => unknown.push(i),
+                        Knowledge::Mine => mines += 1,
+                        _ => {}
+                    }
+                }
+                if !unknown.is_empty() {
+                    constraints.push((unknown, count.saturating_sub(mines)));
+                }
+            }
+        }
+        constraints
+    }
+
+    /// Mark everything that follows from what we know, returns the cells newly proven safe
+    pub fn deduce(&mut self) -> Vec<(u32, u32)> {
+        let mut safe = Vec::new();
+        loop {
+            let constraints = self.constraints();
+            let mut progress = false;
+
+            for (cells, mines) in constraints.iter() {
+                if *mines == 0 {
+                    progress |= self.mark(cells, Knowledge::Safe, &mut safe);
+                } else if *mines as usize == cells.len() {
+                    progress |= self.mark(cells, Knowledge::Mine, &mut safe);
+                }
+            }
+
+            // only compare pairs once the cheap rule has nothing left
+            if !progress {
+                'subset: for (a, a_mines) in constraints.iter() {
+                    for (b, b_mines) in constraints.iter() {
+                        if a.len() >= b.len() || !a.iter().all(|i| b.contains(i)) {
+                            continue;
+                        }
+                        let rest: Vec<usize> = b.iter().filter(|i| !a.contains(i)).copied().collect();
+                        let mines = match b_mines.checked_sub(*a_mines) {
+                            Some(mines) => mines,
+                            None => continue,
+                        };
+                        if mines == 0 {
+                            progress |= self.mark(&rest, Knowledge::Safe, &mut safe);
+                        } else if mines as usize == rest.len() {
+                            progress |= self.mark(&rest, Knowledge::Mine, &mut safe);
+                        }
+                        if progress {
+                            break 'subset;
+                        }
+                    }
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+        safe
+    }
+
+    fn mark(&mut self, cells: &[usize], knowledge: Knowledge, safe: &mut Vec<(u32, u32)>) -> bool {
+        let mut changed = false;
+        for i in cells.iter() {
+            if self.cells[*i] != Knowledge::Unknown {
+                continue;
+            }
+            self.cells[*i] = knowledge;
+            if knowledge == Knowledge::Safe {
+                safe.push((*i as u32 % self.width, *i as u32 / self.width));
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Uncover a cell in the store, spreading out from cells with no mines around them, returns
+/// every cell that changed
+pub fn reveal(store: &mut CellStore, shape: GridShape, x: u32, y: u32) -> Vec<(u32, u32)> {
+    let topology = shape.topology();
+    let mut revealed = Vec::new();
+    let mut open = vec![(x, y)];
+    while let Some((x, y)) = open.pop() {
+        let cell = match store.get(x, y) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        if cell.status() != (MineStatus::Unknown { mine: false })
+            || matches!(cell.terrain(), Terrain::Obstacle(_))
+        {
+            continue;
+        }
+
+        let count = topology
+            .neighbours(x, y, store.width(), store.height())
+            .into_iter()
+            .filter(|(nx, ny)| {
+                matches!(
+                    store.get(*nx, *ny).map(|c| c.status()),
+                    Some(MineStatus::Unknown { mine: true }) | Some(MineStatus::Exploded)
+                )
+            })
+            .count() as u32;
+        store.set(x, y, StoredCell::new(MineStatus::Count { count }, cell.terrain()));
+        revealed.push((x, y));
+        if count == 0 {
+            open.extend(topology.neighbours(x, y, store.width(), store.height()));
+        }
+    }
+    revealed
+}
+
+fn reset_status(mut status: ResMut<MinesweeperStatus>) {
+    *status = MinesweeperStatus::default();
+}
+
+/// Friendly tanks uncover safe cells they drive onto, mines are left to the explosion systems
+fn reveal_cells(
+    layout: Res<MapLayout>,
+    map_cells: Res<MapCells>,
+    mut store: ResMut<CellStore>,
+    mut picking: ResMut<CellPicking>,
+    tanks: Query<(&Tank, &Transform)>,
+    mut cells: Query<&mut MineLocation>,
+    mut ev_reveal: EventWriter<RevealEvent>,
+) {
+    for (tank, transform) in tanks.iter() {
+        if tank.team != Team::Friendly {
+            continue;
+        }
+        let (x, y) = match layout.world_to_cell(transform.translation) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        if store.get(x, y).map(|c| c.status()) != Some(MineStatus::Unknown { mine: false }) {
+            continue;
+        }
+
+        let revealed = reveal(&mut store, layout.shape, x, y);
+        for (cx, cy) in revealed.iter().copied() {
+            let status = store.get(cx, cy).unwrap().status();
+            if let Some(mut cell) = map_cells.get(cx, cy).and_then(|e| cells.get_mut(e).ok()) {
+                cell.status = status;
+            }
+            if picking.hint == Some((cx, cy)) {
+                picking.hint = None;
+            }
+        }
+        ev_reveal.send(RevealEvent {
+            origin: (x, y),
+            cells: revealed,
+        });
+    }
+}
+
+fn count_cells(store: Res<CellStore>, mut status: ResMut<MinesweeperStatus>) {
+    if !store.is_changed() {
+        return;
+    }
+
+    let mut mines = 0;
+    let mut hidden = 0;
+    for y in 0..store.height() {
+        for x in 0..store.width() {
+            let cell = match store.get(x, y) {
+                Some(cell) => cell,
+                None => continue,
+            };
+            match (cell.status(), cell.terrain()) {
+                (MineStatus::Unknown { mine: true }, _) | (MineStatus::Exploded, _) => mines += 1,
+                (MineStatus::Unknown { mine: false }, Terrain::Open)
+                | (MineStatus::Unknown { mine: false }, Terrain::Rubble) => hidden += 1,
+                _ => {}
+            }
+        }
+    }
+    status.mines = mines;
+    status.hidden = hidden;
+}
+
+fn count_color(count: u32) -> Color32 {
+    match count {
+        1 => Color32::from_rgb(30, 60, 230),
+        2 => Color32::from_rgb(20, 130, 20),
+        3 => Color32::from_rgb(220, 30, 30),
+        4 => Color32::from_rgb(20, 20, 130),
+        5 => Color32::from_rgb(130, 20, 20),
+        6 => Color32::from_rgb(20, 130, 130),
+        7 => Color32::BLACK,
+        _ => Color32::from_rgb(90, 90, 90),
+    }
+}
+
+/// Mine counts on uncovered cells, only for loaded chunks so big maps stay cheap
+fn draw_counts(
+    egui_ctx: Res<EguiContext>,
+    egui_settings: Res<EguiSettings>,
+    windows: Res<Windows>,
+    layout: Res<MapLayout>,
+    store: Res<CellStore>,
+    fog: Res<FogOfWar>,
+    loaded: Res<LoadedChunks>,
+    viewports: Res<PlayerViewports>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let window_height = match windows.get_primary() {
+        Some(window) => window.height(),
+        None => return,
+    };
+
+    let painter = egui_ctx
+        .ctx()
+        .layer_painter(LayerId::new(Order::Background, Id::new("mine_counts")));
+    let scale = egui_settings.scale_factor as f32;
+
+    for viewport in viewports.viewports.iter() {
+        let (camera, camera_transform) = match cameras.get(viewport.camera) {
+            Ok(camera) => camera,
+            Err(_) => continue,
+        };
+        let painter = painter.with_clip_rect(viewport_rect(viewport, window_height, scale));
+
+        for chunk in loaded.chunks.keys() {
+            let (min, max) = layout.chunk_cells(*chunk);
+            for y in min.1..max.1 {
+                for x in min.0..max.0 {
+                    let count = match store.get(x, y).map(|c| c.status()) {
+                        Some(MineStatus::Count { count }) if count > 0 => count,
+                        _ => continue,
+                    };
+                    if !fog.is_explored(x, y) {
+                        continue;
+                    }
+                    let position = match viewport.world_to_window(
+                        camera,
+                        camera_transform,
+                        layout.cell_to_world(x, y),
+                    ) {
+                        Some(position) => position,
+                        None => continue,
+                    };
+
+                    // bevy screen space starts bottom left, egui top left
+                    let center = Pos2::new(position.x / scale, (window_height - position.y) / scale);
+                    painter.text(
+                        center,
+                        Align2::CENTER_CENTER,
+                        count.to_string(),
+                        TextStyle::Heading,
+                        count_color(count),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Board info and the hint button, the hint goes to the proven safe cell closest to a local tank
+fn draw_minesweeper_window(
+    egui_ctx: Res<EguiContext>,
+    settings: Res<MinesweeperSettings>,
+    daily: Res<DailyChallenge>,
+    clock: Res<MinesweeperClock>,
+    tanks_data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    store: Res<CellStore>,
+    mut status: ResMut<MinesweeperStatus>,
+    mut picking: ResMut<CellPicking>,
+    tanks: Query<(&Tank, &Transform)>,
+) {
+    Window::new("Minesweeper")
+        .resizable(false)
+        .default_pos([10.0, 200.0])
+        .show(egui_ctx.ctx(), |ui| {
+            match daily.date {
+                Some(date) => ui.label(format!("Daily Challenge {}", date)),
+                None => ui.label(format!(
+                    "{} {}x{}",
+                    settings.difficulty, layout.width, layout.height
+                )),
+            };
+            ui.label(format!("Time {:.1}", clock.elapsed));
+            ui.label(format!("Mines {}", status.mines));
+            if status.hidden == 0 {
+                ui.label("Board cleared");
+            } else {
+                ui.label(format!("Cells left {}", status.hidden));
+            }
+
+            if ui.button("Hint").clicked() {
+                let mut solver = Solver::from_store(&store, layout.shape);
+                let safe = solver.deduce();
+                let near = tanks
+                    .iter()
+                    .find(|(tank, _)| tanks_data.is_local(tank.player))
+                    .map(|(_, transform)| transform.translation)
+                    .unwrap_or_default();
+                let hint = safe.into_iter().min_by(|a, b| {
+                    let a = layout.cell_to_world(a.0, a.1).distance_squared(near);
+                    let b = layout.cell_to_world(b.0, b.1).distance_squared(near);
+                    a.partial_cmp(&b).unwrap()
+                });
+                picking.hint = hint;
+                status.message = match hint {
+                    Some(_) => None,
+                    None => Some("No cell can be proven safe".to_string()),
+                };
+            }
+            if let Some(message) = &status.message {
+                ui.label(egui::Label::new(message).small());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn field(rows: &[&str]) -> Minefield {
+        let height = rows.len() as u32;
+        let width = rows[0].len() as u32;
+        let mut field = Minefield {
+            width,
+            height,
+            shape: GridShape::Square,
+            blocked: vec![false; (width * height) as usize],
+            mines: vec![false; (width * height) as usize],
+        };
+        // first row is y = 0
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let i = y * width as usize + x;
+                field.mines[i] = c == '*';
+                field.blocked[i] = c == '#';
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn solves_simple_board() {
+        let field = field(&["....", "....", "...*", "...."]);
+        assert!(field.solvable(START));
+    }
+
+    #[test]
+    fn detects_fifty_fifty() {
+        // the mine in the top row could be either cell
+        let field = field(&["..", "..", "*."]);
+        assert!(!field.solvable(START));
+    }
+
+    #[test]
+    fn detects_walled_off_cells() {
+        let field = field(&["....", "....", "####", ".*.."]);
+        assert!(!field.solvable(START));
+    }
+
+    #[test]
+    fn subset_rule() {
+        // 1 and 2 next to each other along a wall, the 2 has one more unknown than the 1
+        let mut solver = Solver::new(3, 2, GridShape::Square);
+        solver.cells = vec![
+            Knowledge::Revealed(1),
+            Knowledge::Revealed(2),
+            Knowledge::Revealed(1),
+            Knowledge::Unknown,
+            Knowledge::Unknown,
+            Knowledge::Unknown,
+        ];
+        let safe = solver.deduce();
+        assert_eq!(safe, vec![(1, 1)]);
+        assert_eq!(solver.cells[3], Knowledge::Mine);
+        assert_eq!(solver.cells[5], Knowledge::Mine);
+    }
+
+    fn stored_field(store: &CellStore, board: Board) -> Minefield {
+        let size = (board.width * board.height) as usize;
+        let mut field = Minefield {
+            width: board.width,
+            height: board.height,
+            shape: board.shape,
+            blocked: vec![false; size],
+            mines: vec![false; size],
+        };
+        for y in 0..board.height {
+            for x in 0..board.width {
+                let i = field.index(x, y);
+                let cell = store.get(x, y).unwrap();
+                field.mines[i] = cell.status() == (MineStatus::Unknown { mine: true });
+                field.blocked[i] = matches!(cell.terrain(), Terrain::Obstacle(_));
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn clearing_guesses_makes_boards_solvable() {
+        let mut fifty_fifty = field(&["..", "..", "*."]);
+        assert_eq!(fifty_fifty.clear_guesses(START), 1);
+        assert!(fifty_fifty.solvable(START));
+
+        // nothing behind the wall can be reached until some of it goes
+        let mut walled_off = field(&["....", "....", "####", ".*.."]);
+        walled_off.clear_guesses(START);
+        assert!(walled_off.solvable(START));
+        assert!(walled_off.blocked.iter().any(|blocked| !*blocked));
+    }
+
+    #[test]
+    fn no_guess_boards_are_solvable_even_when_too_crowded() {
+        // every open cell is mined, so no random board can be solved
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let board = Board {
+            width: 4,
+            height: 4,
+            mines: 10,
+            shape: GridShape::Square,
+        };
+        let mut store = CellStore::new(board.width, board.height);
+        generate(&mut store, board, true, &mut rng);
+        assert!(stored_field(&store, board).solvable(START));
+    }
+
+    #[test]
+    fn same_seed_same_board() {
+        let board = Board {
+            width: 16,
+            height: 16,
+            mines: 40,
+            shape: GridShape::Square,
+        };
+        let boards: Vec<_> = (0..2)
+            .map(|_| {
+                let mut rng = ChaCha8Rng::seed_from_u64(20745);
+                let mut store = CellStore::new(board.width, board.height);
+                generate(&mut store, board, false, &mut rng);
+                let field = stored_field(&store, board);
+                (field.mines, field.blocked)
+            })
+            .collect();
+        assert_eq!(boards[0], boards[1]);
+    }
+
+    #[test]
+    fn no_guess_boards_are_solvable() {
+        for shape in [GridShape::Square, GridShape::Hex].iter() {
+            let mut rng = ChaCha8Rng::seed_from_u64(7);
+            let board = Board {
+                width: 9,
+                height: 9,
+                mines: 10,
+                shape: *shape,
+            };
+            let mut store = CellStore::new(board.width, board.height);
+            generate(&mut store, board, true, &mut rng);
+
+            let field = stored_field(&store, board);
+            assert_eq!(field.mines.iter().filter(|m| **m).count(), 10);
+            assert!(field.solvable(START));
+        }
+    }
+
+    #[test]
+    fn reveal_spreads_from_empty_cells() {
+        let mut store = CellStore::new(3, 3);
+        store.set(2, 2, StoredCell::new(MineStatus::Unknown { mine: true }, Terrain::Open));
+        let revealed = reveal(&mut store, GridShape::Square, 0, 0);
+        assert_eq!(revealed.len(), 8);
+        assert_eq!(
+            store.get(1, 1).unwrap().status(),
+            MineStatus::Count { count: 1 }
+        );
+    }
+
+    #[test]
+    fn hex_counts_use_six_neighbours() {
+        // (0, 2) touches (1, 1) on a square grid but not on a hex one
+        let mut store = CellStore::new(3, 3);
+        store.set(0, 2, StoredCell::new(MineStatus::Unknown { mine: true }, Terrain::Open));
+        reveal(&mut store, GridShape::Hex, 1, 1);
+        assert_eq!(
+            store.get(1, 1).unwrap().status(),
+            MineStatus::Count { count: 0 }
+        );
+        // the flood stops beside the mine
+        assert_eq!(
+            store.get(1, 2).unwrap().status(),
+            MineStatus::Count { count: 1 }
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_egui::{
+    egui::{self, Align2, Area, Color32, Pos2, Rect, Sense, Stroke, Vec2 as EguiVec2},
+    EguiContext,
+};
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+
+use crate::{editor::RegisterConsoleCommand, GameState};
+
+use super::{
+    camera::CameraComponent,
+    map::{MapLayout, MineStatus},
+    split_screen::PlayerViewports,
+    streaming::{CellStore, Terrain},
+    tank::{Tank, Team},
+    visibility::{CellVisibility, FogOfWar},
+};
+
+pub struct MinimapPlugin {
+    state: GameState,
+}
+
+impl MinimapPlugin {
+    pub fn new(state: GameState) -> Self {
+        MinimapPlugin { state: state }
+    }
+}
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<MinimapSettings>::new().open(false))
+            .register_console_resource::<MinimapSettings>("minimap")
+            .add_system_set(
+                SystemSet::on_update(self.state.clone()).with_system(draw_minimap.system()),
+            );
+    }
+}
+
+#[derive(Inspectable, Debug, Clone, Copy, PartialEq)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl MinimapCorner {
+    fn anchor(&self, margin: f32) -> (Align2, [f32; 2]) {
+        match self {
+            MinimapCorner::TopLeft => (Align2::LEFT_TOP, [margin, margin]),
+            MinimapCorner::TopRight => (Align2::RIGHT_TOP, [-margin, margin]),
+            MinimapCorner::BottomLeft => (Align2::LEFT_BOTTOM, [margin, -margin]),
+            MinimapCorner::BottomRight => (Align2::RIGHT_BOTTOM, [-margin, -margin]),
+        }
+    }
+}
+
+#[derive(Inspectable, Debug, Reflect)]
+pub struct MinimapSettings {
+    pub enabled: bool,
+    /// Size of the longest side in points
+    #[inspectable(min = 50.0, max = 600.0)]
+    pub size: f32,
+    #[reflect(ignore)]
+    pub corner: MinimapCorner,
+    #[inspectable(min = 0.0, max = 100.0)]
+    pub margin: f32,
+}
+
+impl FromWorld for MinimapSettings {
+    fn from_world(_world: &mut World) -> Self {
+        MinimapSettings {
+            enabled: true,
+            size: 200.0,
+            corner: MinimapCorner::BottomRight,
+            margin: 10.0,
+        }
+    }
+}
+
+/// Most blocks drawn along a side, past that several cells share a block
+const MAX_BLOCKS: u32 = 96;
+
+/// Anything worth pointing out on the minimap, objectives like the spawn points players fight
+/// over and the like
+pub struct MinimapIcon {
+    pub color: Color,
+    pub label: Option<String>,
+}
+
+/// World directions along the ground that point right and up the screen, snapped to the
+/// sides of the map so it stays a rectangle on the minimap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenAxes {
+    pub right: Vec3,
+    pub up: Vec3,
+}
+
+impl Default for ScreenAxes {
+    /// The free tanks camera, looking straight down with +z up the screen
+    fn default() -> Self {
+        ScreenAxes {
+            right: -Vec3::X,
+            up: Vec3::Z,
+        }
+    }
+}
+
+impl ScreenAxes {
+    /// For a camera looking down at the ground
+    pub fn of(camera: &GlobalTransform) -> Self {
+        ScreenAxes {
+            right: ground_axis(camera.rotation * Vec3::X),
+            up: ground_axis(camera.rotation * Vec3::Y),
+        }
+    }
+
+    /// Turned like the first view, split screen views all face the same way
+    pub fn of_views(
+        viewports: &PlayerViewports,
+        cameras: &Query<(&Camera, &GlobalTransform)>,
+    ) -> Self {
+        viewports
+            .viewports
+            .first()
+            .and_then(|viewport| cameras.get(viewport.camera).ok())
+            .map_or(ScreenAxes::default(), |(_, transform)| {
+                ScreenAxes::of(transform)
+            })
+    }
+
+    /// Size of something this big across x and z once it's turned to the screen
+    fn on_screen(&self, size: Vec2) -> Vec2 {
+        if self.right.x != 0.0 {
+            size
+        } else {
+            Vec2::new(size.y, size.x)
+        }
+    }
+}
+
+/// The side of the map a direction mostly runs along
+fn ground_axis(direction: Vec3) -> Vec3 {
+    if direction.x.abs() >= direction.z.abs() {
+        Vec3::X * direction.x.signum()
+    } else {
+        Vec3::Z * direction.z.signum()
+    }
+}
+
+/// Converts between world positions and minimap positions, the minimap is turned the same way
+/// as the camera
+struct MinimapSpace {
+    rect: Rect,
+    layout: MapLayout,
+    axes: ScreenAxes,
+    scale: f32,
+}
+
+impl MinimapSpace {
+    fn new(rect: Rect, layout: MapLayout, axes: ScreenAxes) -> Self {
+        let world = axes.on_screen(layout.size());
+        MinimapSpace {
+            rect,
+            layout,
+            axes,
+            scale: rect.width() / world.x,
+        }
+    }
+
+    /// The middle of the map is the middle of the minimap
+    fn center(&self) -> Vec3 {
+        let size = self.layout.size();
+        self.layout.origin() + Vec3::new(size.x, 0.0, size.y) * 0.5
+    }
+
+    fn world_to_minimap(&self, position: Vec3) -> Pos2 {
+        let local = position - self.center();
+        self.rect.center()
+            + EguiVec2::new(
+                local.dot(self.axes.right) * self.scale,
+                -local.dot(self.axes.up) * self.scale,
+            )
+    }
+
+    fn minimap_to_world(&self, position: Pos2) -> Vec3 {
+        let local = position - self.rect.center();
+        self.center() + self.axes.right * (local.x / self.scale)
+            - self.axes.up * (local.y / self.scale)
+    }
+
+    /// How far to move a camera looking at `looking_at` on the ground so it looks at the
+    /// point clicked on the minimap, it keeps its height
+    fn pan_to(&self, position: Pos2, looking_at: Vec3) -> Vec3 {
+        let delta = self.minimap_to_world(position) - looking_at;
+        Vec3::new(delta.x, 0.0, delta.z)
+    }
+
+    /// Hex cells are drawn as the rectangle they tile, close enough at minimap size
+    fn cell_rect(&self, x: u32, y: u32) -> Rect {
+        let half = self.layout.footprint() * 0.5;
+        let center = self.layout.cell_to_world(x, y);
+        Rect::from_two_pos(
+            self.world_to_minimap(center - Vec3::new(half.x, 0.0, half.y)),
+            self.world_to_minimap(center + Vec3::new(half.x, 0.0, half.y)),
+        )
+    }
+}
+
+fn to_color32(color: Color) -> Color32 {
+    Color32::from_rgb(
+        (color.r() * 255.0) as u8,
+        (color.g() * 255.0) as u8,
+        (color.b() * 255.0) as u8,
+    )
+}
+
+fn cell_color(status: MineStatus, visibility: CellVisibility, terrain: Terrain) -> Color32 {
+    let color = match (status, terrain) {
+        (_, Terrain::Obstacle(_)) => Color32::from_rgb(115, 75, 40),
+        (_, Terrain::Rubble) => Color32::from_rgb(90, 75, 65),
+        (MineStatus::Count { .. }, _) => Color32::from_rgb(200, 200, 200),
+        (MineStatus::Marked, _) => Color32::from_rgb(230, 140, 20),
+        (MineStatus::Exploded, _) => Color32::from_rgb(200, 30, 30),
+        (MineStatus::Unknown { .. }, _) => Color32::from_rgb(120, 120, 120),
+    };
+
+    match visibility {
+        CellVisibility::Unexplored => Color32::from_rgb(15, 15, 15),
+        // remembered cells are dimmed
+        CellVisibility::Explored => Color32::from_rgb(color.r() / 2, color.g() / 2, color.b() / 2),
+        CellVisibility::Visible => color,
+    }
+}
+
+fn draw_minimap(
+    egui_ctx: Res<EguiContext>,
+    settings: Res<MinimapSettings>,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    viewports: Res<PlayerViewports>,
+    store: Res<CellStore>,
+    tanks: Query<(&Tank, &GlobalTransform)>,
+    icons: Query<(&MinimapIcon, &GlobalTransform)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut movable: Query<&mut Transform, With<CameraComponent>>,
+) {
+    if !settings.enabled || layout.width == 0 || layout.height == 0 {
+        return;
+    }
+
+    let axes = ScreenAxes::of_views(&viewports, &cameras);
+    let world = axes.on_screen(layout.size());
+    let aspect = world.y / world.x;
+    let size = if aspect > 1.0 {
+        EguiVec2::new(settings.size / aspect, settings.size)
+    } else {
+        EguiVec2::new(settings.size, settings.size * aspect)
+    };
+    let (align, offset) = settings.corner.anchor(settings.margin);
+
+    Area::new("tanks_minimap")
+        .anchor(align, offset)
+        .show(egui_ctx.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+                let space = MinimapSpace::new(response.rect, *layout, axes);
+
+                // big maps are drawn in blocks, each colored from the cell in its middle
+                let step = (layout.width.max(layout.height) + MAX_BLOCKS - 1) / MAX_BLOCKS;
+                let gap = if step == 1 { 0.5 } else { 0.0 };
+                for y in (0..layout.height).step_by(step as usize) {
+                    for x in (0..layout.width).step_by(step as usize) {
+                        let sample = (
+                            (x + step / 2).min(layout.width - 1),
+                            (y + step / 2).min(layout.height - 1),
+                        );
+                        let cell = match store.get(sample.0, sample.1) {
+                            Some(cell) => cell,
+                            None => continue,
+                        };
+                        let color =
+                            cell_color(cell.status(), fog.get(sample.0, sample.1), cell.terrain());
+                        let last = (
+                            (x + step - 1).min(layout.width - 1),
+                            (y + step - 1).min(layout.height - 1),
+                        );
+                        let rect = space.cell_rect(x, y).union(space.cell_rect(last.0, last.1));
+                        painter.rect_filled(rect.shrink(gap), 0.0, color);
+                    }
+                }
+
+                for (icon, transform) in icons.iter() {
+                    let center = space.world_to_minimap(transform.translation);
+                    painter.circle_filled(center, 3.0, to_color32(icon.color));
+                    painter.circle_stroke(center, 4.0, Stroke::new(1.0, Color32::WHITE));
+                    if let Some(label) = &icon.label {
+                        painter.text(
+                            center + EguiVec2::new(5.0, 0.0),
+                            Align2::LEFT_CENTER,
+                            label,
+                            egui::TextStyle::Small,
+                            Color32::WHITE,
+                        );
+                    }
+                }
+
+                for (tank, transform) in tanks.iter() {
+                    let seen = tank.team == Team::Friendly
+                        || layout
+                            .world_to_cell(transform.translation)
+                            .map(|(x, y)| fog.is_visible(x, y))
+                            .unwrap_or(false);
+                    if !seen {
+                        continue;
+                    }
+
+                    let color = match tank.team {
+                        Team::Friendly => Color32::from_rgb(40, 90, 230),
+                        Team::Enemy => Color32::from_rgb(230, 40, 40),
+                    };
+                    let center = space.world_to_minimap(transform.translation);
+                    let heading = space.world_to_minimap(
+                        transform.translation + transform.forward() * layout.cell_size * 0.75,
+                    );
+                    painter.circle_filled(center, 3.5, color);
+                    painter.line_segment([center, heading], Stroke::new(1.5, color));
+                }
+
+                // outline what each camera can see on the ground
+                for viewport in viewports.viewports.iter() {
+                    let (camera, transform) = match cameras.get(viewport.camera) {
+                        Ok(camera) => camera,
+                        Err(_) => continue,
+                    };
+                    let corners = [
+                        viewport.min,
+                        viewport.min + Vec2::new(viewport.size.x, 0.0),
+                        viewport.min + viewport.size,
+                        viewport.min + Vec2::new(0.0, viewport.size.y),
+                    ];
+                    let points: Vec<Pos2> = corners
+                        .iter()
+                        .filter_map(|corner| viewport.ground(camera, transform, *corner))
+                        .map(|hit| space.world_to_minimap(hit))
+                        .collect();
+                    if points.len() == corners.len() {
+                        for i in 0..points.len() {
+                            painter.line_segment(
+                                [points[i], points[(i + 1) % points.len()]],
+                                Stroke::new(1.0, Color32::YELLOW),
+                            );
+                        }
+                    }
+                }
+
+                // clicking moves the free camera, split screen cameras stay with their tanks
+                if response.clicked() || response.dragged() {
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        for viewport in viewports.viewports.iter() {
+                            let (camera, camera_transform) = match cameras.get(viewport.camera) {
+                                Ok(camera) => camera,
+                                Err(_) => continue,
+                            };
+                            let center = viewport.min + viewport.size * 0.5;
+                            let looking_at = match viewport.ground(camera, camera_transform, center) {
+                                Some(hit) => hit,
+                                None => continue,
+                            };
+                            if let Ok(mut transform) = movable.get_mut(viewport.camera) {
+                                transform.translation += space.pan_to(pointer, looking_at);
+                            }
+                        }
+                    }
+                }
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> MinimapSpace {
+        // the default map is 10 by 10 cells of 256, so 2560 across centered on zero
+        let rect = Rect::from_min_size(Pos2::new(10.0, 20.0), EguiVec2::new(100.0, 100.0));
+        MinimapSpace::new(rect, MapLayout::default(), ScreenAxes::default())
+    }
+
+    /// Where the free tanks camera sits and looks
+    fn tanks_camera() -> GlobalTransform {
+        let mut transform = Transform::from_xyz(0.0, 3500.0, 0.0);
+        transform.look_at(Vec3::ZERO, Vec3::Z);
+        GlobalTransform::from(transform)
+    }
+
+    fn close(a: Pos2, b: Pos2) -> bool {
+        (a - b).length() < 0.001
+    }
+
+    #[test]
+    fn corners_of_the_map_land_on_the_minimap_corners() {
+        let space = space();
+        let layout = MapLayout::default();
+        // +z is up the minimap and -x is right, like the tanks camera
+        assert!(close(
+            space.world_to_minimap(layout.origin()),
+            Pos2::new(110.0, 120.0)
+        ));
+        assert!(close(
+            space.world_to_minimap(Vec3::new(1280.0, 0.0, 1280.0)),
+            Pos2::new(10.0, 20.0)
+        ));
+        assert!(close(space.world_to_minimap(Vec3::ZERO), Pos2::new(60.0, 70.0)));
+        // height doesn't matter
+        assert!(close(
+            space.world_to_minimap(Vec3::new(0.0, 500.0, 0.0)),
+            Pos2::new(60.0, 70.0)
+        ));
+    }
+
+    #[test]
+    fn minimap_and_world_round_trip() {
+        let space = space();
+        for position in [
+            Vec3::new(-1000.0, 0.0, 300.0),
+            Vec3::new(12.5, 0.0, -640.0),
+            Vec3::ZERO,
+        ]
+        .iter()
+        {
+            let back = space.minimap_to_world(space.world_to_minimap(*position));
+            assert!(back.distance(*position) < 0.01);
+        }
+    }
+
+    #[test]
+    fn clicking_pans_the_camera_over_the_point() {
+        let space = space();
+        // camera looking at the middle of the map, click the top right corner
+        let delta = space.pan_to(Pos2::new(110.0, 20.0), Vec3::ZERO);
+        assert!(delta.distance(Vec3::new(-1280.0, 0.0, 1280.0)) < 0.01);
+
+        // already looking there, nothing to do, and the camera never changes height
+        let delta = space.pan_to(Pos2::new(60.0, 70.0), Vec3::new(0.0, 40.0, 0.0));
+        assert!(delta.length() < 0.01);
+    }
+
+    #[test]
+    fn minimap_faces_the_same_way_as_the_view() {
+        let camera = tanks_camera();
+        assert_eq!(ScreenAxes::of(&camera), ScreenAxes::default());
+
+        // split screen cameras sit back towards +z, so they see the map the other way up
+        let split = GlobalTransform::from(
+            Transform::from_xyz(0.0, 1800.0, 900.0).looking_at(Vec3::ZERO, Vec3::Y),
+        );
+        let rect = Rect::from_min_size(Pos2::ZERO, EguiVec2::new(100.0, 100.0));
+        for camera in [camera, split].iter() {
+            let space = MinimapSpace::new(rect, MapLayout::default(), ScreenAxes::of(camera));
+            let tank = Vec3::new(300.0, 0.0, -200.0);
+            // where the tank is in front of the camera, +x right and +y up
+            let seen = camera.rotation.inverse() * (tank - camera.translation);
+            let drawn = space.world_to_minimap(tank) - rect.center();
+            assert_eq!(seen.x > 0.0, drawn.x > 0.0, "right for {:?}", camera);
+            // egui's y goes down the screen
+            assert_eq!(seen.y > 0.0, drawn.y < 0.0, "up for {:?}", camera);
+        }
+    }
+}
@@ -1,28 +1,99 @@
 mod map;
+pub mod tank;
 pub mod actions;
+pub mod artillery;
+pub mod benchmark;
 pub mod camera;
+pub mod explosion;
+pub mod health;
+pub mod highlight;
+pub mod hud;
+pub mod minimap;
+pub mod minesweeper;
+pub mod projectile;
+pub mod records;
+pub mod save;
+pub mod split_screen;
+pub mod streaming;
+pub mod topology;
+pub mod visibility;
 
 use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
 use bevy_inspector_egui::InspectorPlugin;
-use crate::GameState;
+use serde::{Deserialize, Serialize};
+use crate::{editor::{GameTime, RegisterConsoleCommand}, GameStages, GameState};
 
 use map::MapPlugin;
+use tank::TankPlugin;
+use actions::TankActionsPlugin;
+use artillery::ArtilleryPlugin;
+use benchmark::BenchmarkPlugin;
 use camera::CameraPlugin;
+use explosion::ExplosionPlugin;
+use health::{DamageRules, HealthPlugin};
+use highlight::HighlightPlugin;
+use hud::HudPlugin;
+use minimap::MinimapPlugin;
+use minesweeper::MinesweeperPlugin;
+use projectile::ProjectilePlugin;
+use records::RecordsPlugin;
+use save::SavePlugin;
+use split_screen::SplitScreenPlugin;
+use streaming::StreamingPlugin;
+use tank::PlayerId;
+use visibility::VisibilityPlugin;
 
-#[derive(Inspectable, Debug)]
+/// How a Tanks game is played
+#[derive(Inspectable, Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum TanksMode {
+    /// Everyone drives and fires at once
+    RealTime,
+    /// Players take turns aiming and firing lobbed shells
+    Artillery,
+}
+
+impl Default for TanksMode {
+    fn default() -> Self {
+        TanksMode::RealTime
+    }
+}
+
+#[derive(Inspectable, Debug, Reflect)]
 pub struct TanksData {
     clear_color: Color,
+    pub mode: TanksMode,
+    /// Players sharing this machine, 2 splits the screen, takes effect next time the game starts
+    #[inspectable(min = 1, max = 2)]
+    pub local_players: u32,
+    pub damage: DamageRules,
 }
 
 impl FromWorld for TanksData {
     fn from_world(world: &mut World) -> Self {
         TanksData {
             clear_color: Color::BLACK,
+            mode: TanksMode::RealTime,
+            local_players: 1,
+            damage: DamageRules::default(),
         }
     }
 }
 
+impl TanksData {
+    /// Is this player controlled by someone at this machine
+    pub fn is_local(&self, player: PlayerId) -> bool {
+        player.0 < self.local_players
+    }
+}
+
+/// Seconds played this session, carried over by save games
+#[derive(Debug, Default)]
+pub struct SessionClock {
+    pub elapsed: f32,
+}
+
 pub struct TanksPlugin {
     state: GameState,
 }
@@ -38,13 +109,36 @@ impl TanksPlugin {
 impl Plugin for TanksPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_plugin(InspectorPlugin::<TanksData>::new().open(false))
+            .register_console_resource::<TanksData>("tanks")
+            .insert_resource(SessionClock::default())
             .add_plugin(MapPlugin::new(self.state.clone()))
+            .add_plugin(StreamingPlugin::new(self.state.clone()))
+            .add_plugin(MinesweeperPlugin::new(self.state.clone()))
+            .add_plugin(HighlightPlugin::new(self.state.clone()))
+            .add_plugin(RecordsPlugin::new(self.state.clone()))
+            .add_plugin(TankPlugin::new(self.state.clone()))
+            .add_plugin(TankActionsPlugin::new(self.state.clone()))
+            .add_plugin(ProjectilePlugin::new(self.state.clone()))
+            .add_plugin(ArtilleryPlugin::new(self.state.clone()))
+            .add_plugin(ExplosionPlugin::new(self.state.clone()))
+            .add_plugin(HealthPlugin::new(self.state.clone()))
+            .add_plugin(HudPlugin::new(self.state.clone()))
+            .add_plugin(MinimapPlugin::new(self.state.clone()))
+            .add_plugin(VisibilityPlugin::new(self.state.clone()))
             .add_plugin(CameraPlugin::new(self.state.clone()))
+            .add_plugin(SplitScreenPlugin::new(self.state.clone()))
+            .add_plugin(BenchmarkPlugin::new(self.state.clone()))
+            .add_plugin(SavePlugin::new(self.state.clone()))
             .add_system_set(
                 SystemSet::on_enter(self.state.clone())
                 .with_system(startup.system())
             )
             .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                .with_system(update_clear_color.system())
+            )
+            .add_system_set_to_stage(
+                GameStages::Game,
                 SystemSet::on_update(self.state.clone())
                 .with_system(update.system())
             );
@@ -55,17 +149,19 @@ impl Plugin for TanksPlugin {
 fn startup(
     data: Res<TanksData>,
     mut clear_color: ResMut<ClearColor>,
+    mut clock: ResMut<SessionClock>,
 ) {
     clear_color.0 = data.clear_color;
+    clock.elapsed = 0.0;
 }
 
-fn update(
-    mut clear_color: ResMut<ClearColor>,
-    data: Res<TanksData>,
-) {
+/// Outside the gameplay stage, so edits to the color show up while paused
+fn update_clear_color(mut clear_color: ResMut<ClearColor>, data: Res<TanksData>) {
     if data.is_changed() {
         clear_color.0 = data.clear_color;
     }
 }
 
-
+fn update(time: Res<GameTime>, mut clock: ResMut<SessionClock>) {
+    clock.elapsed += time.delta_seconds();
+}
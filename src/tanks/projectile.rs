@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+
+use crate::{editor::GameTime, helpers::cleanup_system, GameStages, GameState};
+
+use super::{
+    explosion::{ExplosionData, ExplosionEvent, ExplosionSource},
+    map::MapLayout,
+    tank::Tank,
+    visibility::FogOfWar,
+};
+
+pub struct ProjectilePlugin {
+    state: GameState,
+}
+
+impl ProjectilePlugin {
+    pub fn new(state: GameState) -> Self {
+        ProjectilePlugin { state: state }
+    }
+}
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<ProjectileData>::new().open(false))
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(shell_movement.system().label("shell_movement"))
+                    .with_system(shell_impact.system().after("shell_movement")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone()).with_system(cleanup_system::<Shell>.system()),
+            );
+    }
+}
+
+#[derive(Inspectable, Debug)]
+pub struct ProjectileData {
+    /// World units per second
+    #[inspectable(min = 0.0)]
+    pub shell_speed: f32,
+    /// Seconds before a shell that hit nothing goes off on its own
+    #[inspectable(min = 0.0)]
+    pub shell_lifetime: f32,
+
+    pub shell_mesh: Handle<Mesh>,
+    pub shell_material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ProjectileData {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+
+        let mut meshes = world
+            .get_resource_mut::<Assets<Mesh>>()
+            .expect("ResMut<Assets<Mesh>> not found.");
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .expect("ResMut<Assets<StandardMaterial>> not found.");
+
+        ProjectileData {
+            shell_speed: 1500.0,
+            shell_lifetime: 2.0,
+            shell_mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 20.0,
+                subdivisions: 2,
+            })),
+            shell_material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.9, 0.9, 0.2),
+                unlit: true,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+pub struct Shell {
+    pub velocity: Vec3,
+    /// Tank that fired it, so we don't blow up in our own barrel
+    pub owner: Option<Entity>,
+    pub lifetime: Timer,
+}
+
+pub fn spawn_shell(
+    commands: &mut Commands,
+    data: &ProjectileData,
+    owner: Option<Entity>,
+    position: Vec3,
+    direction: Vec3,
+) -> Entity {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: data.shell_mesh.clone(),
+            material: data.shell_material.clone(),
+            transform: Transform::from_translation(position),
+            ..Default::default()
+        })
+        .insert(Shell {
+            velocity: direction.normalize_or_zero() * data.shell_speed,
+            owner,
+            lifetime: Timer::from_seconds(data.shell_lifetime, false),
+        })
+        .insert(Name::new("Shell"))
+        .id()
+}
+
+/// Shells that arc, pulled along by gravity and wind, artillery fires these
+#[derive(Debug, Clone, Copy)]
+pub struct Ballistic {
+    /// World units per second squared
+    pub acceleration: Vec3,
+}
+
+/// How tall obstacles are as a fraction of a cell, lobbed shells fly over anything lower
+pub const OBSTACLE_HEIGHT: f32 = 0.5;
+
+/// Has a lobbed shell at this position come down, on the ground, an obstacle or off the map
+pub fn ballistic_hit(layout: &MapLayout, fog: &FogOfWar, position: Vec3) -> bool {
+    if position.y <= 0.0 {
+        return true;
+    }
+    match layout.world_to_cell(position) {
+        Some((x, y)) => {
+            fog.is_blocked(x, y) && position.y < layout.cell_size * OBSTACLE_HEIGHT
+        }
+        None => true,
+    }
+}
+
+fn shell_movement(
+    time: Res<GameTime>,
+    mut query: Query<(&mut Shell, &mut Transform, Option<&Ballistic>)>,
+) {
+    for (mut shell, mut transform, ballistic) in query.iter_mut() {
+        shell.lifetime.tick(time.delta());
+        if let Some(ballistic) = ballistic {
+            shell.velocity += ballistic.acceleration * time.delta_seconds();
+        }
+        transform.translation += shell.velocity * time.delta_seconds();
+    }
+}
+
+/// Shells explode on obstacles, other tanks, the ground, leaving the map or running out of time
+fn shell_impact(
+    mut commands: Commands,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    explosions: Res<ExplosionData>,
+    shells: Query<(Entity, &Shell, &Transform, Option<&Ballistic>)>,
+    tanks: Query<(Entity, &Transform), With<Tank>>,
+    mut ev_explosion: EventWriter<ExplosionEvent>,
+) {
+    let hit_distance = layout.cell_size * 0.3;
+    for (entity, shell, transform, ballistic) in shells.iter() {
+        let position = transform.translation;
+
+        let hit_cell = if ballistic.is_some() {
+            ballistic_hit(&layout, &fog, position)
+        } else {
+            match layout.world_to_cell(position) {
+                Some((x, y)) => fog.is_blocked(x, y),
+                None => true,
+            }
+        };
+        let hit_tank = tanks.iter().any(|(tank, tank_transform)| {
+            Some(tank) != shell.owner && tank_transform.translation.distance(position) < hit_distance
+        });
+
+        if hit_cell || hit_tank || shell.lifetime.finished() {
+            ev_explosion.send(explosions.explosion(
+                ExplosionSource::Shell,
+                position,
+                shell.owner,
+                &layout,
+            ));
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Window},
+    EguiContext,
+};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::{editor::GameTime, ConfigPath, GameStages, GameState};
+
+use super::{
+    minesweeper::{Board, Difficulty, MinesweeperSettings, MinesweeperStatus, RevealEvent, START},
+    topology::GridShape,
+};
+
+/// Best times per difficulty and the daily challenge, kept under the config path
+pub struct RecordsPlugin {
+    state: GameState,
+}
+
+impl RecordsPlugin {
+    pub fn new(state: GameState) -> Self {
+        RecordsPlugin { state: state }
+    }
+}
+
+impl Plugin for RecordsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(MinesweeperClock::default())
+            .insert_resource(DailyChallenge::default())
+            .insert_resource(RecordsWindow::default())
+            .add_startup_system(load_records.system())
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone()).with_system(start_clock.system()),
+            )
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(run_clock.system().after("minesweeper_count")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone()).with_system(end_challenge.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(draw_records.system()),
+            );
+    }
+}
+
+/// Everyone gets the same board on the same day
+pub const DAILY_BOARD: Board = Board {
+    width: 16,
+    height: 16,
+    mines: 40,
+    shape: GridShape::Square,
+};
+
+/// Fastest times kept for each difficulty
+const LEADERBOARD_SIZE: usize = 10;
+
+/// Calendar day in UTC, so the whole team agrees on what today is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Date::from_days((seconds / 86400) as i64)
+    }
+
+    /// Days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date {
+            year: year as i32,
+            month,
+            day,
+        }
+    }
+
+    /// Seed for the day's board, FNV-1a over the date so it doesn't change between builds
+    pub fn seed(&self) -> u64 {
+        self.to_string().bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Set from the menu to play today's board, cleared when the game ends
+#[derive(Debug, Default)]
+pub struct DailyChallenge {
+    pub date: Option<Date>,
+}
+
+impl DailyChallenge {
+    /// Board and no guess setting for the next game
+    pub fn board(&self, settings: &MinesweeperSettings) -> (Board, bool) {
+        match self.date {
+            Some(_) => (DAILY_BOARD, true),
+            None => (settings.board(), settings.no_guess),
+        }
+    }
+}
+
+/// Starts with the first cell uncovered after the free starting corner, stops when the board
+/// is cleared
+#[derive(Debug, Default)]
+pub struct MinesweeperClock {
+    pub started: bool,
+    pub finished: bool,
+    pub elapsed: f32,
+    /// Resumed games don't count towards records
+    pub ranked: bool,
+    difficulty: Option<Difficulty>,
+    daily: Option<Date>,
+    no_guess: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub seconds: f32,
+    pub date: Date,
+    pub no_guess: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Records {
+    /// Fastest first, custom boards vary too much to compare
+    pub best: BTreeMap<Difficulty, Vec<Record>>,
+    pub daily: BTreeMap<Date, f32>,
+}
+
+impl Records {
+    /// Adds a time, returns where it placed if it made the leaderboard
+    pub fn add(&mut self, difficulty: Difficulty, record: Record) -> Option<usize> {
+        let times = self.best.entry(difficulty).or_default();
+        let place = times
+            .iter()
+            .position(|r| record.seconds < r.seconds)
+            .unwrap_or(times.len());
+        if place >= LEADERBOARD_SIZE {
+            return None;
+        }
+        times.insert(place, record);
+        times.truncate(LEADERBOARD_SIZE);
+        Some(place)
+    }
+
+    /// Keeps the best time for the day, returns true if this beat it
+    pub fn add_daily(&mut self, date: Date, seconds: f32) -> bool {
+        let best = self.daily.entry(date).or_insert(f32::MAX);
+        if seconds < *best {
+            *best = seconds;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub fn records_path(config: &ConfigPath) -> PathBuf {
+    [
+        config.path.clone(),
+        "tanks".to_string(),
+        "records.ron".to_string(),
+    ]
+    .iter()
+    .collect()
+}
+
+pub fn write_records(config: &ConfigPath, records: &Records) -> Result<()> {
+    let path = records_path(config);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, to_string_pretty(records, PrettyConfig::new())?)?;
+    Ok(())
+}
+
+pub fn read_records(config: &ConfigPath) -> Result<Records> {
+    let text = std::fs::read_to_string(records_path(config))?;
+    Ok(ron::de::from_str(&text)?)
+}
+
+fn load_records(mut commands: Commands, config: Res<ConfigPath>) {
+    let records = match read_records(&config) {
+        Ok(records) => records,
+        Err(e) => {
+            println!("Failed to load records: {}", e);
+            Records::default()
+        }
+    };
+    commands.insert_resource(records);
+}
+
+fn start_clock(
+    settings: Res<MinesweeperSettings>,
+    daily: Res<DailyChallenge>,
+    mut clock: ResMut<MinesweeperClock>,
+) {
+    let (_, no_guess) = daily.board(&settings);
+    let difficulty = match (daily.date, settings.difficulty) {
+        // custom boards vary too much to compare
+        (None, Difficulty::Custom) | (Some(_), _) => None,
+        (None, difficulty) => Some(difficulty),
+    };
+    *clock = MinesweeperClock {
+        ranked: true,
+        difficulty,
+        daily: daily.date,
+        no_guess,
+        ..Default::default()
+    };
+}
+
+fn run_clock(
+    time: Res<GameTime>,
+    config: Res<ConfigPath>,
+    status: Res<MinesweeperStatus>,
+    mut clock: ResMut<MinesweeperClock>,
+    mut records: ResMut<Records>,
+    mut ev_reveal: EventReader<RevealEvent>,
+) {
+    for event in ev_reveal.iter() {
+        if event.origin != START {
+            clock.started = true;
+        }
+    }
+    if !clock.started || clock.finished {
+        return;
+    }
+
+    clock.elapsed += time.delta_seconds();
+    if status.hidden > 0 {
+        return;
+    }
+
+    clock.finished = true;
+    if !clock.ranked {
+        return;
+    }
+    let today = Date::today();
+    let placed = match (clock.daily, clock.difficulty) {
+        (Some(date), _) => records.add_daily(date, clock.elapsed),
+        (None, Some(difficulty)) => records
+            .add(
+                difficulty,
+                Record {
+                    seconds: clock.elapsed,
+                    date: today,
+                    no_guess: clock.no_guess,
+                },
+            )
+            .is_some(),
+        (None, None) => false,
+    };
+    info!("board cleared in {:.1} seconds", clock.elapsed);
+    if placed {
+        if let Err(e) = write_records(&config, &records) {
+            println!("Failed to save records: {}", e);
+        }
+    }
+}
+
+fn end_challenge(mut daily: ResMut<DailyChallenge>) {
+    daily.date = None;
+}
+
+#[derive(Debug, Default)]
+pub struct RecordsWindow {
+    pub open: bool,
+}
+
+fn draw_records(
+    egui_ctx: Res<EguiContext>,
+    records: Res<Records>,
+    mut window: ResMut<RecordsWindow>,
+) {
+    let today = Date::today();
+    Window::new("Records")
+        .open(&mut window.open)
+        .resizable(false)
+        .show(egui_ctx.ctx(), |ui| {
+            for difficulty in [
+                Difficulty::Beginner,
+                Difficulty::Intermediate,
+                Difficulty::Expert,
+            ]
+            .iter()
+            {
+                ui.heading(difficulty.to_string());
+                let times = records.best.get(difficulty).map(|t| t.as_slice()).unwrap_or(&[]);
+                if times.is_empty() {
+                    ui.label("No times yet");
+                }
+                egui::Grid::new(format!("records_{}", difficulty))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, record) in times.iter().enumerate() {
+                            ui.label(format!("{}.", i + 1));
+                            ui.label(format!("{:.1}s", record.seconds));
+                            ui.label(record.date.to_string());
+                            ui.label(if record.no_guess { "no guess" } else { "" });
+                            ui.end_row();
+                        }
+                    });
+            }
+
+            ui.separator();
+            ui.heading("Daily Challenge");
+            match records.daily.get(&today) {
+                Some(seconds) => ui.label(format!("Today {:.1}s", seconds)),
+                None => ui.label("Not cleared today"),
+            };
+            for (date, seconds) in records.daily.iter().rev().filter(|(d, _)| **d != today).take(6) {
+                ui.label(format!("{} {:.1}s", date, seconds));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_from_days() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20745).to_string(), "2026-10-19");
+    }
+
+    #[test]
+    fn daily_seed_is_stable() {
+        let date = Date {
+            year: 2026,
+            month: 10,
+            day: 19,
+        };
+        assert_eq!(date.seed(), Date::from_days(20745).seed());
+        assert_ne!(date.seed(), Date::from_days(20746).seed());
+    }
+
+    #[test]
+    fn leaderboard_keeps_fastest() {
+        let date = Date::from_days(0);
+        let mut records = Records::default();
+        for seconds in 0..LEADERBOARD_SIZE + 2 {
+            records.add(
+                Difficulty::Expert,
+                Record {
+                    seconds: 100.0 - seconds as f32,
+                    date,
+                    no_guess: false,
+                },
+            );
+        }
+        let times = &records.best[&Difficulty::Expert];
+        assert_eq!(times.len(), LEADERBOARD_SIZE);
+        assert_eq!(times[0].seconds, 100.0 - (LEADERBOARD_SIZE + 1) as f32);
+        assert!(records
+            .add(
+                Difficulty::Expert,
+                Record {
+                    seconds: 500.0,
+                    date,
+                    no_guess: false,
+                },
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut records = Records::default();
+        records.add(
+            Difficulty::Beginner,
+            Record {
+                seconds: 12.5,
+                date: Date::from_days(20745),
+                no_guess: true,
+            },
+        );
+        records.add_daily(Date::from_days(20745), 40.0);
+        let text = to_string_pretty(&records, PrettyConfig::new()).unwrap();
+        assert_eq!(ron::de::from_str::<Records>(&text).unwrap(), records);
+    }
+}
@@ -0,0 +1,1102 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf, time::Duration};
+
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Window},
+    EguiContext,
+};
+use bevy_input_actionmap::{ActionPlugin, InputMap};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::cleanup_actions_system, ConfigPath, GameState};
+
+use super::{
+    artillery::{Artillery, TurnPhase},
+    health::{Health, Invulnerable, PlayerRecord, Respawns, Scoreboard},
+    map::{MapCells, MapLayout, MapSeed, MineStatus},
+    projectile::{spawn_shell, Ballistic, ProjectileData, Shell},
+    records::MinesweeperClock,
+    streaming::{unload_chunk, CellStore, LoadedChunks, StoredCell, Terrain},
+    tank::{spawn_point, spawn_tank, PlayerId, SpawnPoint, Tank, TankData, Team},
+    topology::GridShape,
+    visibility::FogOfWar,
+    SessionClock, TanksData, TanksMode,
+};
+
+/// Bump this when the save format changes, and add a migration from the old version to
+/// `SaveGame::from_ron`, new fields need `#[serde(default)]` so older saves still read
+///
+/// 1. first version
+/// 2. hex maps, artillery shells and chain reactions waiting to go off
+/// 3. game mode, artillery turns and the minesweeper clock
+pub const SAVE_VERSION: u32 = 3;
+
+pub const SAVE_SLOTS: u32 = 5;
+
+pub struct SavePlugin {
+    state: GameState,
+}
+
+impl SavePlugin {
+    pub fn new(state: GameState) -> Self {
+        SavePlugin { state: state }
+    }
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(ActionPlugin::<SaveAction>::default())
+            .add_event::<SaveGameEvent>()
+            .add_event::<LoadGameEvent>()
+            .insert_resource(PendingLoad::default())
+            .insert_resource(SaveSlots::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(refresh_slots.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone())
+                    .with_system(setup_save_actions.system())
+                    .with_system(refresh_slots.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(save_actions.system().label("save_actions"))
+                    .with_system(draw_saves_window.system().label("save_actions"))
+                    .with_system(save_game.system().after("save_actions"))
+                    .with_system(load_game.system().after("save_actions"))
+                    .with_system(apply_pending_load.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone())
+                    .with_system(cleanup_actions_system::<SaveAction>.system()),
+            );
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SaveAction {
+    QuickSave,
+    QuickLoad,
+    Saves,
+}
+
+impl fmt::Display for SaveAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveAction::QuickSave => write!(f, "Quick Save"),
+            SaveAction::QuickLoad => write!(f, "Quick Load"),
+            SaveAction::Saves => write!(f, "Toggle Saves"),
+        }
+    }
+}
+
+fn setup_save_actions(mut input: ResMut<InputMap<SaveAction>>) {
+    input
+        .bind(SaveAction::QuickSave, KeyCode::F5)
+        .bind(SaveAction::QuickLoad, KeyCode::F9)
+        .bind(SaveAction::Saves, KeyCode::F6);
+}
+
+pub struct SaveGameEvent {
+    pub slot: u32,
+}
+
+pub struct LoadGameEvent {
+    pub slot: u32,
+}
+
+/// Save waiting to be applied, the menu uses this to resume a game
+#[derive(Default)]
+pub struct PendingLoad(pub Option<SaveGame>);
+
+/// Whole Tanks session, everything needed to pick up exactly where we left off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub seed: u64,
+    /// Seconds played
+    pub elapsed: f32,
+    pub map: SavedMap,
+    pub spawn_points: Vec<SavedSpawnPoint>,
+    pub tanks: Vec<SavedTank>,
+    pub shells: Vec<SavedShell>,
+    pub scoreboard: BTreeMap<PlayerId, PlayerRecord>,
+    pub respawns: Vec<SavedRespawn>,
+    #[serde(default)]
+    pub mode: TanksMode,
+    #[serde(default)]
+    pub artillery: SavedArtillery,
+    #[serde(default)]
+    pub minesweeper: SavedMinesweeperClock,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMap {
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: f32,
+    /// Saves from before hex maps are all square
+    #[serde(default)]
+    pub shape: GridShape,
+    /// Row by row, starting bottom left
+    pub cells: Vec<SavedCell>,
+    /// Cells the friendly team has seen, what is visible right now is worked out again on load
+    pub explored: Vec<bool>,
+    /// Mines caught in a chain reaction that haven't gone off yet
+    #[serde(default)]
+    pub pending: Vec<SavedDetonation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SavedDetonation {
+    pub x: u32,
+    pub y: u32,
+    /// Seconds left
+    pub remaining: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SavedCell {
+    pub status: MineStatus,
+    pub terrain: Terrain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSpawnPoint {
+    pub player: PlayerId,
+    pub team: Team,
+    pub translation: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTank {
+    pub player: PlayerId,
+    pub team: Team,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub health: f32,
+    pub max_health: f32,
+    pub armor: f32,
+    /// Seconds since the last shot
+    pub reload: f32,
+    /// Seconds of invulnerability left
+    pub invulnerable: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedShell {
+    pub owner: Option<PlayerId>,
+    pub translation: Vec3,
+    pub velocity: Vec3,
+    pub lifetime: f32,
+    /// Seconds already in flight
+    pub elapsed: f32,
+    /// Gravity and wind on artillery shells
+    #[serde(default)]
+    pub acceleration: Option<Vec3>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedArtillery {
+    pub player: Option<PlayerId>,
+    pub turn: u32,
+    pub wind: Vec3,
+    pub phase: SavedTurnPhase,
+}
+
+impl Default for SavedArtillery {
+    fn default() -> Self {
+        SavedArtillery::capture(&Artillery::default(), |_| None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SavedTurnPhase {
+    Aiming,
+    /// Index into the saved shells
+    InFlight {
+        shell: usize,
+    },
+    /// Seconds left
+    Settling {
+        remaining: f32,
+    },
+}
+
+impl SavedArtillery {
+    /// `shell_index` finds where a shell went in the saved shells
+    pub fn capture(artillery: &Artillery, shell_index: impl Fn(Entity) -> Option<usize>) -> Self {
+        let phase = match &artillery.phase {
+            TurnPhase::Aiming => SavedTurnPhase::Aiming,
+            TurnPhase::InFlight(shell) => match shell_index(*shell) {
+                Some(shell) => SavedTurnPhase::InFlight { shell },
+                // the shell is already gone, it comes down this frame
+                None => SavedTurnPhase::Settling { remaining: 0.0 },
+            },
+            TurnPhase::Settling(timer) => SavedTurnPhase::Settling {
+                remaining: timer.duration().as_secs_f32() - timer.elapsed_secs(),
+            },
+        };
+        SavedArtillery {
+            player: artillery.player,
+            turn: artillery.turn,
+            wind: artillery.wind,
+            phase,
+        }
+    }
+
+    /// `shells` are the shells spawned for the save, in the same order
+    pub fn restore(&self, artillery: &mut Artillery, shells: &[Entity]) {
+        artillery.player = self.player;
+        artillery.turn = self.turn;
+        artillery.wind = self.wind;
+        artillery.phase = match self.phase {
+            SavedTurnPhase::Aiming => TurnPhase::Aiming,
+            SavedTurnPhase::InFlight { shell } => match shells.get(shell) {
+                Some(shell) => TurnPhase::InFlight(*shell),
+                None => TurnPhase::Settling(Timer::from_seconds(0.0, false)),
+            },
+            SavedTurnPhase::Settling { remaining } => {
+                TurnPhase::Settling(Timer::from_seconds(remaining, false))
+            }
+        };
+    }
+}
+
+/// Whether it is ranked is left out, a resumed board never is
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMinesweeperClock {
+    pub started: bool,
+    pub finished: bool,
+    pub elapsed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedRespawn {
+    pub player: PlayerId,
+    pub team: Team,
+    /// Seconds left
+    pub remaining: f32,
+}
+
+/// Just enough to find out which version a save is before reading the rest
+#[derive(Deserialize)]
+#[serde(rename = "SaveGame")]
+struct SaveHeader {
+    version: u32,
+}
+
+impl SaveGame {
+    pub fn to_ron(&self) -> Result<String> {
+        let pretty = PrettyConfig::new()
+            .with_depth_limit(2)
+            .with_separate_tuple_members(true);
+        Ok(to_string_pretty(self, pretty)?)
+    }
+
+    /// Reads a save of any version we know about, migrating older ones to the current format
+    pub fn from_ron(text: &str) -> Result<SaveGame> {
+        let header: SaveHeader = ron::de::from_str(text)?;
+        let save: SaveGame = match header.version {
+            SAVE_VERSION => ron::de::from_str(text)?,
+            // older versions get upgraded here, one version at a time
+            1 => from_v2(from_v1(ron::de::from_str(text)?)),
+            2 => from_v2(ron::de::from_str(text)?),
+            version if version > SAVE_VERSION => bail!(
+                "save version {} is newer than this build supports ({})",
+                version,
+                SAVE_VERSION
+            ),
+            version => bail!("no migration from save version {}", version),
+        };
+
+        let count = (save.map.width * save.map.height) as usize;
+        if save.map.cells.len() != count || save.map.explored.len() != count {
+            bail!(
+                "save map is {}x{} but has {} cells",
+                save.map.width,
+                save.map.height,
+                save.map.cells.len()
+            );
+        }
+        Ok(save)
+    }
+}
+
+/// Version 1 only had square maps, straight shells and no pending chain reactions, which is
+/// exactly what the defaults for the fields added in version 2 give us
+fn from_v1(save: SaveGame) -> SaveGame {
+    SaveGame { version: 2, ..save }
+}
+
+/// Version 2 games were all real time, so the defaults for artillery turns do, and the
+/// minesweeper clock starts again from nothing
+fn from_v2(save: SaveGame) -> SaveGame {
+    SaveGame {
+        version: SAVE_VERSION,
+        ..save
+    }
+}
+
+impl SavedMap {
+    pub fn capture(layout: &MapLayout, store: &CellStore, fog: &FogOfWar) -> Self {
+        let mut cells = Vec::with_capacity((store.width() * store.height()) as usize);
+        let mut explored = Vec::with_capacity(cells.capacity());
+        for y in 0..store.height() {
+            for x in 0..store.width() {
+                let cell = store.get(x, y).expect("cell inside the store");
+                cells.push(SavedCell {
+                    status: cell.status(),
+                    terrain: cell.terrain(),
+                });
+                explored.push(fog.is_explored(x, y));
+            }
+        }
+
+        SavedMap {
+            width: store.width(),
+            height: store.height(),
+            cell_size: layout.cell_size,
+            shape: layout.shape,
+            cells,
+            explored,
+            pending: store
+                .pending()
+                .map(|((x, y), remaining)| SavedDetonation { x, y, remaining })
+                .collect(),
+        }
+    }
+
+    pub fn restore(&self, layout: &mut MapLayout, store: &mut CellStore, fog: &mut FogOfWar) {
+        layout.width = self.width;
+        layout.height = self.height;
+        layout.cell_size = self.cell_size;
+        layout.shape = self.shape;
+        *store = CellStore::new(self.width, self.height);
+        *fog = FogOfWar::new(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = (y * self.width + x) as usize;
+                let cell = self.cells[i];
+                store.set(x, y, StoredCell::new(cell.status, cell.terrain));
+                if self.explored[i] {
+                    fog.explore(x, y);
+                }
+            }
+        }
+        for detonation in self.pending.iter() {
+            store.arm(detonation.x, detonation.y, detonation.remaining);
+        }
+    }
+}
+
+pub fn slot_path(config: &ConfigPath, slot: u32) -> PathBuf {
+    [
+        config.path.clone(),
+        "tanks".to_string(),
+        format!("save_{}.ron", slot),
+    ]
+    .iter()
+    .collect()
+}
+
+pub fn write_slot(config: &ConfigPath, slot: u32, save: &SaveGame) -> Result<()> {
+    let path = slot_path(config, slot);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, save.to_ron()?)?;
+    Ok(())
+}
+
+pub fn read_slot(config: &ConfigPath, slot: u32) -> Result<SaveGame> {
+    let text = std::fs::read_to_string(slot_path(config, slot))?;
+    SaveGame::from_ron(&text)
+}
+
+/// What is in each slot, for the saves window and the menu
+#[derive(Debug, Default)]
+pub struct SaveSlots {
+    pub open: bool,
+    pub slots: BTreeMap<u32, SlotSummary>,
+    /// Result of the last save or load
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotSummary {
+    pub width: u32,
+    pub height: u32,
+    pub elapsed: f32,
+}
+
+impl fmt::Display for SlotSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed as u32;
+        write!(
+            f,
+            "{}x{} - {}:{:02}",
+            self.width,
+            self.height,
+            seconds / 60,
+            seconds % 60
+        )
+    }
+}
+
+pub fn refresh_slots(config: Res<ConfigPath>, mut slots: ResMut<SaveSlots>) {
+    slots.slots.clear();
+    for slot in 1..=SAVE_SLOTS {
+        if let Ok(save) = read_slot(&config, slot) {
+            slots.slots.insert(
+                slot,
+                SlotSummary {
+                    width: save.map.width,
+                    height: save.map.height,
+                    elapsed: save.elapsed,
+                },
+            );
+        }
+    }
+}
+
+fn save_actions(
+    input: Res<InputMap<SaveAction>>,
+    mut slots: ResMut<SaveSlots>,
+    mut ev_save: EventWriter<SaveGameEvent>,
+    mut ev_load: EventWriter<LoadGameEvent>,
+) {
+    // quick save always uses the first slot
+    if input.just_active(SaveAction::QuickSave) {
+        ev_save.send(SaveGameEvent { slot: 1 });
+    }
+    if input.just_active(SaveAction::QuickLoad) {
+        ev_load.send(LoadGameEvent { slot: 1 });
+    }
+    if input.just_active(SaveAction::Saves) {
+        slots.open = !slots.open;
+    }
+}
+
+fn draw_saves_window(
+    egui_ctx: Res<EguiContext>,
+    mut slots: ResMut<SaveSlots>,
+    mut ev_save: EventWriter<SaveGameEvent>,
+    mut ev_load: EventWriter<LoadGameEvent>,
+) {
+    let mut open = slots.open;
+    Window::new("Saves")
+        .open(&mut open)
+        .resizable(false)
+        .show(egui_ctx.ctx(), |ui| {
+            egui::Grid::new("tanks_saves").striped(true).show(ui, |ui| {
+                for slot in 1..=SAVE_SLOTS {
+                    ui.label(format!("Slot {}", slot));
+                    match slots.slots.get(&slot) {
+                        Some(summary) => ui.label(summary.to_string()),
+                        None => ui.label("Empty"),
+                    };
+                    if ui.button("Save").clicked() {
+                        ev_save.send(SaveGameEvent { slot });
+                    }
+                    let saved = slots.slots.contains_key(&slot);
+                    ui.scope(|ui| {
+                        ui.set_enabled(saved);
+                        if ui.button("Load").clicked() {
+                            ev_load.send(LoadGameEvent { slot });
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+            if let Some(message) = &slots.message {
+                ui.label(message);
+            }
+        });
+    slots.open = open;
+}
+
+fn save_game(
+    mut ev_save: EventReader<SaveGameEvent>,
+    config: Res<ConfigPath>,
+    seed: Res<MapSeed>,
+    clock: Res<SessionClock>,
+    tanks_data: Res<TanksData>,
+    artillery: Res<Artillery>,
+    minesweeper_clock: Res<MinesweeperClock>,
+    layout: Res<MapLayout>,
+    store: Res<CellStore>,
+    fog: Res<FogOfWar>,
+    scoreboard: Res<Scoreboard>,
+    respawns: Res<Respawns>,
+    mut slots: ResMut<SaveSlots>,
+    spawn_points: Query<(&SpawnPoint, &Transform)>,
+    tanks: Query<(&Tank, &Transform, &Health, Option<&Invulnerable>)>,
+    shells: Query<(Entity, &Shell, &Transform, Option<&Ballistic>)>,
+) {
+    for event in ev_save.iter() {
+        let save = SaveGame {
+            version: SAVE_VERSION,
+            seed: seed.current,
+            elapsed: clock.elapsed,
+            map: SavedMap::capture(&layout, &store, &fog),
+            spawn_points: spawn_points
+                .iter()
+                .map(|(point, transform)| SavedSpawnPoint {
+                    player: point.player,
+                    team: point.team,
+                    translation: transform.translation,
+                })
+                .collect(),
+            tanks: tanks
+                .iter()
+                .map(|(tank, transform, health, invulnerable)| SavedTank {
+                    player: tank.player,
+                    team: tank.team,
+                    translation: transform.translation,
+                    rotation: transform.rotation,
+                    scale: transform.scale,
+                    health: health.current,
+                    max_health: health.max,
+                    armor: health.armor,
+                    reload: tank.reload.elapsed_secs(),
+                    invulnerable: invulnerable.map(|i| i.remaining()),
+                })
+                .collect(),
+            shells: shells
+                .iter()
+                .map(|(_, shell, transform, ballistic)| SavedShell {
+                    owner: shell
+                        .owner
+                        .and_then(|owner| tanks.get(owner).ok())
+                        .map(|(tank, ..)| tank.player),
+                    translation: transform.translation,
+                    velocity: shell.velocity,
+                    lifetime: shell.lifetime.duration().as_secs_f32(),
+                    elapsed: shell.lifetime.elapsed_secs(),
+                    acceleration: ballistic.map(|b| b.acceleration),
+                })
+                .collect(),
+            scoreboard: scoreboard.players.clone(),
+            respawns: respawns
+                .pending()
+                .map(|(player, team, remaining)| SavedRespawn {
+                    player,
+                    team,
+                    remaining,
+                })
+                .collect(),
+            mode: tanks_data.mode,
+            artillery: SavedArtillery::capture(&artillery, |shell| {
+                shells.iter().position(|(entity, ..)| entity == shell)
+            }),
+            minesweeper: SavedMinesweeperClock {
+                started: minesweeper_clock.started,
+                finished: minesweeper_clock.finished,
+                elapsed: minesweeper_clock.elapsed,
+            },
+        };
+
+        match write_slot(&config, event.slot, &save) {
+            Ok(_) => {
+                slots.message = Some(format!("Saved slot {}", event.slot));
+                slots.slots.insert(
+                    event.slot,
+                    SlotSummary {
+                        width: save.map.width,
+                        height: save.map.height,
+                        elapsed: save.elapsed,
+                    },
+                );
+            }
+            Err(e) => {
+                println!("Failed to save slot {}: {}", event.slot, e);
+                slots.message = Some(format!("Failed to save slot {}: {}", event.slot, e));
+            }
+        }
+    }
+}
+
+fn load_game(
+    mut ev_load: EventReader<LoadGameEvent>,
+    config: Res<ConfigPath>,
+    mut pending: ResMut<PendingLoad>,
+    mut slots: ResMut<SaveSlots>,
+) {
+    for event in ev_load.iter() {
+        match read_slot(&config, event.slot) {
+            Ok(save) => {
+                pending.0 = Some(save);
+                slots.message = Some(format!("Loaded slot {}", event.slot));
+            }
+            Err(e) => {
+                println!("Failed to load slot {}: {}", event.slot, e);
+                slots.message = Some(format!("Failed to load slot {}: {}", event.slot, e));
+            }
+        }
+    }
+}
+
+/// Replace the running game with a save, chunks reload from the restored store on their own
+fn apply_pending_load(
+    mut commands: Commands,
+    mut pending: ResMut<PendingLoad>,
+    tank_data: Res<TankData>,
+    mut tanks_data: ResMut<TanksData>,
+    projectile_data: Res<ProjectileData>,
+    mut layout: ResMut<MapLayout>,
+    mut seed: ResMut<MapSeed>,
+    mut clock: ResMut<SessionClock>,
+    mut store: ResMut<CellStore>,
+    mut fog: ResMut<FogOfWar>,
+    mut cells: ResMut<MapCells>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut respawns: ResMut<Respawns>,
+    mut minesweeper_clock: ResMut<MinesweeperClock>,
+    mut artillery: ResMut<Artillery>,
+    existing: Query<Entity, Or<(With<Tank>, With<Shell>, With<SpawnPoint>)>>,
+) {
+    let save = match pending.0.take() {
+        Some(save) => save,
+        None => return,
+    };
+
+    for (chunk, entity) in loaded.chunks.drain() {
+        unload_chunk(&mut commands, &layout, &mut cells, chunk, entity);
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    save.map.restore(&mut layout, &mut store, &mut fog);
+    *cells = MapCells::new(layout.width, layout.height);
+    seed.current = save.seed;
+    clock.elapsed = save.elapsed;
+    tanks_data.mode = save.mode;
+    minesweeper_clock.started = save.minesweeper.started;
+    minesweeper_clock.finished = save.minesweeper.finished;
+    minesweeper_clock.elapsed = save.minesweeper.elapsed;
+    // the board was part played already, so it can't set a record
+    minesweeper_clock.ranked = false;
+    scoreboard.players = save.scoreboard.clone();
+    respawns.clear();
+    for respawn in save.respawns.iter() {
+        respawns.push(respawn.player, respawn.team, respawn.remaining);
+    }
+
+    for point in save.spawn_points.iter() {
+        spawn_point(
+            &mut commands,
+            point.player,
+            point.team,
+            Transform::from_translation(point.translation),
+        );
+    }
+
+    let mut owners = BTreeMap::new();
+    for saved in save.tanks.iter() {
+        let transform = Transform {
+            translation: saved.translation,
+            rotation: saved.rotation,
+            scale: saved.scale,
+        };
+        let entity = spawn_tank(
+            &mut commands,
+            &tank_data,
+            &tanks_data,
+            &layout,
+            saved.team,
+            saved.player,
+            transform,
+        );
+
+        let mut reload = Timer::from_seconds(tank_data.reload_time, false);
+        reload.tick(Duration::from_secs_f32(saved.reload));
+        commands
+            .entity(entity)
+            .insert(transform)
+            .insert(Tank {
+                team: saved.team,
+                player: saved.player,
+                reload,
+            })
+            .insert(Health {
+                current: saved.health,
+                max: saved.max_health,
+                armor: saved.armor,
+            });
+        if let Some(remaining) = saved.invulnerable {
+            commands.entity(entity).insert(Invulnerable::new(remaining));
+        }
+        owners.insert(saved.player, entity);
+    }
+
+    let mut shells = Vec::with_capacity(save.shells.len());
+    for saved in save.shells.iter() {
+        let owner = saved.owner.and_then(|player| owners.get(&player).copied());
+        let entity = spawn_shell(
+            &mut commands,
+            &projectile_data,
+            owner,
+            saved.translation,
+            saved.velocity,
+        );
+
+        let mut lifetime = Timer::from_seconds(saved.lifetime, false);
+        lifetime.tick(Duration::from_secs_f32(saved.elapsed));
+        commands.entity(entity).insert(Shell {
+            velocity: saved.velocity,
+            owner,
+            lifetime,
+        });
+        if let Some(acceleration) = saved.acceleration {
+            commands.entity(entity).insert(Ballistic { acceleration });
+        }
+        shells.push(entity);
+    }
+    save.artillery.restore(&mut artillery, &shells);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tanks::streaming::ObstacleKind;
+
+    fn sample_save() -> SaveGame {
+        let layout = MapLayout {
+            width: 3,
+            height: 2,
+            shape: GridShape::Hex,
+            ..Default::default()
+        };
+        let mut store = CellStore::new(layout.width, layout.height);
+        store.set(0, 0, StoredCell::new(MineStatus::Count { count: 2 }, Terrain::Open));
+        store.set(1, 0, StoredCell::new(MineStatus::Unknown { mine: true }, Terrain::Open));
+        store.set(2, 0, StoredCell::new(MineStatus::Marked, Terrain::Rubble));
+        store.set(
+            0,
+            1,
+            StoredCell::new(
+                MineStatus::Unknown { mine: false },
+                Terrain::Obstacle(ObstacleKind::Wall),
+            ),
+        );
+        store.set(1, 1, StoredCell::new(MineStatus::Exploded, Terrain::Open));
+        store.arm(1, 0, 0.08);
+        let mut fog = FogOfWar::new(layout.width, layout.height);
+        fog.explore(0, 0);
+        fog.explore(2, 1);
+
+        let mut scoreboard = BTreeMap::new();
+        scoreboard.insert(
+            PlayerId(0),
+            PlayerRecord {
+                team: Some(Team::Friendly),
+                lives: 2,
+                score: 100,
+                deaths: 1,
+            },
+        );
+
+        SaveGame {
+            version: SAVE_VERSION,
+            seed: 0xdead_beef,
+            elapsed: 93.25,
+            map: SavedMap::capture(&layout, &store, &fog),
+            spawn_points: vec![SavedSpawnPoint {
+                player: PlayerId(0),
+                team: Team::Friendly,
+                translation: Vec3::new(-256.0, 0.0, -128.0),
+            }],
+            tanks: vec![SavedTank {
+                player: PlayerId(0),
+                team: Team::Friendly,
+                translation: Vec3::new(1.5, 38.4, -7.25),
+                rotation: Quat::from_rotation_y(0.3),
+                scale: Vec3::splat(153.6),
+                health: 61.3,
+                max_health: 100.0,
+                armor: 0.1,
+                reload: 0.2,
+                invulnerable: Some(0.4),
+            }],
+            shells: vec![SavedShell {
+                owner: Some(PlayerId(0)),
+                translation: Vec3::new(10.0, 38.4, 20.0),
+                velocity: Vec3::new(0.0, 0.0, 1500.0),
+                lifetime: 2.0,
+                elapsed: 0.7,
+                acceleration: Some(Vec3::new(120.0, -1500.0, 0.0)),
+            }],
+            scoreboard,
+            respawns: vec![SavedRespawn {
+                player: PlayerId(1),
+                team: Team::Enemy,
+                remaining: 1.5,
+            }],
+            mode: TanksMode::Artillery,
+            artillery: SavedArtillery {
+                player: Some(PlayerId(0)),
+                turn: 4,
+                wind: Vec3::new(30.0, 0.0, -40.0),
+                phase: SavedTurnPhase::InFlight { shell: 0 },
+            },
+            minesweeper: SavedMinesweeperClock {
+                started: true,
+                finished: false,
+                elapsed: 41.5,
+            },
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let save = sample_save();
+        let text = save.to_ron().unwrap();
+        assert_eq!(SaveGame::from_ron(&text).unwrap(), save);
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let save = sample_save();
+        let mut layout = MapLayout::default();
+        let mut store = CellStore::default();
+        let mut fog = FogOfWar::default();
+        save.map.restore(&mut layout, &mut store, &mut fog);
+
+        assert_eq!(layout.width, 3);
+        assert_eq!(layout.height, 2);
+        assert_eq!(SavedMap::capture(&layout, &store, &fog), save.map);
+        assert!(fog.is_explored(0, 0));
+        assert!(!fog.is_explored(1, 0));
+        // the chain reaction carries on where it left off
+        assert_eq!(store.pending().collect::<Vec<_>>(), vec![((1, 0), 0.08)]);
+    }
+
+    fn temp_config(name: &str) -> ConfigPath {
+        ConfigPath {
+            path: std::env::temp_dir()
+                .join(format!("{}_{}", name, std::process::id()))
+                .into_os_string()
+                .into_string()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let config = temp_config("tanks_save_test");
+        let save = sample_save();
+        write_slot(&config, 3, &save).unwrap();
+        let loaded = read_slot(&config, 3);
+        let _ = std::fs::remove_dir_all(&config.path);
+        assert_eq!(loaded.unwrap(), save);
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let text = r#"(
+    version: 1,
+    seed: 42,
+    elapsed: 12.5,
+    map: (
+        width: 2,
+        height: 1,
+        cell_size: 256.0,
+        cells: [
+            (status: Count(count: 1), terrain: Open),
+            (status: Unknown(mine: true), terrain: Obstacle(Crate)),
+        ],
+        explored: [true, false],
+    ),
+    spawn_points: [
+        (player: (0), team: Friendly, translation: (-128.0, 0.0, 0.0)),
+    ],
+    tanks: [
+        (
+            player: (0),
+            team: Friendly,
+            translation: (-128.0, 38.4, 0.0),
+            rotation: (0.0, 0.0, 0.0, 1.0),
+            scale: (153.6, 153.6, 153.6),
+            health: 80.0,
+            max_health: 100.0,
+            armor: 0.1,
+            reload: 0.5,
+            invulnerable: None,
+        ),
+    ],
+    shells: [
+        (
+            owner: Some((0)),
+            translation: (0.0, 38.4, 0.0),
+            velocity: (1500.0, 0.0, 0.0),
+            lifetime: 2.0,
+            elapsed: 0.25,
+        ),
+    ],
+    scoreboard: {
+        (0): (team: Some(Friendly), lives: 3, score: 0, deaths: 0),
+    },
+    respawns: [],
+)"#;
+        let save = SaveGame::from_ron(text).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.seed, 42);
+        assert_eq!(save.map.shape, GridShape::Square);
+        assert!(save.map.pending.is_empty());
+        assert_eq!(
+            save.map.cells[1],
+            SavedCell {
+                status: MineStatus::Unknown { mine: true },
+                terrain: Terrain::Obstacle(ObstacleKind::Crate),
+            }
+        );
+        assert_eq!(save.tanks[0].health, 80.0);
+        assert_eq!(save.shells[0].owner, Some(PlayerId(0)));
+        assert_eq!(save.shells[0].acceleration, None);
+        assert_eq!(save.scoreboard[&PlayerId(0)].lives, 3);
+        assert_eq!(save.mode, TanksMode::RealTime);
+        assert_eq!(save.artillery, SavedArtillery::default());
+        assert_eq!(save.minesweeper, SavedMinesweeperClock::default());
+    }
+
+    #[test]
+    fn migrates_version_2() {
+        let mut save = sample_save();
+        save.version = 2;
+        let mut text = save.to_ron().unwrap();
+        // version 2 saves stop at the respawns
+        let end = text.find("    mode:").unwrap();
+        text.replace_range(end..text.len() - 1, "");
+        let migrated = SaveGame::from_ron(&text).unwrap();
+        assert_eq!(migrated.version, SAVE_VERSION);
+        assert_eq!(migrated.mode, TanksMode::RealTime);
+        assert_eq!(migrated.artillery, SavedArtillery::default());
+        assert_eq!(migrated.tanks, save.tanks);
+    }
+
+    #[test]
+    fn artillery_turns_round_trip() {
+        let mut artillery = Artillery {
+            player: Some(PlayerId(1)),
+            turn: 7,
+            wind: Vec3::new(-12.0, 0.0, 80.0),
+            ..Default::default()
+        };
+        let mut timer = Timer::from_seconds(1.0, false);
+        timer.tick(Duration::from_secs_f32(0.25));
+        artillery.phase = TurnPhase::Settling(timer);
+
+        let saved = SavedArtillery::capture(&artillery, |_| None);
+        assert_eq!(saved.phase, SavedTurnPhase::Settling { remaining: 0.75 });
+        let mut restored = Artillery::default();
+        saved.restore(&mut restored, &[]);
+        assert_eq!(SavedArtillery::capture(&restored, |_| None), saved);
+
+        // a shell that already landed ends the turn
+        artillery.phase = TurnPhase::InFlight(Entity::new(3));
+        assert_eq!(
+            SavedArtillery::capture(&artillery, |_| None).phase,
+            SavedTurnPhase::Settling { remaining: 0.0 }
+        );
+    }
+
+    fn game_world(config: ConfigPath) -> World {
+        let mut world = World::default();
+        let tanks_data = TanksData::from_world(&mut world);
+        world.insert_resource(tanks_data);
+        world.insert_resource(TankData {
+            mesh: Handle::default(),
+            friendly: Handle::default(),
+            enemy: Handle::default(),
+            sight_radius: 3,
+            speed: 400.0,
+            turn_speed: 2.0,
+            reload_time: 0.75,
+        });
+        world.insert_resource(ProjectileData {
+            shell_speed: 1500.0,
+            shell_lifetime: 2.0,
+            shell_mesh: Handle::default(),
+            shell_material: Handle::default(),
+        });
+        world.insert_resource(config);
+        world.insert_resource(MapLayout::default());
+        world.insert_resource(MapSeed::default());
+        world.insert_resource(SessionClock::default());
+        world.insert_resource(CellStore::default());
+        world.insert_resource(FogOfWar::default());
+        world.insert_resource(MapCells::default());
+        world.insert_resource(LoadedChunks::default());
+        world.insert_resource(Scoreboard::default());
+        world.insert_resource(Respawns::default());
+        world.insert_resource(MinesweeperClock::default());
+        world.insert_resource(Artillery::default());
+        world.insert_resource(PendingLoad::default());
+        world.insert_resource(SaveSlots::default());
+        world.insert_resource(Events::<SaveGameEvent>::default());
+        world
+    }
+
+    #[test]
+    fn load_then_save_gives_back_the_same_game() {
+        let config = temp_config("tanks_save_systems_test");
+        let mut save = sample_save();
+        // timers go through Duration, keep to times it holds exactly
+        save.tanks[0].reload = 0.25;
+        save.tanks[0].invulnerable = Some(0.5);
+        save.shells[0].elapsed = 0.5;
+
+        let mut world = game_world(temp_config("tanks_save_systems_test"));
+        world.get_resource_mut::<PendingLoad>().unwrap().0 = Some(save.clone());
+        let mut load = SystemStage::single(apply_pending_load.system());
+        load.run(&mut world);
+
+        assert!(world.get_resource::<PendingLoad>().unwrap().0.is_none());
+        assert!(!world.get_resource::<MinesweeperClock>().unwrap().ranked);
+        assert_eq!(
+            world.get_resource::<MapLayout>().unwrap().shape,
+            GridShape::Hex
+        );
+        // the artillery turn carries on with the same shell in the air
+        assert_eq!(
+            world.get_resource::<TanksData>().unwrap().mode,
+            TanksMode::Artillery
+        );
+        let artillery = world.get_resource::<Artillery>().unwrap();
+        assert_eq!(artillery.wind, save.artillery.wind);
+        let shell = match artillery.phase {
+            TurnPhase::InFlight(shell) => shell,
+            ref phase => panic!("not in flight: {:?}", phase),
+        };
+        assert!(world.get::<Ballistic>(shell).is_some());
+
+        world
+            .get_resource_mut::<Events<SaveGameEvent>>()
+            .unwrap()
+            .send(SaveGameEvent { slot: 2 });
+        let mut write = SystemStage::single(save_game.system());
+        write.run(&mut world);
+
+        let saved = read_slot(&config, 2);
+        let _ = std::fs::remove_dir_all(&config.path);
+        assert_eq!(saved.unwrap(), save);
+        let slots = world.get_resource::<SaveSlots>().unwrap();
+        assert_eq!(slots.message.as_deref(), Some("Saved slot 2"));
+        assert!(slots.slots.contains_key(&2));
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut save = sample_save();
+        save.version = SAVE_VERSION + 1;
+        let text = save.to_ron().unwrap();
+        assert!(SaveGame::from_ron(&text).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::{ActiveCameras, Camera, PerspectiveProjection},
+        render_graph::RenderGraph,
+    },
+    window::WindowResized,
+};
+use bevy_egui::{
+    egui::{self, Area, Order, Pos2, TextureId},
+    EguiContext, EguiSettings,
+};
+
+use crate::{
+    helpers::{
+        cleanup_system,
+        render::{add_texture_camera, refresh_egui_textures},
+    },
+    GameState,
+};
+
+use super::{
+    camera::CameraComponent,
+    map::MapLayout,
+    tank::{PlayerId, Tank},
+    TanksData,
+};
+
+/// Bevy can't render a camera to part of the window yet, so each player's camera renders
+/// into its own texture and egui lays those out side by side.
+///
+/// There is no mesh picking in split screen, bevy_mod_picking only casts from the window
+/// camera. Cells are picked through `ViewportCursor` instead, and tanks can't be picked
+pub struct SplitScreenPlugin {
+    state: GameState,
+}
+
+impl SplitScreenPlugin {
+    pub fn new(state: GameState) -> Self {
+        SplitScreenPlugin { state: state }
+    }
+}
+
+impl Plugin for SplitScreenPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(PlayerViewports::default())
+            .insert_resource(ViewportCursor::default())
+            .add_startup_system(setup_render_graph.system())
+            .add_system_set(
+                SystemSet::on_enter(self.state.clone())
+                    .with_system(spawn_player_cameras.system())
+                    .with_system(register_textures.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(update_viewports.system().label("viewports"))
+                    .with_system(follow_tanks.system())
+                    .with_system(fit_projections.system().after("viewports"))
+                    .with_system(update_viewport_cursor.system().after("viewports").label("viewport_cursor"))
+                    .with_system(draw_player_views.system().after("viewports"))
+                    .with_system(refresh_textures.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone())
+                    .with_system(cleanup_system::<PlayerCamera>.system())
+                    .with_system(clear_viewports.system()),
+            );
+    }
+}
+
+pub const PLAYER_CAMERAS: [&str; 2] = ["tanks_player_1", "tanks_player_2"];
+
+const PLAYER_TEXTURES: [HandleUntyped; 2] = [
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x7a4e_51c3_2d90_0001),
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x7a4e_51c3_2d90_0002),
+];
+
+/// Egui ids for the player textures, picked to stay clear of anything else using user textures
+const EGUI_TEXTURE_BASE: u64 = 0x7a4e_0000;
+
+/// Camera rig that follows one player's tank
+pub struct PlayerCamera {
+    pub player: PlayerId,
+    /// Offset from the tank the camera sits at
+    pub offset: Vec3,
+}
+
+/// Part of the window belonging to a player, in logical pixels with bevy's bottom left origin
+#[derive(Debug, Clone)]
+pub struct PlayerViewport {
+    pub player: Option<PlayerId>,
+    pub camera: Entity,
+    pub min: Vec2,
+    pub size: Vec2,
+}
+
+impl PlayerViewport {
+    pub fn contains(&self, position: Vec2) -> bool {
+        let max = self.min + self.size;
+        position.x >= self.min.x
+            && position.y >= self.min.y
+            && position.x < max.x
+            && position.y < max.y
+    }
+
+    /// Normalized device coordinates for a window position inside this viewport
+    pub fn to_ndc(&self, position: Vec2) -> Vec2 {
+        (position - self.min) / self.size * 2.0 - Vec2::ONE
+    }
+
+    /// Window position for normalized device coordinates of this viewport's camera
+    pub fn from_ndc(&self, ndc: Vec2) -> Vec2 {
+        self.min + (ndc + Vec2::ONE) * 0.5 * self.size
+    }
+
+    /// Window position of a world position, None if its behind the camera
+    pub fn world_to_window(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        position: Vec3,
+    ) -> Option<Vec2> {
+        let world_to_ndc =
+            camera.projection_matrix * camera_transform.compute_matrix().inverse();
+        let clip = world_to_ndc * position.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(self.from_ndc(Vec2::new(ndc.x, ndc.y)))
+    }
+
+    /// Ray from the camera through a window position, as origin and direction
+    pub fn ray(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        position: Vec2,
+    ) -> (Vec3, Vec3) {
+        let ndc = self.to_ndc(position);
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+        let near = ndc_to_world.project_point3(ndc.extend(0.0));
+        let far = ndc_to_world.project_point3(ndc.extend(1.0));
+        (near, (far - near).normalize())
+    }
+
+    /// Where the ray through a window position hits the ground plane
+    pub fn ground(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        position: Vec2,
+    ) -> Option<Vec3> {
+        let (origin, direction) = self.ray(camera, camera_transform, position);
+        if direction.y.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = -origin.y / direction.y;
+        if t < 0.0 {
+            return None;
+        }
+        Some(origin + direction * t)
+    }
+}
+
+/// Viewport for each local player, a single full window viewport when not split
+#[derive(Debug, Default)]
+pub struct PlayerViewports {
+    pub viewports: Vec<PlayerViewport>,
+}
+
+impl PlayerViewports {
+    pub fn at(&self, position: Vec2) -> Option<&PlayerViewport> {
+        self.viewports.iter().find(|v| v.contains(position))
+    }
+
+    pub fn for_player(&self, player: PlayerId) -> Option<&PlayerViewport> {
+        self.viewports.iter().find(|v| v.player == Some(player))
+    }
+}
+
+/// Where the mouse is pointing, routed through whichever viewport it is over
+#[derive(Debug, Default)]
+pub struct ViewportCursor {
+    pub player: Option<PlayerId>,
+    /// Point on the ground plane under the cursor
+    pub ground: Option<Vec3>,
+    pub cell: Option<(u32, u32)>,
+}
+
+fn setup_render_graph(
+    mut render_graph: ResMut<RenderGraph>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    msaa: Res<Msaa>,
+) {
+    // players split the window side by side
+    let fraction = Vec2::new(1.0 / PLAYER_CAMERAS.len() as f32, 1.0);
+    for (i, camera) in PLAYER_CAMERAS.iter().enumerate() {
+        add_texture_camera(
+            &mut render_graph,
+            &mut active_cameras,
+            &msaa,
+            camera,
+            PLAYER_TEXTURES[i].clone(),
+            fraction,
+        );
+    }
+}
+
+fn egui_textures() -> Vec<(u64, HandleUntyped)> {
+    PLAYER_TEXTURES
+        .iter()
+        .enumerate()
+        .map(|(i, texture)| (EGUI_TEXTURE_BASE + i as u64, texture.clone()))
+        .collect()
+}
+
+fn register_textures(mut egui_ctx: ResMut<EguiContext>) {
+    for (id, texture) in egui_textures() {
+        egui_ctx.set_egui_texture(id, texture.typed());
+    }
+}
+
+/// The render graph makes the player textures again when the window resizes, egui needs to
+/// pick up the new ones
+fn refresh_textures(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut ev_resized: EventReader<WindowResized>,
+    mut removed: Local<bool>,
+) {
+    let resized = ev_resized.iter().count() > 0;
+    refresh_egui_textures(&mut egui_ctx, &egui_textures(), resized, &mut removed);
+}
+
+fn spawn_player_cameras(mut commands: Commands, data: Res<TanksData>) {
+    if data.local_players < 2 {
+        return;
+    }
+
+    for i in 0..data.local_players.min(PLAYER_CAMERAS.len() as u32) {
+        let offset = Vec3::new(0.0, 1800.0, 900.0);
+        commands
+            .spawn_bundle(PerspectiveCameraBundle {
+                camera: Camera {
+                    name: Some(PLAYER_CAMERAS[i as usize].to_string()),
+                    ..Default::default()
+                },
+                perspective_projection: PerspectiveProjection {
+                    far: 4000.0,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(offset).looking_at(Vec3::ZERO, Vec3::Y),
+                ..Default::default()
+            })
+            .insert(PlayerCamera {
+                player: PlayerId(i),
+                offset,
+            })
+            .insert(Name::new(format!("Player {} Camera", i + 1)));
+    }
+}
+
+fn follow_tanks(
+    tanks: Query<(&Tank, &Transform)>,
+    mut cameras: Query<(&PlayerCamera, &mut Transform), Without<Tank>>,
+) {
+    for (rig, mut transform) in cameras.iter_mut() {
+        if let Some((_, tank)) = tanks.iter().find(|(tank, _)| tank.player == rig.player) {
+            *transform = Transform::from_translation(tank.translation + rig.offset)
+                .looking_at(tank.translation, Vec3::Y);
+        }
+    }
+}
+
+/// Split the window between player cameras, or give all of it to the single camera
+fn update_viewports(
+    windows: Res<Windows>,
+    mut viewports: ResMut<PlayerViewports>,
+    single: Query<Entity, With<CameraComponent>>,
+    players: Query<(Entity, &PlayerCamera)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => Vec2::new(window.width(), window.height()),
+        None => return,
+    };
+
+    let mut rigs: Vec<(Entity, PlayerId)> = players.iter().map(|(e, rig)| (e, rig.player)).collect();
+    rigs.sort_by_key(|(_, player)| *player);
+
+    viewports.viewports.clear();
+    if rigs.is_empty() {
+        if let Some(camera) = single.iter().next() {
+            viewports.viewports.push(PlayerViewport {
+                player: Some(PlayerId(0)),
+                camera,
+                min: Vec2::ZERO,
+                size: window,
+            });
+        }
+        return;
+    }
+
+    let width = window.x / rigs.len() as f32;
+    for (i, (camera, player)) in rigs.into_iter().enumerate() {
+        viewports.viewports.push(PlayerViewport {
+            player: Some(player),
+            camera,
+            min: Vec2::new(width * i as f32, 0.0),
+            size: Vec2::new(width, window.y),
+        });
+    }
+}
+
+/// Bevy sizes projections to the whole window, player cameras need their viewport's aspect
+fn fit_projections(
+    viewports: Res<PlayerViewports>,
+    mut cameras: Query<(&mut Camera, &mut PerspectiveProjection), With<PlayerCamera>>,
+) {
+    for viewport in viewports.viewports.iter() {
+        if let Ok((mut camera, mut projection)) = cameras.get_mut(viewport.camera) {
+            let aspect = viewport.size.x / viewport.size.y;
+            if (projection.aspect_ratio - aspect).abs() > f32::EPSILON {
+                projection.aspect_ratio = aspect;
+                camera.projection_matrix = projection.get_projection_matrix();
+            }
+        }
+    }
+}
+
+fn update_viewport_cursor(
+    windows: Res<Windows>,
+    layout: Res<MapLayout>,
+    viewports: Res<PlayerViewports>,
+    mut cursor: ResMut<ViewportCursor>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    *cursor = ViewportCursor::default();
+
+    let position = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(position) => position,
+        None => return,
+    };
+    let viewport = match viewports.at(position) {
+        Some(viewport) => viewport,
+        None => return,
+    };
+    let (camera, transform) = match cameras.get(viewport.camera) {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    cursor.player = viewport.player;
+    cursor.ground = viewport.ground(camera, transform, position);
+    cursor.cell = cursor.ground.and_then(|ground| layout.world_to_cell(ground));
+}
+
+fn draw_player_views(
+    egui_ctx: Res<EguiContext>,
+    egui_settings: Res<EguiSettings>,
+    windows: Res<Windows>,
+    viewports: Res<PlayerViewports>,
+    split: Query<(), With<PlayerCamera>>,
+) {
+    if split.iter().next().is_none() {
+        return;
+    }
+    let window_height = match windows.get_primary() {
+        Some(window) => window.height(),
+        None => return,
+    };
+    let scale = egui_settings.scale_factor as f32;
+
+    for (i, viewport) in viewports.viewports.iter().enumerate() {
+        // egui starts top left
+        let top_left = Pos2::new(
+            viewport.min.x / scale,
+            (window_height - viewport.min.y - viewport.size.y) / scale,
+        );
+        let size = egui::Vec2::new(viewport.size.x / scale, viewport.size.y / scale);
+
+        Area::new(format!("player_view_{}", i))
+            .order(Order::Background)
+            .fixed_pos(top_left)
+            .interactable(false)
+            .show(egui_ctx.ctx(), |ui| {
+                ui.image(TextureId::User(EGUI_TEXTURE_BASE + i as u64), size);
+            });
+    }
+}
+
+fn clear_viewports(mut viewports: ResMut<PlayerViewports>, mut cursor: ResMut<ViewportCursor>) {
+    viewports.viewports.clear();
+    *cursor = ViewportCursor::default();
+}
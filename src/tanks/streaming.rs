@@ -0,0 +1,623 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+use bevy_prototype_debug_lines::DebugLines;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::{EditorState, RegisterConsoleCommand},
+    loading::TanksAssets,
+    GameState,
+};
+
+use super::{
+    map::{
+        build_chunk_mesh, Destructible, MapCells, MapData, MapLayout, MineLocation, MineStatus,
+        Obstacle, ObstacleModel, Rubble,
+    },
+    split_screen::PlayerViewports,
+    tank::Tank,
+};
+
+/// Only the chunks around the cameras and tanks exist as entities, everything else lives in the
+/// [`CellStore`]
+pub struct StreamingPlugin {
+    state: GameState,
+}
+
+impl StreamingPlugin {
+    pub fn new(state: GameState) -> Self {
+        StreamingPlugin { state: state }
+    }
+}
+
+impl Plugin for StreamingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<MapStreaming>::new().open(false))
+            .register_console_resource::<MapStreaming>("streaming")
+            .insert_resource(CellStore::default())
+            .insert_resource(LoadedChunks::default())
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(stream_chunks.system().label("streaming"))
+                    .with_system(store_cells.system())
+                    .with_system(draw_chunk_overlay.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone()).with_system(unload_all.system()),
+            );
+    }
+}
+
+#[derive(Inspectable, Debug, Reflect)]
+pub struct MapStreaming {
+    /// Cells along each side of a chunk, takes effect next time the game starts
+    #[inspectable(min = 4, max = 64)]
+    pub chunk_size: u32,
+    /// Chunks kept loaded around each camera and tank
+    #[inspectable(min = 1, max = 16)]
+    pub load_radius: u32,
+    /// Most chunks spawned in a single frame, so moving fast doesn't stall
+    #[inspectable(min = 1, max = 64)]
+    pub chunks_per_frame: u32,
+    /// Outline loaded chunks, drawn while the editor is open
+    pub show_chunks: bool,
+}
+
+impl FromWorld for MapStreaming {
+    fn from_world(_world: &mut World) -> Self {
+        MapStreaming {
+            chunk_size: 16,
+            load_radius: 2,
+            chunks_per_frame: 4,
+            show_chunks: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObstacleKind {
+    Crate,
+    Wall,
+    Column,
+}
+
+impl ObstacleKind {
+    pub fn destructible(&self) -> bool {
+        match self {
+            ObstacleKind::Crate | ObstacleKind::Wall => true,
+            ObstacleKind::Column => false,
+        }
+    }
+
+    pub fn scene(&self, assets: &TanksAssets) -> Handle<Scene> {
+        match self {
+            ObstacleKind::Crate => assets.crate_box.clone(),
+            ObstacleKind::Wall => assets.wall.clone(),
+            ObstacleKind::Column => assets.column.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Terrain {
+    Open,
+    Obstacle(ObstacleKind),
+    Rubble,
+}
+
+/// Everything about a cell packed into two bytes
+///
+/// bits 0-1 terrain, 2-3 obstacle kind, 4-6 mine status, 8-15 mine count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoredCell(u16);
+
+impl StoredCell {
+    pub fn new(status: MineStatus, terrain: Terrain) -> Self {
+        let terrain = match terrain {
+            Terrain::Open => 0,
+            Terrain::Obstacle(kind) => {
+                1 | match kind {
+                    ObstacleKind::Crate => 0,
+                    ObstacleKind::Wall => 1 << 2,
+                    ObstacleKind::Column => 2 << 2,
+                }
+            }
+            Terrain::Rubble => 2,
+        };
+        let status = match status {
+            MineStatus::Unknown { mine: false } => 0,
+            MineStatus::Unknown { mine: true } => 1 << 4,
+            MineStatus::Count { count } => 2 << 4 | (count.min(255) as u16) << 8,
+            MineStatus::Marked => 3 << 4,
+            MineStatus::Exploded => 4 << 4,
+        };
+        StoredCell(terrain | status)
+    }
+
+    pub fn terrain(&self) -> Terrain {
+        match self.0 & 0b11 {
+            1 => Terrain::Obstacle(match (self.0 >> 2) & 0b11 {
+                0 => ObstacleKind::Crate,
+                1 => ObstacleKind::Wall,
+                _ => ObstacleKind::Column,
+            }),
+            2 => Terrain::Rubble,
+            _ => Terrain::Open,
+        }
+    }
+
+    pub fn status(&self) -> MineStatus {
+        match (self.0 >> 4) & 0b111 {
+            1 => MineStatus::Unknown { mine: true },
+            2 => MineStatus::Count {
+                count: (self.0 >> 8) as u32,
+            },
+            3 => MineStatus::Marked,
+            4 => MineStatus::Exploded,
+            _ => MineStatus::Unknown { mine: false },
+        }
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.terrain(), Terrain::Obstacle(_))
+    }
+}
+
+/// State of every cell on the map, loaded or not
+#[derive(Debug, Default)]
+pub struct CellStore {
+    width: u32,
+    height: u32,
+    cells: Vec<StoredCell>,
+    /// Cells that started or stopped blocking sight since the fog last caught up
+    blocked_changes: Vec<(u32, u32)>,
+    /// Seconds left for each mine caught in a blast that is about to go off, kept here so chain
+    /// reactions wait out unloaded chunks and carry on when they come back
+    pending: BTreeMap<(u32, u32), f32>,
+}
+
+impl CellStore {
+    pub fn new(width: u32, height: u32) -> Self {
+        CellStore {
+            width,
+            height,
+            cells: vec![
+                StoredCell::new(MineStatus::Unknown { mine: false }, Terrain::Open);
+                (width * height) as usize
+            ],
+            blocked_changes: Vec::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<StoredCell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.cells[(y * self.width + x) as usize])
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, cell: StoredCell) {
+        if x < self.width && y < self.height {
+            let index = (y * self.width + x) as usize;
+            if self.cells[index].is_blocked() != cell.is_blocked() {
+                self.blocked_changes.push((x, y));
+            }
+            self.cells[index] = cell;
+        }
+    }
+
+    pub fn is_blocked(&self, x: u32, y: u32) -> bool {
+        self.get(x, y).map(|cell| cell.is_blocked()).unwrap_or(true)
+    }
+
+    pub fn has_blocked_changes(&self) -> bool {
+        !self.blocked_changes.is_empty()
+    }
+
+    /// Cells that started or stopped blocking sight since this was last called
+    pub fn take_blocked_changes(&mut self) -> Vec<(u32, u32)> {
+        std::mem::take(&mut self.blocked_changes)
+    }
+
+    /// Set off the mine in a cell after a delay, unless it is already counting down
+    pub fn arm(&mut self, x: u32, y: u32, seconds: f32) {
+        if x < self.width && y < self.height {
+            self.pending.entry((x, y)).or_insert(seconds);
+        }
+    }
+
+    pub fn is_pending(&self, x: u32, y: u32) -> bool {
+        self.pending.contains_key(&(x, y))
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Each mine about to go off, with the seconds it has left
+    pub fn pending(&self) -> impl Iterator<Item = ((u32, u32), f32)> + '_ {
+        self.pending.iter().map(|(cell, seconds)| (*cell, *seconds))
+    }
+
+    /// Count down mines about to go off in cells that are `loaded`, the rest wait for their
+    /// chunk. Returns the cells that are due, in order
+    pub fn tick_pending(
+        &mut self,
+        seconds: f32,
+        loaded: impl Fn(u32, u32) -> bool,
+    ) -> Vec<(u32, u32)> {
+        let mut due = Vec::new();
+        for (&(x, y), left) in self.pending.iter_mut() {
+            if !loaded(x, y) {
+                continue;
+            }
+            *left -= seconds;
+            if *left <= 0.0 {
+                due.push((x, y));
+            }
+        }
+        for cell in due.iter() {
+            self.pending.remove(cell);
+        }
+        due
+    }
+}
+
+/// Chunk entity for each loaded chunk, cell entities are its children
+#[derive(Debug, Default)]
+pub struct LoadedChunks {
+    pub chunks: HashMap<(u32, u32), Entity>,
+    /// Wanted chunks that haven't been spawned yet
+    pub pending: usize,
+}
+
+/// Load chunks near anything that needs the map, drop the ones nothing is near anymore
+fn stream_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<MapStreaming>,
+    layout: Res<MapLayout>,
+    data: Res<MapData>,
+    assets: Res<TanksAssets>,
+    store: Res<CellStore>,
+    viewports: Res<PlayerViewports>,
+    mut cells: ResMut<MapCells>,
+    mut loaded: ResMut<LoadedChunks>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tanks: Query<&GlobalTransform, With<Tank>>,
+) {
+    let mut focus: Vec<Vec3> = tanks.iter().map(|t| t.translation).collect();
+    for viewport in viewports.viewports.iter() {
+        if let Ok((camera, transform)) = cameras.get(viewport.camera) {
+            let center = viewport.min + viewport.size * 0.5;
+            if let Some(ground) = viewport.ground(camera, transform, center) {
+                focus.push(ground);
+            }
+        }
+    }
+
+    // wanted chunks, with how far they are from the closest focus so the nearest load first
+    let (chunks_x, chunks_y) = layout.chunks();
+    let radius = settings.load_radius as i64;
+    let mut wanted: HashMap<(u32, u32), i64> = HashMap::new();
+    for position in focus.iter() {
+        let (x, y) = layout.cell_coords(*position);
+        let size = layout.chunk_size.max(1) as i64;
+        let (cx, cy) = (x.div_euclid(size), y.div_euclid(size));
+        for y in (cy - radius).max(0)..=(cy + radius).min(chunks_y as i64 - 1) {
+            for x in (cx - radius).max(0)..=(cx + radius).min(chunks_x as i64 - 1) {
+                let distance = (x - cx).abs().max((y - cy).abs());
+                let entry = wanted.entry((x as u32, y as u32)).or_insert(distance);
+                *entry = (*entry).min(distance);
+            }
+        }
+    }
+
+    let unload: Vec<(u32, u32)> = loaded
+        .chunks
+        .keys()
+        .filter(|chunk| !wanted.contains_key(chunk))
+        .copied()
+        .collect();
+    for chunk in unload {
+        if let Some(entity) = loaded.chunks.remove(&chunk) {
+            unload_chunk(&mut commands, &layout, &mut cells, chunk, entity);
+        }
+    }
+
+    let mut missing: Vec<((u32, u32), i64)> = wanted
+        .into_iter()
+        .filter(|(chunk, _)| !loaded.chunks.contains_key(chunk))
+        .collect();
+    missing.sort_by_key(|(_, distance)| *distance);
+    let spawning = missing.len().min(settings.chunks_per_frame as usize);
+    loaded.pending = missing.len() - spawning;
+    for (chunk, _) in missing.into_iter().take(spawning) {
+        let entity = load_chunk(
+            &mut commands,
+            &mut meshes,
+            &layout,
+            &data,
+            &assets,
+            &store,
+            &mut cells,
+            chunk,
+        );
+        loaded.chunks.insert(chunk, entity);
+    }
+}
+
+/// Spawn the terrain mesh and a cell entity for every cell in a chunk
+fn load_chunk(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    layout: &MapLayout,
+    data: &MapData,
+    assets: &TanksAssets,
+    store: &CellStore,
+    cells: &mut MapCells,
+    chunk: (u32, u32),
+) -> Entity {
+    let (mesh, map_chunk) = build_chunk_mesh(layout, chunk);
+    let (min, max) = layout.chunk_cells(chunk);
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: data.terrain.clone(),
+            ..Default::default()
+        })
+        .insert(map_chunk)
+        .insert(Name::new(format!("Map Chunk {}, {}", chunk.0, chunk.1)))
+        .with_children(|parent| {
+            for y in min.1..max.1 {
+                for x in min.0..max.0 {
+                    let stored = match store.get(x, y) {
+                        Some(stored) => stored,
+                        None => continue,
+                    };
+
+                    // cells are plain data, only obstacles need a transform for their model
+                    let mut cell = parent.spawn();
+                    cell.insert(MineLocation {
+                        x,
+                        y,
+                        status: stored.status(),
+                    });
+                    match stored.terrain() {
+                        Terrain::Open => {}
+                        Terrain::Rubble => {
+                            cell.insert(Rubble);
+                        }
+                        Terrain::Obstacle(kind) => {
+                            // pieces from the retro medieval kit are about 1 unit, scale them up to the cell
+                            cell.insert_bundle((
+                                Transform::from_translation(layout.cell_to_world(x, y)),
+                                GlobalTransform::default(),
+                            ))
+                            .insert(Obstacle)
+                            .with_children(|parent| {
+                                parent
+                                    .spawn_bundle((
+                                        Transform::from_scale(Vec3::splat(layout.cell_size)),
+                                        GlobalTransform::default(),
+                                    ))
+                                    .insert(ObstacleModel)
+                                    .with_children(|model| {
+                                        model.spawn_scene(kind.scene(assets));
+                                    });
+                            });
+                            if kind.destructible() {
+                                cell.insert(Destructible);
+                            }
+                        }
+                    }
+                    cells.set(x, y, cell.id());
+                }
+            }
+        })
+        .id()
+}
+
+/// Cell state, and any chain reaction still to go off, is already in the store, so unloading
+/// is just despawning
+pub fn unload_chunk(
+    commands: &mut Commands,
+    layout: &MapLayout,
+    cells: &mut MapCells,
+    chunk: (u32, u32),
+    entity: Entity,
+) {
+    let (min, max) = layout.chunk_cells(chunk);
+    for y in min.1..max.1 {
+        for x in min.0..max.0 {
+            cells.clear(x, y);
+        }
+    }
+    commands.entity(entity).despawn_recursive();
+}
+
+fn unload_all(
+    mut commands: Commands,
+    layout: Res<MapLayout>,
+    mut cells: ResMut<MapCells>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    for (chunk, entity) in loaded.chunks.drain() {
+        unload_chunk(&mut commands, &layout, &mut cells, chunk, entity);
+    }
+}
+
+/// What the store should hold for a loaded cell, obstacles only ever go away
+pub fn stored_cell(
+    current: StoredCell,
+    status: MineStatus,
+    obstacle: bool,
+    rubble: bool,
+) -> StoredCell {
+    let terrain = match (current.terrain(), obstacle, rubble) {
+        (_, _, true) => Terrain::Rubble,
+        (Terrain::Obstacle(kind), true, false) => Terrain::Obstacle(kind),
+        (Terrain::Obstacle(_), false, false) => Terrain::Open,
+        (terrain, _, false) => terrain,
+    };
+    StoredCell::new(status, terrain)
+}
+
+/// Write changes to loaded cells back to the store, so nothing is lost when they unload
+fn store_cells(
+    mut store: ResMut<CellStore>,
+    changed: Query<
+        (&MineLocation, Option<&Obstacle>, Option<&Rubble>),
+        Or<(Changed<MineLocation>, Added<Rubble>)>,
+    >,
+) {
+    for (cell, obstacle, rubble) in changed.iter() {
+        let current = match store.get(cell.x, cell.y) {
+            Some(current) => current,
+            None => continue,
+        };
+        let next = stored_cell(current, cell.status, obstacle.is_some(), rubble.is_some());
+        if next != current {
+            store.set(cell.x, cell.y, next);
+        }
+    }
+}
+
+/// Outline each loaded chunk on the ground
+fn draw_chunk_overlay(
+    settings: Res<MapStreaming>,
+    editor: Res<State<EditorState>>,
+    layout: Res<MapLayout>,
+    loaded: Res<LoadedChunks>,
+    mut lines: ResMut<DebugLines>,
+) {
+    if !settings.show_chunks || *editor.current() != EditorState::Playing {
+        return;
+    }
+
+    let half = layout.footprint() * 0.5;
+    let lift = Vec3::Y * 5.0;
+    for chunk in loaded.chunks.keys() {
+        let (min, max) = layout.chunk_cells(*chunk);
+        let a = layout.cell_to_world(min.0, min.1) - Vec3::new(half.x, 0.0, half.y) + lift;
+        let c = layout.cell_to_world(max.0 - 1, max.1 - 1) + Vec3::new(half.x, 0.0, half.y) + lift;
+        let b = Vec3::new(c.x, a.y, a.z);
+        let d = Vec3::new(a.x, a.y, c.z);
+        lines.line(a, b, 0.0);
+        lines.line(b, c, 0.0);
+        lines.line(c, d, 0.0);
+        lines.line(d, a, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_pack_into_two_bytes_and_back() {
+        let statuses = [
+            MineStatus::Unknown { mine: false },
+            MineStatus::Unknown { mine: true },
+            MineStatus::Count { count: 0 },
+            MineStatus::Count { count: 6 },
+            MineStatus::Count { count: 255 },
+            MineStatus::Marked,
+            MineStatus::Exploded,
+        ];
+        let terrains = [
+            Terrain::Open,
+            Terrain::Rubble,
+            Terrain::Obstacle(ObstacleKind::Crate),
+            Terrain::Obstacle(ObstacleKind::Wall),
+            Terrain::Obstacle(ObstacleKind::Column),
+        ];
+        for status in statuses.iter() {
+            for terrain in terrains.iter() {
+                let cell = StoredCell::new(*status, *terrain);
+                assert_eq!(cell.status(), *status);
+                assert_eq!(cell.terrain(), *terrain);
+                assert_eq!(cell.is_blocked(), matches!(terrain, Terrain::Obstacle(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn changes_to_loaded_cells_survive_a_reload() {
+        let mut world = World::default();
+        let mut store = CellStore::new(4, 4);
+        let wall = StoredCell::new(
+            MineStatus::Unknown { mine: false },
+            Terrain::Obstacle(ObstacleKind::Wall),
+        );
+        store.set(1, 1, wall);
+        world.insert_resource(store);
+
+        // loaded like load_chunk does, then a mine gets marked and the wall gets blown up
+        let marked = world
+            .spawn()
+            .insert(MineLocation {
+                x: 0,
+                y: 0,
+                status: MineStatus::Unknown { mine: false },
+            })
+            .id();
+        let broken = world
+            .spawn()
+            .insert(MineLocation {
+                x: 1,
+                y: 1,
+                status: MineStatus::Unknown { mine: false },
+            })
+            .insert(Obstacle)
+            .id();
+        let mut stage = SystemStage::single(store_cells.system());
+        stage.run(&mut world);
+
+        world.get_mut::<MineLocation>(marked).unwrap().status = MineStatus::Marked;
+        let mut entity = world.entity_mut(broken);
+        entity.remove::<Obstacle>();
+        entity.insert(Rubble);
+        stage.run(&mut world);
+
+        // unloading despawns the cells, loading again only has the store to go on
+        world.despawn(marked);
+        world.despawn(broken);
+        let store = world.get_resource::<CellStore>().unwrap();
+        assert_eq!(store.get(0, 0).unwrap().status(), MineStatus::Marked);
+        assert_eq!(store.get(1, 1).unwrap().terrain(), Terrain::Rubble);
+        assert!(!store.is_blocked(1, 1));
+        assert_eq!(store.get(2, 2).unwrap().terrain(), Terrain::Open);
+    }
+
+    #[test]
+    fn chain_reactions_wait_for_unloaded_chunks() {
+        let mut store = CellStore::new(4, 4);
+        store.arm(0, 0, 0.1);
+        store.arm(3, 3, 0.1);
+        // arming again doesn't restart the countdown
+        store.arm(0, 0, 5.0);
+        let left_half = |x: u32, _: u32| x < 2;
+
+        assert!(store.tick_pending(0.05, left_half).is_empty());
+        assert_eq!(store.tick_pending(0.05, left_half), vec![(0, 0)]);
+        // the right half was unloaded the whole time
+        assert!(store.tick_pending(1.0, left_half).is_empty());
+        assert!(store.is_pending(3, 3));
+
+        assert_eq!(store.tick_pending(0.1, |_, _| true), vec![(3, 3)]);
+        assert!(!store.has_pending());
+    }
+}
@@ -0,0 +1,431 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy_input_actionmap::InputMap;
+use bevy_inspector_egui::{Inspectable, InspectorPlugin};
+use serde::{Deserialize, Serialize};
+
+use crate::{editor::GameTime, helpers::cleanup_system, GameStages, GameState};
+
+use super::{
+    actions::{TankAction, TankControl},
+    health::Health,
+    map::MapLayout,
+    minimap::MinimapIcon,
+    projectile::{spawn_shell, ProjectileData},
+    visibility::{FogOfWar, Sight},
+    TanksData, TanksMode,
+};
+
+pub struct TankPlugin {
+    state: GameState,
+}
+
+impl TankPlugin {
+    pub fn new(state: GameState) -> Self {
+        TankPlugin { state: state }
+    }
+}
+
+impl Plugin for TankPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(InspectorPlugin::<TankData>::new().open(false))
+            .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(startup.system()))
+            .add_system_set_to_stage(
+                GameStages::Game,
+                SystemSet::on_update(self.state.clone())
+                    .with_system(tank_movement.system())
+                    .with_system(computer_movement.system())
+                    .with_system(tank_fire.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(self.state.clone())
+                    .with_system(cleanup_system::<TankCleanup>.system()),
+            );
+    }
+}
+
+#[derive(Inspectable, Debug)]
+pub struct TankData {
+    /// Unit sized, scaled up to the map's cells when spawned
+    pub mesh: Handle<Mesh>,
+    pub friendly: Handle<StandardMaterial>,
+    pub enemy: Handle<StandardMaterial>,
+    #[inspectable(min = 1)]
+    pub sight_radius: u32,
+    /// World units per second
+    #[inspectable(min = 0.0)]
+    pub speed: f32,
+    /// Radians per second
+    #[inspectable(min = 0.0)]
+    pub turn_speed: f32,
+    /// Seconds between shots
+    #[inspectable(min = 0.0)]
+    pub reload_time: f32,
+}
+
+impl FromWorld for TankData {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .expect("ResMut<Assets<StandardMaterial>> not found.");
+        let mut meshes = world
+            .get_resource_mut::<Assets<Mesh>>()
+            .expect("ResMut<Assets<Mesh>> not found.");
+
+        TankData {
+            mesh: meshes.add(Mesh::from(shape::Box::new(1.0, 0.5, 1.0))),
+            friendly: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.1, 0.3, 0.8),
+                unlit: true,
+                ..Default::default()
+            }),
+            enemy: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.8, 0.1, 0.1),
+                unlit: true,
+                ..Default::default()
+            }),
+            sight_radius: 3,
+            speed: 400.0,
+            turn_speed: 2.0,
+            reload_time: 0.75,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Inspectable, Serialize, Deserialize)]
+pub enum Team {
+    Friendly,
+    Enemy,
+}
+
+impl Default for Team {
+    fn default() -> Self {
+        Team::Friendly
+    }
+}
+
+/// Who controls a tank, lives and score are tracked per player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PlayerId(pub u32);
+
+#[derive(Debug)]
+pub struct Tank {
+    pub team: Team,
+    pub player: PlayerId,
+    pub reload: Timer,
+}
+
+/// Where a player's tank starts and comes back after being destroyed
+#[derive(Debug)]
+pub struct SpawnPoint {
+    pub player: PlayerId,
+    pub team: Team,
+}
+
+struct TankCleanup;
+
+fn startup(
+    mut commands: Commands,
+    data: Res<TankData>,
+    tanks_data: Res<TanksData>,
+    layout: Res<MapLayout>,
+) {
+    let mut spawns = vec![(PlayerId(0), Team::Friendly, (0, 0))];
+    if tanks_data.local_players > 1 {
+        spawns.push((PlayerId(1), Team::Friendly, (1, 0)));
+    }
+    spawns.push((
+        PlayerId(tanks_data.local_players),
+        Team::Enemy,
+        (layout.width - 1, layout.height - 1),
+    ));
+
+    for (player, team, cell) in spawns.iter() {
+        let transform = Transform::from_translation(layout.cell_to_world(cell.0, cell.1));
+        spawn_point(&mut commands, *player, *team, transform);
+
+        spawn_tank(
+            &mut commands,
+            &data,
+            &tanks_data,
+            &layout,
+            *team,
+            *player,
+            transform,
+        );
+    }
+}
+
+pub fn spawn_point(
+    commands: &mut Commands,
+    player: PlayerId,
+    team: Team,
+    transform: Transform,
+) -> Entity {
+    commands
+        .spawn_bundle((transform, GlobalTransform::default()))
+        .insert(SpawnPoint { player, team })
+        .insert(MinimapIcon {
+            color: match team {
+                Team::Friendly => Color::rgb(0.1, 0.3, 0.8),
+                Team::Enemy => Color::rgb(0.8, 0.1, 0.1),
+            },
+            label: Some(match team {
+                Team::Friendly => format!("P{}", player.0 + 1),
+                Team::Enemy => "Enemy".to_string(),
+            }),
+        })
+        .insert(Name::new(format!("{:?} Spawn Point", player)))
+        .insert(TankCleanup)
+        .id()
+}
+
+pub fn spawn_tank(
+    commands: &mut Commands,
+    data: &TankData,
+    tanks_data: &TanksData,
+    layout: &MapLayout,
+    team: Team,
+    player: PlayerId,
+    spawn: Transform,
+) -> Entity {
+    let size = layout.cell_size * 0.6;
+    let mut transform = spawn;
+    transform.translation += Vec3::Y * size * 0.25;
+    transform.scale = Vec3::splat(size);
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: data.mesh.clone(),
+            material: match team {
+                Team::Friendly => data.friendly.clone(),
+                Team::Enemy => data.enemy.clone(),
+            },
+            transform,
+            ..Default::default()
+        })
+        .insert(Tank {
+            team,
+            player,
+            reload: Timer::from_seconds(data.reload_time, false),
+        })
+        .insert(Health::new(
+            tanks_data.damage.max_health,
+            tanks_data.damage.armor,
+        ))
+        .insert(Sight {
+            radius: data.sight_radius,
+        })
+        // made pickable once visibility knows it can be seen and picked
+        .insert(Name::new(format!("{:?} Tank {}", team, player.0)))
+        .insert(TankCleanup)
+        .id()
+}
+
+/// Drive the local players' tanks, obstacles and the map edge stop them, artillery turns do
+/// their own aiming
+fn tank_movement(
+    time: Res<GameTime>,
+    data: Res<TankData>,
+    tanks_data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    input: Res<InputMap<TankAction>>,
+    mut query: Query<(&Tank, &mut Transform)>,
+) {
+    if tanks_data.mode != TanksMode::RealTime {
+        return;
+    }
+    for (tank, mut transform) in query.iter_mut() {
+        if !tanks_data.is_local(tank.player) {
+            continue;
+        }
+        let active = |control| input.active(TankAction::new(tank.player, control));
+
+        let mut turn = 0.0;
+        if active(TankControl::TurnLeft) {
+            turn += 1.0;
+        }
+        if active(TankControl::TurnRight) {
+            turn -= 1.0;
+        }
+        transform.rotate(Quat::from_rotation_y(
+            turn * data.turn_speed * time.delta_seconds(),
+        ));
+
+        let mut drive = 0.0;
+        if active(TankControl::Forward) {
+            drive += 1.0;
+        }
+        if active(TankControl::Back) {
+            drive -= 1.0;
+        }
+
+        let target = transform.translation
+            + transform.forward() * drive * data.speed * time.delta_seconds();
+        let open = match layout.world_to_cell(target) {
+            Some((x, y)) => !fog.is_blocked(x, y),
+            None => false,
+        };
+        if open {
+            transform.translation = target;
+        }
+    }
+}
+
+/// Next cell center on the way from one position to another, going around obstacles, None
+/// once they are next to each other or there is no way through
+pub fn next_waypoint(layout: &MapLayout, fog: &FogOfWar, from: Vec3, to: Vec3) -> Option<Vec3> {
+    let start = layout.world_to_cell(from)?;
+    let end = layout.world_to_cell(to)?;
+    let path = layout.find_path(start, end, |x, y| fog.is_blocked(x, y))?;
+    if path.len() <= 2 {
+        return None;
+    }
+    let (x, y) = path[1];
+    let mut waypoint = layout.cell_to_world(x, y);
+    waypoint.y = from.y;
+    Some(waypoint)
+}
+
+/// Tanks nobody is sitting at head for the closest tank on the other team
+fn computer_movement(
+    time: Res<GameTime>,
+    data: Res<TankData>,
+    tanks_data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    fog: Res<FogOfWar>,
+    mut query: Query<(&Tank, &mut Transform)>,
+) {
+    if tanks_data.mode != TanksMode::RealTime {
+        return;
+    }
+    let targets: Vec<(Team, Vec3)> = query
+        .iter_mut()
+        .map(|(tank, transform)| (tank.team, transform.translation))
+        .collect();
+
+    for (tank, mut transform) in query.iter_mut() {
+        if tanks_data.is_local(tank.player) {
+            continue;
+        }
+        let position = transform.translation;
+        let target = targets
+            .iter()
+            .filter(|(team, _)| *team != tank.team)
+            .map(|(_, target)| *target)
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .partial_cmp(&b.distance_squared(position))
+                    .unwrap()
+            });
+        let waypoint =
+            match target.and_then(|target| next_waypoint(&layout, &fog, position, target)) {
+                Some(waypoint) => waypoint,
+                None => continue,
+            };
+
+        // turn towards the waypoint, only driving once roughly facing it
+        let forward = transform.forward();
+        let heading = waypoint - position;
+        let angle = forward.cross(heading).y.atan2(forward.dot(heading));
+        let max_turn = data.turn_speed * time.delta_seconds();
+        transform.rotate(Quat::from_rotation_y(angle.clamp(-max_turn, max_turn)));
+        if angle.abs() > FRAC_PI_4 {
+            continue;
+        }
+
+        let target = position + transform.forward() * data.speed * time.delta_seconds();
+        let open = match layout.world_to_cell(target) {
+            Some((x, y)) => !fog.is_blocked(x, y),
+            None => false,
+        };
+        if open {
+            transform.translation = target;
+        }
+    }
+}
+
+fn tank_fire(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    tanks_data: Res<TanksData>,
+    layout: Res<MapLayout>,
+    projectiles: Res<ProjectileData>,
+    input: Res<InputMap<TankAction>>,
+    mut query: Query<(Entity, &mut Tank, &Transform)>,
+) {
+    if tanks_data.mode != TanksMode::RealTime {
+        return;
+    }
+    for (entity, mut tank, transform) in query.iter_mut() {
+        tank.reload.tick(time.delta());
+        if !tanks_data.is_local(tank.player) || !tank.reload.finished() {
+            continue;
+        }
+
+        if input.just_active(TankAction::new(tank.player, TankControl::Fire)) {
+            tank.reload.reset();
+            // start the shell at the front of the tank
+            let muzzle = transform.translation + transform.forward() * layout.cell_size * 0.4;
+            spawn_shell(
+                &mut commands,
+                &projectiles,
+                Some(entity),
+                muzzle,
+                transform.forward(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waypoints_go_around_obstacles() {
+        let layout = MapLayout {
+            width: 5,
+            height: 5,
+            ..Default::default()
+        };
+        let mut fog = FogOfWar::new(layout.width, layout.height);
+        // wall across the middle with a gap on the right
+        for x in 0..4 {
+            fog.set_blocked(x, 2, true);
+        }
+        let from = layout.cell_to_world(1, 0) + Vec3::Y * 10.0;
+        let to = layout.cell_to_world(1, 4);
+
+        let mut position = from;
+        let mut cells = vec![layout.world_to_cell(position).unwrap()];
+        while let Some(waypoint) = next_waypoint(&layout, &fog, position, to) {
+            assert_eq!(waypoint.y, 10.0);
+            position = waypoint;
+            cells.push(layout.world_to_cell(position).unwrap());
+        }
+        assert!(cells.contains(&(4, 2)));
+        assert!(cells.iter().all(|(x, y)| !fog.is_blocked(*x, *y)));
+        // stops next to the target rather than driving into it
+        assert_eq!(layout.distance(*cells.last().unwrap(), (1, 4)), 1);
+    }
+
+    #[test]
+    fn no_waypoint_without_a_way_through() {
+        let layout = MapLayout {
+            width: 5,
+            height: 5,
+            ..Default::default()
+        };
+        let mut fog = FogOfWar::new(layout.width, layout.height);
+        for x in 0..5 {
+            fog.set_blocked(x, 2, true);
+        }
+        let from = layout.cell_to_world(1, 0);
+        let to = layout.cell_to_world(1, 4);
+        assert_eq!(next_waypoint(&layout, &fog, from, to), None);
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::grid::GridSpace;
+
+/// How cells sit next to each other, anything that depends on the shape of a cell goes through
+/// this. Cells are always addressed by column and row, positions are relative to the bottom
+/// left corner of the map on the ground plane, x across and y along world z
+pub trait GridTopology: Send + Sync {
+    /// Center of a cell
+    fn cell_center(&self, x: u32, y: u32, cell_size: f32) -> Vec2;
+    /// Cell containing a position, which may be off the map
+    fn cell_at(&self, position: Vec2, cell_size: f32) -> (i64, i64);
+    /// Size of the whole map
+    fn extent(&self, width: u32, height: u32, cell_size: f32) -> Vec2;
+    /// Space one cell takes up when tiled
+    fn footprint(&self, cell_size: f32) -> Vec2;
+    /// Outline of a cell around its center, wound the way the map mesh expects
+    fn corners(&self, cell_size: f32) -> Vec<Vec2>;
+    /// Cells touching this one, the ones that count towards a mine count
+    fn neighbours(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32)>;
+    /// Fewest steps between two cells
+    fn distance(&self, a: (u32, u32), b: (u32, u32)) -> u32;
+}
+
+#[derive(Inspectable, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridShape {
+    Square,
+    /// Pointy topped, every other row is pushed half a cell right
+    Hex,
+}
+
+impl Default for GridShape {
+    fn default() -> Self {
+        GridShape::Square
+    }
+}
+
+impl GridShape {
+    pub fn topology(&self) -> &'static dyn GridTopology {
+        match self {
+            GridShape::Square => &SquareGrid,
+            GridShape::Hex => &HexGrid,
+        }
+    }
+}
+
+/// Bounds of the map with cell (0, 0) at the bottom left corner
+fn space(width: u32, height: u32, cell_size: f32) -> GridSpace {
+    GridSpace::new(width, height, cell_size).with_origin(Vec2::ZERO)
+}
+
+pub struct SquareGrid;
+
+impl GridTopology for SquareGrid {
+    fn cell_center(&self, x: u32, y: u32, cell_size: f32) -> Vec2 {
+        space(0, 0, cell_size).cell_to_world(x, y)
+    }
+
+    fn cell_at(&self, position: Vec2, cell_size: f32) -> (i64, i64) {
+        space(0, 0, cell_size).cell_at(position)
+    }
+
+    fn extent(&self, width: u32, height: u32, cell_size: f32) -> Vec2 {
+        space(width, height, cell_size).size()
+    }
+
+    fn footprint(&self, cell_size: f32) -> Vec2 {
+        Vec2::splat(cell_size)
+    }
+
+    fn corners(&self, cell_size: f32) -> Vec<Vec2> {
+        let half = cell_size * 0.5;
+        vec![
+            Vec2::new(-half, -half),
+            Vec2::new(-half, half),
+            Vec2::new(half, half),
+            Vec2::new(half, -half),
+        ]
+    }
+
+    fn neighbours(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32)> {
+        space(width, height, 1.0).neighbours(x, y, true)
+    }
+
+    fn distance(&self, a: (u32, u32), b: (u32, u32)) -> u32 {
+        let dx = (a.0 as i64 - b.0 as i64).abs();
+        let dy = (a.1 as i64 - b.1 as i64).abs();
+        dx.max(dy) as u32
+    }
+}
+
+/// Cell size is the width of a hex, flat side to flat side
+pub struct HexGrid;
+
+impl HexGrid {
+    /// Center to corner
+    fn radius(cell_size: f32) -> f32 {
+        cell_size / 3f32.sqrt()
+    }
+
+    /// Column and row to axial coordinates, see https://www.redblobgames.com/grids/hexagons/
+    fn axial(cell: (u32, u32)) -> (i64, i64) {
+        let (x, y) = (cell.0 as i64, cell.1 as i64);
+        (x - (y - (y & 1)) / 2, y)
+    }
+}
+
+impl GridTopology for HexGrid {
+    fn cell_center(&self, x: u32, y: u32, cell_size: f32) -> Vec2 {
+        let radius = Self::radius(cell_size);
+        Vec2::new(
+            cell_size * (x as f32 + 0.5 + 0.5 * (y & 1) as f32),
+            radius + 1.5 * radius * y as f32,
+        )
+    }
+
+    fn cell_at(&self, position: Vec2, cell_size: f32) -> (i64, i64) {
+        let radius = Self::radius(cell_size);
+        // relative to the center of the first cell
+        let p = position - Vec2::new(cell_size * 0.5, radius);
+        let q = (3f32.sqrt() / 3.0 * p.x - p.y / 3.0) / radius;
+        let r = (2.0 / 3.0 * p.y) / radius;
+
+        // round in cube coordinates, fixing up whichever component moved the most
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+
+        let (q, r) = (rq as i64, rr as i64);
+        (q + (r - (r & 1)) / 2, r)
+    }
+
+    fn extent(&self, width: u32, height: u32, cell_size: f32) -> Vec2 {
+        let radius = Self::radius(cell_size);
+        let shifted = if height > 1 { 0.5 } else { 0.0 };
+        Vec2::new(
+            cell_size * (width as f32 + shifted),
+            2.0 * radius + 1.5 * radius * height.saturating_sub(1) as f32,
+        )
+    }
+
+    fn footprint(&self, cell_size: f32) -> Vec2 {
+        Vec2::new(cell_size, 1.5 * Self::radius(cell_size))
+    }
+
+    fn corners(&self, cell_size: f32) -> Vec<Vec2> {
+        let radius = Self::radius(cell_size);
+        // same winding as the square corners, starting at the top point
+        (0..6)
+            .map(|i| {
+                let angle = (90.0 - 60.0 * i as f32).to_radians();
+                Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect()
+    }
+
+    fn neighbours(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32)> {
+        const EVEN: [(i64, i64); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+        const ODD: [(i64, i64); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+        let offsets = if y & 1 == 0 { &EVEN } else { &ODD };
+        space(width, height, 1.0).offset_cells(x, y, offsets)
+    }
+
+    fn distance(&self, a: (u32, u32), b: (u32, u32)) -> u32 {
+        let (aq, ar) = Self::axial(a);
+        let (bq, br) = Self::axial(b);
+        let (dq, dr) = (aq - bq, ar - br);
+        ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as u32
+    }
+}
+
+/// Shortest route between two cells that avoids blocked ones, both ends included. A* with the
+/// topology's distance, every step costs the same
+pub fn find_path(
+    topology: &dyn GridTopology,
+    width: u32,
+    height: u32,
+    from: (u32, u32),
+    to: (u32, u32),
+    blocked: impl Fn(u32, u32) -> bool,
+) -> Option<Vec<(u32, u32)>> {
+    if blocked(to.0, to.1) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    let mut cost: HashMap<(u32, u32), u32> = HashMap::new();
+    cost.insert(from, 0);
+    open.push(Reverse((topology.distance(from, to), 0, from)));
+
+    while let Some(Reverse((_, steps, cell))) = open.pop() {
+        if cell == to {
+            let mut path = vec![cell];
+            let mut current = cell;
+            while let Some(previous) = came_from.get(&current) {
+                path.push(*previous);
+                current = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // already reached this cell a cheaper way
+        if steps > cost[&cell] {
+            continue;
+        }
+
+        for next in topology.neighbours(cell.0, cell.1, width, height) {
+            if blocked(next.0, next.1) {
+                continue;
+            }
+            let next_steps = steps + 1;
+            if cost.get(&next).map_or(true, |c| next_steps < *c) {
+                cost.insert(next, next_steps);
+                came_from.insert(next, cell);
+                open.push(Reverse((
+                    next_steps + topology.distance(next, to),
+                    next_steps,
+                    next,
+                )));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_centers_round_trip() {
+        for shape in [GridShape::Square, GridShape::Hex].iter() {
+            let topology = shape.topology();
+            for y in 0..7 {
+                for x in 0..7 {
+                    let center = topology.cell_center(x, y, 10.0);
+                    assert_eq!(topology.cell_at(center, 10.0), (x as i64, y as i64));
+                    // near the edge but still inside
+                    let edge = center + Vec2::new(4.0, 0.0);
+                    assert_eq!(topology.cell_at(edge, 10.0), (x as i64, y as i64));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbour_counts() {
+        let square = GridShape::Square.topology();
+        assert_eq!(square.neighbours(2, 2, 5, 5).len(), 8);
+        assert_eq!(square.neighbours(0, 0, 5, 5).len(), 3);
+
+        let hex = GridShape::Hex.topology();
+        assert_eq!(hex.neighbours(2, 2, 5, 5).len(), 6);
+        assert_eq!(hex.neighbours(2, 3, 5, 5).len(), 6);
+        // every neighbour is one step away and lists us back
+        for (x, y) in hex.neighbours(2, 3, 5, 5) {
+            assert_eq!(hex.distance((2, 3), (x, y)), 1);
+            assert!(hex.neighbours(x, y, 5, 5).contains(&(2, 3)));
+        }
+    }
+
+    #[test]
+    fn neighbours_are_next_to_each_other() {
+        let hex = GridShape::Hex.topology();
+        let center = hex.cell_center(3, 3, 10.0);
+        for (x, y) in hex.neighbours(3, 3, 7, 7) {
+            let distance = hex.cell_center(x, y, 10.0).distance(center);
+            assert!((distance - 10.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn paths_go_around_walls() {
+        // wall down the middle with a gap at the top
+        let blocked = |x: u32, y: u32| x == 2 && y < 4;
+        for shape in [GridShape::Square, GridShape::Hex].iter() {
+            let path = find_path(shape.topology(), 5, 5, (0, 0), (4, 0), blocked).unwrap();
+            assert_eq!(path.first(), Some(&(0, 0)));
+            assert_eq!(path.last(), Some(&(4, 0)));
+            assert!(path.iter().all(|(x, y)| !blocked(*x, *y)));
+            for step in path.windows(2) {
+                assert_eq!(shape.topology().distance(step[0], step[1]), 1);
+            }
+        }
+        assert!(find_path(GridShape::Hex.topology(), 5, 5, (0, 0), (2, 0), blocked).is_none());
+    }
+}
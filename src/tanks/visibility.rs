@@ -0,0 +1,327 @@
+use bevy::prelude::*;
+use bevy_mod_picking::PickableBundle;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::grid::{line, GridSpace},
+    GameState,
+};
+
+use super::{
+    map::MapLayout,
+    split_screen::PlayerCamera,
+    streaming::CellStore,
+    tank::{Tank, Team},
+};
+
+pub struct VisibilityPlugin {
+    state: GameState,
+}
+
+impl VisibilityPlugin {
+    pub fn new(state: GameState) -> Self {
+        VisibilityPlugin { state: state }
+    }
+}
+
+impl Plugin for VisibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(FogOfWar::default())
+            .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(reset_fog.system()))
+            .add_system_set(
+                SystemSet::on_update(self.state.clone())
+                    .with_system(update_fog.system().label("fog"))
+                    .with_system(apply_fog_to_tanks.system().after("fog")),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellVisibility {
+    /// Never been seen
+    Unexplored,
+    /// Seen before, but nothing friendly can see it right now
+    Explored,
+    /// In sight of a friendly unit
+    Visible,
+}
+
+impl Default for CellVisibility {
+    fn default() -> Self {
+        CellVisibility::Unexplored
+    }
+}
+
+/// How far a unit can see, in cells
+#[derive(Debug)]
+pub struct Sight {
+    pub radius: u32,
+}
+
+/// Visibility of each map cell from the friendly team's point of view
+///
+/// This only knows about cells, not entities, so anything that needs to ask
+/// "can we see this" (ai, minimap, picking) can use it without touching the world
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FogOfWar {
+    width: u32,
+    height: u32,
+    cells: Vec<CellVisibility>,
+    blocked: Vec<bool>,
+    /// Cell and sight radius of everything revealed last time, None until the fog has caught up
+    /// with the map, so nothing is recomputed while nothing moves
+    viewers: Option<Vec<((u32, u32), u32)>>,
+}
+
+impl FogOfWar {
+    pub fn new(width: u32, height: u32) -> Self {
+        let count = (width * height) as usize;
+        FogOfWar {
+            width,
+            height,
+            cells: vec![CellVisibility::Unexplored; count],
+            blocked: vec![false; count],
+            viewers: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        GridSpace::new(self.width, self.height, 1.0).index(x, y)
+    }
+
+    /// Visibility of a cell, anything off the map is unexplored
+    pub fn get(&self, x: u32, y: u32) -> CellVisibility {
+        self.index(x as i64, y as i64)
+            .map(|i| self.cells[i])
+            .unwrap_or_default()
+    }
+
+    pub fn is_visible(&self, x: u32, y: u32) -> bool {
+        self.get(x, y) == CellVisibility::Visible
+    }
+
+    pub fn is_explored(&self, x: u32, y: u32) -> bool {
+        self.get(x, y) != CellVisibility::Unexplored
+    }
+
+    pub fn is_blocked(&self, x: u32, y: u32) -> bool {
+        self.index(x as i64, y as i64)
+            .map(|i| self.blocked[i])
+            .unwrap_or(true)
+    }
+
+    /// Mark a cell as seen before, used when restoring a saved game
+    pub fn explore(&mut self, x: u32, y: u32) {
+        if let Some(i) = self.index(x as i64, y as i64) {
+            if self.cells[i] == CellVisibility::Unexplored {
+                self.cells[i] = CellVisibility::Explored;
+            }
+        }
+    }
+
+    pub fn set_blocked(&mut self, x: u32, y: u32, blocked: bool) {
+        if let Some(i) = self.index(x as i64, y as i64) {
+            self.blocked[i] = blocked;
+        }
+    }
+
+    /// Everything currently visible becomes explored, call before revealing for the new frame
+    pub fn hide_all(&mut self) {
+        for cell in self.cells.iter_mut() {
+            if *cell == CellVisibility::Visible {
+                *cell = CellVisibility::Explored;
+            }
+        }
+    }
+
+    /// Mark every cell within radius of the origin that has a clear line of sight as visible
+    pub fn reveal(&mut self, origin: (u32, u32), radius: u32) {
+        let (ox, oy) = (origin.0 as i64, origin.1 as i64);
+        let r = radius as i64;
+        for y in (oy - r)..=(oy + r) {
+            for x in (ox - r)..=(ox + r) {
+                let (dx, dy) = (x - ox, y - oy);
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                if let Some(i) = self.index(x, y) {
+                    if self.line_of_sight((ox, oy), (x, y)) {
+                        self.cells[i] = CellVisibility::Visible;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walks the cells between two points, blocked cells stop sight but are themselves seen
+    pub fn line_of_sight(&self, from: (i64, i64), to: (i64, i64)) -> bool {
+        line(from, to)
+            .filter(|cell| *cell != from && *cell != to)
+            .all(|(x, y)| matches!(self.index(x, y), Some(i) if !self.blocked[i]))
+    }
+}
+
+fn reset_fog(mut fog: ResMut<FogOfWar>, layout: Res<MapLayout>) {
+    *fog = FogOfWar::new(layout.width, layout.height);
+}
+
+fn update_fog(
+    mut fog: ResMut<FogOfWar>,
+    layout: Res<MapLayout>,
+    mut store: ResMut<CellStore>,
+    units: Query<(&Transform, &Sight, &Tank)>,
+) {
+    let mut viewers: Vec<((u32, u32), u32)> = units
+        .iter()
+        .filter(|(_, _, tank)| tank.team == Team::Friendly)
+        .filter_map(|(transform, sight, _)| {
+            layout
+                .world_to_cell(transform.translation)
+                .map(|cell| (cell, sight.radius))
+        })
+        .collect();
+    viewers.sort_unstable();
+
+    // only touch the fog when something changed, repainting the map keys off it
+    let fresh = fog.viewers.is_none();
+    if !fresh && !store.has_blocked_changes() && fog.viewers.as_ref() == Some(&viewers) {
+        return;
+    }
+
+    // taking changes marks the store as changed, so leave it alone when there are none
+    let changes = if store.has_blocked_changes() {
+        store.take_blocked_changes()
+    } else {
+        Vec::new()
+    };
+    if fresh {
+        // the store knows about every cell, not just the ones in loaded chunks
+        for y in 0..fog.height() {
+            for x in 0..fog.width() {
+                fog.set_blocked(x, y, store.is_blocked(x, y));
+            }
+        }
+    } else {
+        for (x, y) in changes {
+            fog.set_blocked(x, y, store.is_blocked(x, y));
+        }
+    }
+
+    fog.hide_all();
+    for (cell, radius) in viewers.iter() {
+        fog.reveal(*cell, *radius);
+    }
+    fog.viewers = Some(viewers);
+}
+
+/// Enemy tanks are only drawn and pickable while they stand in a visible cell. Split screen
+/// has no mesh picking at all, so there the fog only decides what is drawn
+fn apply_fog_to_tanks(
+    mut commands: Commands,
+    fog: Res<FogOfWar>,
+    layout: Res<MapLayout>,
+    split: Query<(), With<PlayerCamera>>,
+    mut query: Query<(Entity, &Tank, &Transform, &mut Visible, Option<&Interaction>)>,
+) {
+    let picking = split.iter().next().is_none();
+    for (entity, tank, transform, mut visible, interaction) in query.iter_mut() {
+        let seen = tank.team == Team::Friendly
+            || layout
+                .world_to_cell(transform.translation)
+                .map(|(x, y)| fog.is_visible(x, y))
+                .unwrap_or(false);
+
+        if visible.is_visible != seen {
+            visible.is_visible = seen;
+        }
+
+        if !picking {
+            continue;
+        }
+        let pickable = interaction.is_some();
+        if !seen && pickable {
+            commands.entity(entity).remove_bundle::<PickableBundle>();
+        } else if seen && !pickable {
+            commands.entity(entity).insert_bundle(PickableBundle::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walled() -> FogOfWar {
+        // a wall down the middle column, with a gap at the top
+        let mut fog = FogOfWar::new(5, 5);
+        for y in 0..4 {
+            fog.set_blocked(2, y, true);
+        }
+        fog
+    }
+
+    #[test]
+    fn walls_block_sight_but_are_seen() {
+        let fog = walled();
+        assert!(fog.line_of_sight((0, 0), (1, 3)));
+        assert!(!fog.line_of_sight((0, 1), (4, 1)));
+        // the wall itself can be seen, just not past it
+        assert!(fog.line_of_sight((0, 1), (2, 1)));
+        // through the gap
+        assert!(fog.line_of_sight((0, 4), (4, 4)));
+    }
+
+    #[test]
+    fn sight_stops_at_the_map_edge() {
+        let fog = FogOfWar::new(3, 3);
+        assert!(fog.line_of_sight((0, 0), (2, 2)));
+        // anything off the map blocks
+        assert!(!fog.line_of_sight((-2, 0), (2, 0)));
+        assert!(fog.is_blocked(3, 0));
+    }
+
+    #[test]
+    fn reveal_is_limited_by_radius_walls_and_edges() {
+        let mut fog = walled();
+        fog.reveal((0, 1), 3);
+        assert!(fog.is_visible(0, 1));
+        assert!(fog.is_visible(1, 2));
+        assert!(fog.is_visible(0, 4));
+        // the wall, but not what is behind it
+        assert!(fog.is_visible(2, 1));
+        assert!(!fog.is_visible(3, 1));
+        // out of range
+        assert!(!fog.is_visible(3, 3));
+
+        // standing in a corner only reveals what is on the map
+        let mut fog = FogOfWar::new(3, 3);
+        fog.reveal((0, 0), 1);
+        let visible = (0..3)
+            .flat_map(|y| (0..3).map(move |x| (x, y)))
+            .filter(|(x, y)| fog.is_visible(*x, *y))
+            .count();
+        assert_eq!(visible, 3);
+    }
+
+    #[test]
+    fn hidden_cells_stay_explored() {
+        let mut fog = FogOfWar::new(5, 5);
+        fog.reveal((0, 0), 1);
+        fog.hide_all();
+        assert_eq!(fog.get(0, 0), CellVisibility::Explored);
+        assert_eq!(fog.get(1, 0), CellVisibility::Explored);
+        assert_eq!(fog.get(4, 4), CellVisibility::Unexplored);
+
+        fog.reveal((4, 4), 0);
+        assert_eq!(fog.get(4, 4), CellVisibility::Visible);
+        assert_eq!(fog.get(0, 0), CellVisibility::Explored);
+    }
+}
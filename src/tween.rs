@@ -0,0 +1,419 @@
+use std::f32::consts::PI;
+
+use bevy::{asset::Asset, ecs::component::Component, prelude::*};
+
+/// Eased animations for transforms and material colors. Insert a `Tween` with a lens saying
+/// what to animate, once it is done it removes itself and sends a `TweenCompleted`. A tween
+/// can also be ticked by hand for things that aren't entities, like egui colors
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<TweenCompleted>()
+            .add_system(
+                animate_component::<Transform, TransformLens>
+                    .system()
+                    .label("tween"),
+            )
+            .add_system(
+                animate_component::<Transform, TranslationLens>
+                    .system()
+                    .label("tween"),
+            )
+            .add_system(
+                animate_component::<Transform, RotationLens>
+                    .system()
+                    .label("tween"),
+            )
+            .add_system(
+                animate_component::<Transform, ScaleLens>
+                    .system()
+                    .label("tween"),
+            )
+            .add_system(
+                animate_asset::<ColorMaterial, ColorLens>
+                    .system()
+                    .label("tween"),
+            )
+            .add_system(
+                animate_asset::<StandardMaterial, ColorLens>
+                    .system()
+                    .label("tween"),
+            );
+    }
+}
+
+/// Shape of the curve from start to end, see https://easings.net
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ease {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicOut,
+    SineInOut,
+    /// Overshoots a little before settling, good for things popping in
+    BackOut,
+}
+
+impl Ease {
+    /// Map linear progress between 0 and 1 onto the curve
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Ease::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Ease::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Ease::BackOut => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenMode {
+    /// Play once then remove the tween
+    Once,
+    /// Start over from the beginning forever
+    Repeat,
+    /// Go back and forth forever
+    PingPong,
+}
+
+/// Sets part of a `T` somewhere between two values
+pub trait Lens<T> {
+    fn lerp(&self, target: &mut T, ratio: f32);
+}
+
+/// Whole transform, when combined with the other transform lenses the last system to run wins
+#[derive(Debug, Clone, Copy)]
+pub struct TransformLens {
+    pub from: Transform,
+    pub to: Transform,
+}
+
+impl Lens<Transform> for TransformLens {
+    fn lerp(&self, target: &mut Transform, ratio: f32) {
+        target.translation = self.from.translation.lerp(self.to.translation, ratio);
+        target.rotation = self.from.rotation.slerp(self.to.rotation, ratio);
+        target.scale = self.from.scale.lerp(self.to.scale, ratio);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TranslationLens {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+impl Lens<Transform> for TranslationLens {
+    fn lerp(&self, target: &mut Transform, ratio: f32) {
+        target.translation = self.from.lerp(self.to, ratio);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RotationLens {
+    pub from: Quat,
+    pub to: Quat,
+}
+
+impl Lens<Transform> for RotationLens {
+    fn lerp(&self, target: &mut Transform, ratio: f32) {
+        target.rotation = self.from.slerp(self.to, ratio);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScaleLens {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+impl Lens<Transform> for ScaleLens {
+    fn lerp(&self, target: &mut Transform, ratio: f32) {
+        target.scale = self.from.lerp(self.to, ratio);
+    }
+}
+
+/// Works on plain colors and on material handles, materials are assets so every entity
+/// sharing the material changes with it
+#[derive(Debug, Clone, Copy)]
+pub struct ColorLens {
+    pub from: Color,
+    pub to: Color,
+}
+
+impl Lens<Color> for ColorLens {
+    fn lerp(&self, target: &mut Color, ratio: f32) {
+        let from = self.from.as_rgba_f32();
+        let to = self.to.as_rgba_f32();
+        let mix = |i: usize| from[i] + (to[i] - from[i]) * ratio;
+        *target = Color::rgba(mix(0), mix(1), mix(2), mix(3));
+    }
+}
+
+impl Lens<ColorMaterial> for ColorLens {
+    fn lerp(&self, target: &mut ColorMaterial, ratio: f32) {
+        self.lerp(&mut target.color, ratio);
+    }
+}
+
+impl Lens<StandardMaterial> for ColorLens {
+    fn lerp(&self, target: &mut StandardMaterial, ratio: f32) {
+        self.lerp(&mut target.base_color, ratio);
+    }
+}
+
+/// Animates what the lens points at over `duration` seconds
+#[derive(Debug, Clone)]
+pub struct Tween<L> {
+    pub lens: L,
+    pub ease: Ease,
+    pub mode: TweenMode,
+    /// Sent back with `TweenCompleted`, so systems can tell their tweens apart
+    pub tag: u32,
+    duration: f32,
+    delay: f32,
+    elapsed: f32,
+    forward: bool,
+}
+
+impl<L> Tween<L> {
+    pub fn new(lens: L, ease: Ease, duration: f32) -> Self {
+        Tween {
+            lens,
+            ease,
+            mode: TweenMode::Once,
+            tag: 0,
+            duration,
+            delay: 0.0,
+            elapsed: 0.0,
+            forward: true,
+        }
+    }
+
+    pub fn with_mode(self, mode: TweenMode) -> Self {
+        Tween { mode, ..self }
+    }
+
+    /// Seconds to hold the start value before moving
+    pub fn with_delay(self, delay: f32) -> Self {
+        Tween { delay, ..self }
+    }
+
+    pub fn with_tag(self, tag: u32) -> Self {
+        Tween { tag, ..self }
+    }
+
+    /// Move the tween on, returns true when it reaches the end, for repeating tweens that is
+    /// the end of every pass
+    pub fn tick(&mut self, delta: f32) -> bool {
+        if self.finished() {
+            return false;
+        }
+
+        let mut delta = delta;
+        if self.delay > 0.0 {
+            let waited = delta.min(self.delay);
+            self.delay -= waited;
+            delta -= waited;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return false;
+        }
+        match self.mode {
+            TweenMode::Once => self.elapsed = self.duration,
+            TweenMode::Repeat => self.elapsed = wrap(self.elapsed, self.duration),
+            TweenMode::PingPong => {
+                self.elapsed = wrap(self.elapsed, self.duration);
+                self.forward = !self.forward;
+            }
+        }
+        true
+    }
+
+    /// Eased position between the start and end values
+    pub fn progress(&self) -> f32 {
+        let linear = if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        };
+        self.ease
+            .apply(if self.forward { linear } else { 1.0 - linear })
+    }
+
+    /// Only tweens that play once finish
+    pub fn finished(&self) -> bool {
+        self.mode == TweenMode::Once && self.elapsed >= self.duration
+    }
+
+    pub fn apply<T>(&self, target: &mut T)
+    where
+        L: Lens<T>,
+    {
+        self.lens.lerp(target, self.progress());
+    }
+}
+
+fn wrap(elapsed: f32, duration: f32) -> f32 {
+    if duration > 0.0 {
+        elapsed % duration
+    } else {
+        0.0
+    }
+}
+
+/// A tween reached its end
+#[derive(Debug, Clone, Copy)]
+pub struct TweenCompleted {
+    pub entity: Entity,
+    pub tag: u32,
+}
+
+fn animate_component<T: Component, L: Lens<T> + Component>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Tween<L>, &mut T)>,
+    mut ev_completed: EventWriter<TweenCompleted>,
+) {
+    for (entity, mut tween, mut target) in query.iter_mut() {
+        let completed = tween.tick(time.delta_seconds());
+        tween.apply(&mut *target);
+        if completed {
+            ev_completed.send(TweenCompleted {
+                entity,
+                tag: tween.tag,
+            });
+        }
+        if tween.finished() {
+            commands.entity(entity).remove::<Tween<L>>();
+        }
+    }
+}
+
+fn animate_asset<T: Asset, L: Lens<T> + Component>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut assets: ResMut<Assets<T>>,
+    mut query: Query<(Entity, &mut Tween<L>, &Handle<T>)>,
+    mut ev_completed: EventWriter<TweenCompleted>,
+) {
+    for (entity, mut tween, handle) in query.iter_mut() {
+        let completed = tween.tick(time.delta_seconds());
+        if let Some(asset) = assets.get_mut(handle) {
+            tween.apply(asset);
+        }
+        if completed {
+            ev_completed.send(TweenCompleted {
+                entity,
+                tag: tween.tag,
+            });
+        }
+        if tween.finished() {
+            commands.entity(entity).remove::<Tween<L>>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(from: f32, to: f32) -> TranslationLens {
+        TranslationLens {
+            from: Vec3::splat(from),
+            to: Vec3::splat(to),
+        }
+    }
+
+    #[test]
+    fn eases_start_and_end_in_place() {
+        for ease in [
+            Ease::Linear,
+            Ease::QuadIn,
+            Ease::QuadOut,
+            Ease::QuadInOut,
+            Ease::CubicOut,
+            Ease::SineInOut,
+            Ease::BackOut,
+        ]
+        .iter()
+        {
+            assert!(ease.apply(0.0).abs() < 1e-5, "{:?}", ease);
+            assert!((ease.apply(1.0) - 1.0).abs() < 1e-5, "{:?}", ease);
+        }
+        assert!(Ease::BackOut.apply(0.7) > 1.0);
+        assert!(Ease::QuadIn.apply(0.5) < 0.5);
+        assert!(Ease::QuadOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn plays_once() {
+        let mut tween = Tween::new(value(0.0, 10.0), Ease::Linear, 1.0);
+        let mut transform = Transform::default();
+        assert!(!tween.tick(0.5));
+        tween.apply(&mut transform);
+        assert_eq!(transform.translation, Vec3::splat(5.0));
+
+        assert!(tween.tick(0.75));
+        assert!(tween.finished());
+        tween.apply(&mut transform);
+        assert_eq!(transform.translation, Vec3::splat(10.0));
+        // only reports finishing once
+        assert!(!tween.tick(1.0));
+    }
+
+    #[test]
+    fn waits_out_the_delay() {
+        let mut tween = Tween::new(value(0.0, 1.0), Ease::Linear, 1.0).with_delay(0.5);
+        tween.tick(0.4);
+        assert_eq!(tween.progress(), 0.0);
+        tween.tick(0.6);
+        assert!((tween.progress() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ping_pong_turns_around() {
+        let mut tween =
+            Tween::new(value(0.0, 1.0), Ease::Linear, 1.0).with_mode(TweenMode::PingPong);
+        assert!(tween.tick(1.25));
+        assert!((tween.progress() - 0.75).abs() < 1e-5);
+        assert!(tween.tick(1.0));
+        assert!((tween.progress() - 0.25).abs() < 1e-5);
+        assert!(!tween.finished());
+    }
+
+    #[test]
+    fn repeat_starts_over() {
+        let mut tween = Tween::new(value(0.0, 1.0), Ease::Linear, 2.0).with_mode(TweenMode::Repeat);
+        assert!(tween.tick(2.5));
+        assert!((tween.progress() - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn colors_blend() {
+        let lens = ColorLens {
+            from: Color::rgba(0.0, 0.0, 0.0, 1.0),
+            to: Color::rgba(1.0, 0.5, 0.0, 0.0),
+        };
+        let mut color = Color::WHITE;
+        lens.lerp(&mut color, 0.5);
+        assert_eq!(color.as_rgba_f32(), [0.5, 0.25, 0.0, 0.5]);
+    }
+}